@@ -0,0 +1,150 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// The length of the generated inputs, in bytes.
+const LEN: usize = 1 << 20;
+
+/// Return `len` pseudo-random bytes, the same on every run.
+fn random(len: usize) -> Vec<u8> {
+    let mut state: u32 = 0x2545_f491;
+
+    (0..len)
+        .map(|_| {
+            // xorshift32
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect()
+}
+
+/// Return the inputs the codec is measured on, from its best case to its
+/// worst.
+fn inputs() -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        ("same", vec![0; LEN]),
+        ("png", include_bytes!("data/sample.png").to_vec()),
+        ("alternating", (0..LEN).map(|i| (i % 2) as u8).collect()),
+        ("random", random(LEN)),
+    ]
+}
+
+fn bench_encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+
+    for (name, bytes) in inputs() {
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &bytes, |b, bytes| {
+            b.iter(|| rle_rs::encode(black_box(bytes)))
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+
+    for (name, bytes) in inputs() {
+        let encoded = rle_rs::encode(&bytes);
+
+        // Throughput is measured on the decoded data, so that it's comparable
+        // with encoding.
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &encoded, |b, encoded| {
+            b.iter(|| rle_rs::decode(black_box(encoded)).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+/// Measure encoding on runs of a few lengths, where finding the end of a run
+/// dominates.
+fn bench_long_runs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("long runs");
+    group.throughput(Throughput::Bytes(LEN as u64));
+
+    for run in [16, 255, 4096, LEN] {
+        let bytes: Vec<u8> = (0..LEN).map(|i| (i / run) as u8).collect();
+
+        group.bench_with_input(BenchmarkId::from_parameter(run), &bytes, |b, bytes| {
+            b.iter(|| rle_rs::encode(black_box(bytes)))
+        });
+    }
+
+    group.finish();
+}
+
+/// Compare the byte scheme with and without the move-to-front transform on
+/// English text.
+fn bench_mtf(c: &mut Criterion) {
+    let sentence: &[u8] = b"It was the best of times, it was the worst of times, it was the age of wisdom. ";
+    let text: Vec<u8> = sentence.iter().copied().cycle().take(LEN).collect();
+    let mut group = c.benchmark_group("mtf");
+    group.throughput(Throughput::Bytes(LEN as u64));
+
+    group.bench_function("encode", |b| b.iter(|| rle_rs::encode(black_box(&text))));
+    group.bench_function("encode_mtf_rle", |b| b.iter(|| rle_rs::encode_mtf_rle(black_box(&text))));
+
+    let encoded = rle_rs::encode_mtf_rle(&text);
+    group.bench_function("decode_rle_mtf", |b| {
+        b.iter(|| rle_rs::decode_rle_mtf(black_box(&encoded)).unwrap())
+    });
+
+    group.finish();
+}
+
+/// Compare the allocating API with the one reusing buffers, on many small
+/// messages such as network packets.
+fn bench_small_messages(c: &mut Criterion) {
+    let data = random(64 * 1024);
+    // Short runs in every message, so that there's something to decode.
+    let messages: Vec<Vec<u8>> = data.chunks(64).map(|m| m.iter().map(|b| b & 0x81).collect()).collect();
+    let encoded: Vec<Vec<u8>> = messages.iter().map(|m| rle_rs::encode(m)).collect();
+    let mut group = c.benchmark_group("small messages");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+
+    group.bench_function("encode", |b| {
+        b.iter(|| {
+            for message in &messages {
+                black_box(rle_rs::encode(black_box(message)));
+            }
+        })
+    });
+
+    group.bench_function("encode_into", |b| {
+        let mut out = Vec::new();
+
+        b.iter(|| {
+            for message in &messages {
+                rle_rs::encode_into(black_box(message), &mut out);
+                black_box(&out);
+            }
+        })
+    });
+
+    group.bench_function("decode", |b| {
+        b.iter(|| {
+            for message in &encoded {
+                black_box(rle_rs::decode(black_box(message)).unwrap());
+            }
+        })
+    });
+
+    group.bench_function("decode_into", |b| {
+        let mut out = Vec::new();
+
+        b.iter(|| {
+            for message in &encoded {
+                rle_rs::decode_into(black_box(message), &mut out).unwrap();
+                black_box(&out);
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_encode, bench_decode, bench_long_runs, bench_mtf, bench_small_messages);
+criterion_main!(benches);
@@ -0,0 +1,20 @@
+fn main() {
+    #[cfg(feature = "ffi")]
+    generate_header();
+}
+
+/// Write the C header for the bindings in `src/ffi.rs` to `include/rle.h`.
+#[cfg(feature = "ffi")]
+fn generate_header() {
+    let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    cbindgen::Builder::new()
+        .with_src(format!("{}/src/ffi.rs", dir))
+        .with_config(cbindgen::Config::from_file(format!("{}/cbindgen.toml", dir)).unwrap())
+        .generate()
+        .expect("failed to generate the C header")
+        .write_to_file(format!("{}/include/rle.h", dir));
+}
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Decoding arbitrary bytes may fail, but must never panic.
+fuzz_target!(|data: &[u8]| {
+    let _ = rle_rs::decode(data);
+});
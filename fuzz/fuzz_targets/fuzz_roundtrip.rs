@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let encoded = rle_rs::encode(data);
+
+    assert_eq!(rle_rs::decode(&encoded).as_deref(), Ok(data));
+});
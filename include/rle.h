@@ -0,0 +1,63 @@
+#ifndef RLE_H
+#define RLE_H
+
+/* Generated by cbindgen from src/ffi.rs; don't edit by hand. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * The operation succeeded.
+ */
+#define RLE_OK 0
+
+/**
+ * The input isn't valid run-length encoded data.
+ */
+#define RLE_ERR_DECODE -1
+
+/**
+ * The output buffer is too small; `*output_len` is set to the size needed.
+ */
+#define RLE_ERR_BUFFER_TOO_SMALL -2
+
+/**
+ * A pointer that may not be null was.
+ */
+#define RLE_ERR_NULL_POINTER -3
+
+/**
+ * Run-length encode `input_len` bytes from `input` into `output`.
+ *
+ * On success, and when the output buffer is too small, `*output_len` is set
+ * to the length of the encoding.
+ *
+ * # Safety
+ *
+ * `input` must point to `input_len` readable bytes (or may be null if
+ * `input_len` is 0), `output` to `output_max` writable bytes (or may be null
+ * if `output_max` is 0), and `output_len` to a writable `size_t`.
+ */
+int32_t rle_encode(const uint8_t *input,
+                   size_t input_len,
+                   uint8_t *output,
+                   size_t output_max,
+                   size_t *output_len);
+
+/**
+ * Decode `input_len` run-length encoded bytes from `input` into `output`.
+ *
+ * On success, and when the output buffer is too small, `*output_len` is set
+ * to the length of the decoded data.
+ *
+ * # Safety
+ *
+ * The same as for [`rle_encode`].
+ */
+int32_t rle_decode(const uint8_t *input,
+                   size_t input_len,
+                   uint8_t *output,
+                   size_t output_max,
+                   size_t *output_len);
+
+#endif  /* RLE_H */
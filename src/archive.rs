@@ -0,0 +1,235 @@
+//! Archives: several files bundled into one, each encoded on its own behind
+//! a table naming them, so that any one can be extracted without decoding
+//! the others.
+//!
+//! | Bytes | Contents                                                 |
+//! |-------|----------------------------------------------------------|
+//! | 0..4  | The magic number, `b"RLEA"`                              |
+//! | 4     | The archive format version, currently `1`                |
+//! | 5..9  | The number of files, as a little-endian `u32`            |
+//! | 9..   | The table: for each file, the length of its name (1      |
+//! |       | byte), the name in UTF-8, and the offset of its encoding |
+//! |       | from the start of the archive and its length, as         |
+//! |       | little-endian `u64`s                                     |
+//! | ..    | The encodings, each an encoded file as written by        |
+//! |       | [`encode_auto`](crate::encode_auto)                      |
+
+#[cfg(feature = "std")]
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+#[cfg(feature = "std")]
+use crate::{DecodeError, RleError};
+
+/// The bytes every archive starts with.
+const MAGIC: [u8; 4] = *b"RLEA";
+
+/// The version of the archive format written by this library.
+const VERSION: u8 = 1;
+
+/// The length of the start of an archive, before the table.
+const HEADER_LEN: usize = 9;
+
+/// The length of an entry of the table, but for the name.
+const ENTRY_LEN: usize = 17;
+
+/// A file in the table of an archive.
+///
+/// * `name` - The name of the file.
+/// * `encoding` - Where its encoding is in the archive.
+#[cfg(feature = "std")]
+struct Entry<'a> {
+    name: &'a str,
+    encoding: core::ops::Range<usize>,
+}
+
+/// Encode each of `files` and return them bundled into an archive, in the
+/// same order.
+///
+/// * `files` - The name and the contents of each file.
+///
+/// # Panics
+///
+/// Panics if a name is longer than 255 bytes, or there are more than
+/// `u32::MAX` files.
+///
+/// # Examples
+///
+/// ```
+/// let archive = rle_rs::create_archive(&[("a.txt", b"aaab"), ("b.txt", b"bbbbbb")]);
+///
+/// assert_eq!(rle_rs::list_archive(&archive).unwrap(), ["a.txt", "b.txt"]);
+/// assert_eq!(rle_rs::extract_file(&archive, "b.txt").unwrap(), b"bbbbbb");
+/// ```
+pub fn create_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let encodings: Vec<Vec<u8>> = files.iter().map(|(_, bytes)| crate::encode_auto(bytes)).collect();
+    let count: u32 = files.len().try_into().expect("an archive holds at most u32::MAX files");
+
+    let mut archive = MAGIC.to_vec();
+    archive.push(VERSION);
+    archive.extend(&count.to_le_bytes());
+
+    // The encodings start right after the table.
+    let mut offset = HEADER_LEN + files.iter().map(|(name, _)| ENTRY_LEN + name.len()).sum::<usize>();
+
+    for ((name, _), encoding) in files.iter().zip(&encodings) {
+        let len: u8 = name.len().try_into().expect("a file name in an archive is at most 255 bytes long");
+        archive.push(len);
+        archive.extend(name.as_bytes());
+        archive.extend(&(offset as u64).to_le_bytes());
+        archive.extend(&(encoding.len() as u64).to_le_bytes());
+        offset += encoding.len();
+    }
+
+    for encoding in encodings {
+        archive.extend(encoding);
+    }
+
+    archive
+}
+
+/// Return the names of the files in an archive, in the order they were
+/// bundled in.
+///
+/// * `bytes` - The archive, as returned by [`create_archive`].
+///
+/// # Errors
+///
+/// Returns [`RleError::Decode`] with [`DecodeError::InvalidMagic`] if
+/// `bytes` isn't an archive, [`DecodeError::UnsupportedVersion`] if it's one
+/// this library can't read, and [`DecodeError::InvalidArchive`] if its table
+/// is malformed.
+#[cfg(feature = "std")]
+pub fn list_archive(bytes: &[u8]) -> Result<Vec<String>, RleError> {
+    Ok(entries(bytes)?.into_iter().map(|entry| String::from(entry.name)).collect())
+}
+
+/// Decode the file named `name` in an archive and return its contents.
+///
+/// If several files have that name, the first is extracted.
+///
+/// * `bytes` - The archive, as returned by [`create_archive`].
+/// * `name` - The name of the file to extract.
+///
+/// # Errors
+///
+/// Returns [`RleError::Decode`] with [`DecodeError::FileNotInArchive`] if no
+/// file has that name, any error of [`list_archive`], or any error of
+/// [`decode_auto`](crate::decode_auto) if the file's encoding is corrupt.
+#[cfg(feature = "std")]
+pub fn extract_file(bytes: &[u8], name: &str) -> Result<Vec<u8>, RleError> {
+    let entry = entries(bytes)?
+        .into_iter()
+        .find(|entry| entry.name == name)
+        .ok_or_else(|| DecodeError::FileNotInArchive(String::from(name)))?;
+
+    Ok(crate::decode_auto(&bytes[entry.encoding])?)
+}
+
+/// Read the table of an archive.
+///
+/// * `bytes` - The archive.
+#[cfg(feature = "std")]
+fn entries(bytes: &[u8]) -> Result<Vec<Entry<'_>>, DecodeError> {
+    if !bytes.starts_with(&MAGIC) {
+        return Err(DecodeError::InvalidMagic);
+    }
+
+    if bytes.len() < HEADER_LEN {
+        return Err(DecodeError::InvalidArchive { reason: "the archive is truncated" });
+    }
+
+    if bytes[4] != VERSION {
+        return Err(DecodeError::UnsupportedVersion(bytes[4]));
+    }
+
+    let count = u32::from_le_bytes(bytes[5..HEADER_LEN].try_into().unwrap());
+    let mut at = HEADER_LEN;
+    // The count isn't trusted to reserve room with, as the table may be
+    // much shorter than it says.
+    let mut entries = Vec::new();
+
+    for _ in 0..count {
+        let len = usize::from(*bytes.get(at).ok_or(DecodeError::InvalidArchive { reason: "the table is truncated" })?);
+        let entry = bytes
+            .get(at + 1..at + 1 + len + ENTRY_LEN - 1)
+            .ok_or(DecodeError::InvalidArchive { reason: "the table is truncated" })?;
+        let (name, location) = entry.split_at(len);
+        let name = core::str::from_utf8(name).map_err(|_| DecodeError::InvalidArchive { reason: "a file name isn't UTF-8" })?;
+        let offset = u64::from_le_bytes(location[..8].try_into().unwrap());
+        let size = u64::from_le_bytes(location[8..].try_into().unwrap());
+
+        let encoding = match offset.checked_add(size) {
+            Some(end) if end <= bytes.len() as u64 => offset as usize..end as usize,
+            _ => return Err(DecodeError::InvalidArchive { reason: "a file lies past the end of the archive" }),
+        };
+
+        entries.push(Entry { name, encoding });
+        at += len + ENTRY_LEN;
+    }
+
+    Ok(entries)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_several_files() {
+        let large: Vec<u8> = (0..10_000).map(|i| (i / 100) as u8).collect();
+        let files: [(&str, &[u8]); 4] = [("runs", &[7; 300]), ("empty", &[]), ("héllo.txt", b"hello"), ("large", &large)];
+        let archive = create_archive(&files);
+
+        assert_eq!(list_archive(&archive).unwrap(), ["runs", "empty", "héllo.txt", "large"]);
+
+        for (name, bytes) in &files {
+            assert_eq!(extract_file(&archive, name).unwrap(), *bytes);
+        }
+
+        assert_eq!(list_archive(&create_archive(&[])).unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn files_are_only_decoded_when_extracted() {
+        let mut archive = create_archive(&[("a", b"aaaa"), ("b", b"bbbb")]);
+        // The last byte of the trailer of b.
+        *archive.last_mut().unwrap() ^= 0xff;
+
+        assert_eq!(extract_file(&archive, "a").unwrap(), b"aaaa");
+        assert!(matches!(extract_file(&archive, "b"), Err(RleError::Decode(DecodeError::LengthMismatch { .. }))));
+    }
+
+    #[test]
+    fn extract_missing_file() {
+        let archive = create_archive(&[("a", b"aaaa")]);
+
+        assert!(matches!(
+            extract_file(&archive, "b"),
+            Err(RleError::Decode(DecodeError::FileNotInArchive(name))) if name == "b"
+        ));
+    }
+
+    #[test]
+    fn malformed_archives() {
+        let archive = create_archive(&[("a", b"aaaa")]);
+        let invalid = |bytes: &[u8]| match list_archive(bytes) {
+            Err(RleError::Decode(e)) => e,
+            other => panic!("{:?}", other),
+        };
+
+        assert_eq!(invalid(&crate::encode_auto(b"aaaa")), DecodeError::InvalidMagic);
+        assert_eq!(invalid(&archive[..7]), DecodeError::InvalidArchive { reason: "the archive is truncated" });
+        assert_eq!(invalid(&archive[..20]), DecodeError::InvalidArchive { reason: "the table is truncated" });
+        assert_eq!(
+            invalid(&archive[..archive.len() - 1]),
+            DecodeError::InvalidArchive { reason: "a file lies past the end of the archive" }
+        );
+
+        let mut newer = archive.clone();
+        newer[4] = 2;
+
+        assert_eq!(invalid(&newer), DecodeError::UnsupportedVersion(2));
+    }
+}
@@ -0,0 +1,456 @@
+//! Base64 transport for encoded data (RFC 4648, with padding), denser than
+//! [`hex`](crate::hex) for pasting it into text documents.
+//!
+//! Like hex, this changes nothing but how the bytes are written, so it can
+//! wrap any of the encoding schemes, header and trailer included.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
+
+use crate::DecodeError;
+
+/// The symbols standing for each 6-bit value.
+const SYMBOLS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The size of the chunks [`Base64Reader`] reads its input in.
+#[cfg(feature = "std")]
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Return `bytes` in base64, without line breaks.
+///
+/// * `bytes` - The bytes to be written.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(rle_rs::base64::encode(b"RLE\x01"), "UkxFAQ==");
+/// ```
+pub fn encode(bytes: &[u8]) -> String {
+    bytes.chunks(3).flat_map(group).map(char::from).collect()
+}
+
+/// Return the bytes written in base64 in `text`, ignoring whitespace.
+///
+/// * `text` - The base64 symbols.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::InvalidBase64`] with the offset of the problem if
+/// `text` has something other than base64 symbols and whitespace, padding
+/// anywhere but at the end, or an incomplete group of symbols.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(rle_rs::base64::decode(b"UkxF\nAQ==\n").unwrap(), b"RLE\x01");
+/// assert!(rle_rs::base64::decode(b"UkxFAQ").is_err());
+/// ```
+pub fn decode(text: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let mut decoding = Vec::with_capacity(text.len() / 4 * 3);
+    let mut decoder = Decoder::default();
+
+    for (at_byte, &c) in text.iter().enumerate() {
+        if let Some((bytes, len)) = decoder.push(c, at_byte)? {
+            decoding.extend(&bytes[..len]);
+        }
+    }
+
+    decoder.finish()?;
+
+    Ok(decoding)
+}
+
+/// A writer that writes everything written to it in base64 to an inner
+/// writer, breaking it into lines if asked to.
+///
+/// Call [`Base64Writer::finish`] when done, to write the last, padded group
+/// of symbols and end the last line.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use rle_rs::base64::Base64Writer;
+///
+/// let mut writer = Base64Writer::with_line_len(Vec::new(), 4);
+/// writer.write_all(b"RLE\x01").unwrap();
+///
+/// assert_eq!(writer.finish().unwrap(), b"UkxF\nAQ==\n");
+/// ```
+#[cfg(feature = "std")]
+pub struct Base64Writer<W: Write> {
+    inner: W,
+    /// The most symbols on a line, if lines are broken at all.
+    line_len: Option<usize>,
+    /// The number of symbols on the current line.
+    column: usize,
+    /// Bytes written that don't make a whole group yet.
+    pending: Vec<u8>,
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Base64Writer<W> {
+    /// Create a new writer writing base64 to `inner` on a single line.
+    ///
+    /// * `inner` - The writer that receives the symbols.
+    pub fn new(inner: W) -> Self {
+        Self { inner, line_len: None, column: 0, pending: Vec::with_capacity(3) }
+    }
+
+    /// Create a new writer writing base64 to `inner`, in lines of at most
+    /// `line_len` symbols.
+    ///
+    /// * `inner` - The writer that receives the symbols.
+    /// * `line_len` - The most symbols on a line, rounded up to a multiple
+    ///   of 4 so that groups aren't split across lines.
+    pub fn with_line_len(inner: W, line_len: usize) -> Self {
+        Self { line_len: Some(line_len.max(1).div_ceil(4) * 4), ..Self::new(inner) }
+    }
+
+    /// Write the last group of symbols, padded, and end the last line, if
+    /// lines are broken and it has anything on it. Return the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let mut text = Vec::new();
+
+        if !self.pending.is_empty() {
+            let pending = core::mem::take(&mut self.pending);
+            self.push_group(&pending, &mut text);
+        }
+
+        if self.line_len.is_some() && self.column > 0 {
+            text.push(b'\n');
+        }
+
+        self.inner.write_all(&text)?;
+
+        Ok(self.inner)
+    }
+
+    /// Append the symbols of `bytes`, at most 3 of them, to `text`.
+    fn push_group(&mut self, bytes: &[u8], text: &mut Vec<u8>) {
+        if self.line_len == Some(self.column) {
+            text.push(b'\n');
+            self.column = 0;
+        }
+
+        text.extend(&group(bytes));
+        self.column += 4;
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Write for Base64Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut text = Vec::with_capacity(buf.len() / 3 * 4 + buf.len() / 57 + 8);
+        let mut rest = buf;
+
+        // Complete the pending group first.
+        if !self.pending.is_empty() {
+            let missing = (3 - self.pending.len()).min(rest.len());
+            self.pending.extend(&rest[..missing]);
+            rest = &rest[missing..];
+
+            if self.pending.len() == 3 {
+                let pending = core::mem::take(&mut self.pending);
+                self.push_group(&pending, &mut text);
+            }
+        }
+
+        let groups = rest.chunks_exact(3);
+        self.pending.extend(groups.remainder());
+
+        for bytes in groups {
+            self.push_group(bytes, &mut text);
+        }
+
+        self.inner.write_all(&text)?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A reader that reads base64 from an inner reader, ignoring whitespace, and
+/// returns the bytes it stands for.
+///
+/// # Errors
+///
+/// Reading fails with [`io::ErrorKind::InvalidData`], wrapping a
+/// [`DecodeError::InvalidBase64`], if the input is malformed as described
+/// for [`decode`].
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Read;
+/// use rle_rs::base64::Base64Reader;
+///
+/// let mut bytes = Vec::new();
+/// Base64Reader::new(&b"UkxF\nAQ==\n"[..]).read_to_end(&mut bytes).unwrap();
+///
+/// assert_eq!(bytes, b"RLE\x01");
+/// ```
+#[cfg(feature = "std")]
+pub struct Base64Reader<R: Read> {
+    inner: R,
+    /// Text read from `inner` but not decoded yet.
+    buf: Box<[u8]>,
+    start: usize,
+    end: usize,
+    /// How much text has been decoded so far.
+    offset: usize,
+    decoder: Decoder,
+    /// Bytes decoded but not returned yet.
+    pending: ([u8; 3], usize, usize),
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Base64Reader<R> {
+    /// Create a new reader reading base64 from `inner`.
+    ///
+    /// * `inner` - The reader the symbols come from.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buf: vec![0; CHUNK_SIZE].into_boxed_slice(),
+            start: 0,
+            end: 0,
+            offset: 0,
+            decoder: Decoder::default(),
+            pending: ([0; 3], 0, 0),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Read for Base64Reader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let mut written = 0;
+
+        while written < out.len() {
+            let (bytes, start, end) = &mut self.pending;
+
+            if start < end {
+                let n = (*end - *start).min(out.len() - written);
+                out[written..written + n].copy_from_slice(&bytes[*start..*start + n]);
+                written += n;
+                *start += n;
+                continue;
+            }
+
+            if self.start == self.end {
+                // Don't block on the inner reader if there is something to
+                // return already.
+                if written > 0 {
+                    break;
+                }
+
+                match self.inner.read(&mut self.buf) {
+                    Ok(0) => {
+                        self.decoder.finish().map_err(invalid_data)?;
+                        break;
+                    }
+                    Ok(n) => {
+                        self.start = 0;
+                        self.end = n;
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+
+                continue;
+            }
+
+            let c = self.buf[self.start];
+            self.start += 1;
+            self.offset += 1;
+
+            if let Some((bytes, len)) = self.decoder.push(c, self.offset - 1).map_err(invalid_data)? {
+                self.pending = (bytes, 0, len);
+            }
+        }
+
+        Ok(written)
+    }
+}
+
+/// The symbols of a group of 4 read so far.
+#[derive(Debug, Default)]
+struct Decoder {
+    values: [u8; 4],
+    len: usize,
+    /// How many of the symbols are padding.
+    padding: usize,
+    /// The offset of the first symbol of the group.
+    at_byte: usize,
+    /// Whether a padded group was read, which must be the last.
+    ended: bool,
+}
+
+impl Decoder {
+    /// Add the symbol `c`, at offset `at_byte` of the text, to the group, and
+    /// return the bytes it stands for, and how many, if it completes it.
+    fn push(&mut self, c: u8, at_byte: usize) -> Result<Option<([u8; 3], usize)>, DecodeError> {
+        let invalid = |reason| Err(DecodeError::InvalidBase64 { at_byte, reason });
+
+        if c.is_ascii_whitespace() {
+            return Ok(None);
+        }
+
+        if self.ended {
+            return invalid("data after the padding");
+        }
+
+        if self.len == 0 {
+            self.at_byte = at_byte;
+        }
+
+        match (c, value(c)) {
+            (b'=', _) if self.len >= 2 => self.padding += 1,
+            (b'=', _) => return invalid("misplaced padding"),
+            (_, Some(_)) if self.padding > 0 => return invalid("misplaced padding"),
+            (_, Some(value)) => self.values[self.len] = value,
+            (_, None) => return invalid("not a base64 symbol"),
+        }
+
+        self.len += 1;
+
+        if self.len < 4 {
+            return Ok(None);
+        }
+
+        let [a, b, c, d] = self.values;
+        let bytes = [a << 2 | b >> 4, b << 4 | c >> 2, c << 6 | d];
+        let len = 3 - self.padding;
+        self.ended = self.padding > 0;
+        self.len = 0;
+
+        Ok(Some((bytes, len)))
+    }
+
+    /// Check that the text didn't end in the middle of a group.
+    fn finish(&self) -> Result<(), DecodeError> {
+        match self.len {
+            0 => Ok(()),
+            _ => Err(DecodeError::InvalidBase64 {
+                at_byte: self.at_byte,
+                reason: "incomplete group of symbols",
+            }),
+        }
+    }
+}
+
+/// Return the 4 symbols of `bytes`, at most 3 of them, padded.
+fn group(bytes: &[u8]) -> [u8; 4] {
+    let [a, b, c] = [0, 1, 2].map(|i| bytes.get(i).copied().unwrap_or(0));
+    let symbols = [a >> 2, (a << 4 | b >> 4) & 0x3f, (b << 2 | c >> 6) & 0x3f, c & 0x3f];
+    let mut group = symbols.map(|value| SYMBOLS[value as usize]);
+
+    for symbol in &mut group[bytes.len() + 1..] {
+        *symbol = b'=';
+    }
+
+    group
+}
+
+/// Return the value of the base64 symbol `c`, if it is one.
+fn value(c: u8) -> Option<u8> {
+    match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "std")]
+fn invalid_data(e: DecodeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "std")]
+    fn write_base64(bytes: &[u8], line_len: usize) -> Vec<u8> {
+        let mut writer = Base64Writer::with_line_len(Vec::new(), line_len);
+
+        // Write a byte at a time, so that groups and lines span writes.
+        for &byte in bytes {
+            writer.write_all(&[byte]).unwrap();
+        }
+
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn rfc_4648_test_vectors() {
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+
+        for &(bytes, text) in &vectors {
+            assert_eq!(encode(bytes.as_bytes()), text);
+            assert_eq!(decode(text.as_bytes()), Ok(bytes.as_bytes().to_vec()));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn lines_are_broken_between_groups() {
+        assert_eq!(write_base64(b"foobarfo", 8), b"Zm9vYmFy\nZm8=\n");
+        assert_eq!(write_base64(b"foobar", 5), b"Zm9vYmFy\n");
+        assert_eq!(write_base64(b"", 8), b"");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn round_trip() {
+        let bytes: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let text = write_base64(&bytes, 76);
+
+        assert_eq!(decode(&text), Ok(bytes.clone()));
+
+        let mut read = Vec::new();
+        Base64Reader::new(&text[..]).read_to_end(&mut read).unwrap();
+        assert_eq!(read, bytes);
+    }
+
+    #[test]
+    fn decode_malformed_input() {
+        let invalid = |at_byte, reason| Err(DecodeError::InvalidBase64 { at_byte, reason });
+
+        assert_eq!(decode(b"Zm9v\nYg"), invalid(5, "incomplete group of symbols"));
+        assert_eq!(decode(b"Zm9v Y!=="), invalid(6, "not a base64 symbol"));
+        assert_eq!(decode(b"Z==="), invalid(1, "misplaced padding"));
+        assert_eq!(decode(b"Zm=v"), invalid(3, "misplaced padding"));
+        assert_eq!(decode(b"Zg==Zm9v"), invalid(4, "data after the padding"));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn base64_reader_malformed_input() {
+        let e = Base64Reader::new(&b"Zm9vYmFy\nZm"[..]).read_to_end(&mut Vec::new()).unwrap_err();
+
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            e.to_string(),
+            DecodeError::InvalidBase64 { at_byte: 9, reason: "incomplete group of symbols" }
+                .to_string()
+        );
+    }
+}
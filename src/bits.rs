@@ -0,0 +1,229 @@
+//! A run-length scheme for bi-level data, such as 1-bit-per-pixel scans,
+//! whose runs are of bits rather than bytes.
+//!
+//! The data is read as a stream of bits, most significant first within each
+//! byte. Its encoding starts with the number of bits, as a varint, followed
+//! by the lengths of the runs of bits, also as varints, alternating between
+//! runs of 0s and runs of 1s. The first run is always of 0s, so it's empty if
+//! the data starts with a 1.
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use crate::varint::{push_count, read_count};
+use crate::DecodeError;
+
+/// Read a byte slice and return the run-length encoding of its bits.
+///
+/// * `bytes` - The bytes to be encoded.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::bits;
+///
+/// // 16 bits: 4 0s, 8 1s and 4 0s.
+/// assert_eq!(bits::encode(&[0x0f, 0xf0]), [16, 4, 8, 4]);
+/// ```
+pub fn encode(bytes: &[u8]) -> Vec<u8> {
+    encode_bits(bytes, bytes.len() as u64 * 8)
+}
+
+/// Return the run-length encoding of the first `bit_len` bits of `bytes`.
+///
+/// * `bytes` - The bytes holding the bits to be encoded.
+/// * `bit_len` - The number of bits to encode.
+///
+/// # Panics
+///
+/// Panics if `bytes` holds fewer than `bit_len` bits.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::bits;
+///
+/// assert_eq!(bits::encode_bits(&[0xe0], 3), [3, 0, 3]);
+/// ```
+pub fn encode_bits(bytes: &[u8], bit_len: u64) -> Vec<u8> {
+    assert!(bit_len <= bytes.len() as u64 * 8, "bit_len is longer than the data");
+
+    let mut encoding = Vec::new();
+    push_count(&mut encoding, bit_len);
+
+    let mut current = false;
+    let mut run: u64 = 0;
+
+    for i in 0..bit_len {
+        if bit(bytes, i) == current {
+            run += 1;
+        } else {
+            push_count(&mut encoding, run);
+            current = !current;
+            run = 1;
+        }
+    }
+
+    if run > 0 || bit_len == 0 {
+        push_count(&mut encoding, run);
+    }
+
+    encoding
+}
+
+/// Read the run-length encoding of a stream of bits and return its decoded
+/// contents, the last byte padded with 0s.
+///
+/// * `bytes` - The bytes to be decoded, as produced by [`encode`].
+///
+/// # Errors
+///
+/// The same as for [`decode_bits`].
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::bits;
+///
+/// assert_eq!(bits::decode(&[16, 4, 8, 4]).unwrap(), [0x0f, 0xf0]);
+/// ```
+pub fn decode(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    decode_bits(bytes).map(|(decoding, _)| decoding)
+}
+
+/// Read the run-length encoding of a stream of bits and return its decoded
+/// contents, the last byte padded with 0s, along with the number of bits.
+///
+/// * `bytes` - The bytes to be decoded, as produced by [`encode_bits`].
+///
+/// # Errors
+///
+/// Returns [`DecodeError::TruncatedInput`] if the input ends in the middle of
+/// a count or the runs are shorter than the stream, [`DecodeError::ZeroCount`]
+/// if a run other than the first is empty, and [`DecodeError::CountOverflow`]
+/// if the runs are longer than the stream, or than fits in memory.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::bits;
+///
+/// assert_eq!(bits::decode_bits(&[3, 0, 3]).unwrap(), (vec![0xe0], 3));
+/// assert!(bits::decode_bits(&[3, 0, 2]).is_err());
+/// ```
+pub fn decode_bits(bytes: &[u8]) -> Result<(Vec<u8>, u64), DecodeError> {
+    let (bit_len, mut i) = read_count(bytes, 0, 0)?;
+    let mut decoding = Vec::new();
+    let mut decoded: u64 = 0;
+    let mut current = false;
+
+    while i < bytes.len() {
+        let at_byte = i;
+        let (run, next) = read_count(bytes, i, at_byte)?;
+
+        if run == 0 && decoded > 0 {
+            return Err(DecodeError::ZeroCount { at_byte });
+        }
+
+        if run > bit_len - decoded {
+            return Err(DecodeError::CountOverflow { at_byte });
+        }
+
+        // Room for the run is asked for before it's written, so that a stream
+        // too long for memory fails instead of aborting.
+        usize::try_from(run / 8 + 1)
+            .ok()
+            .filter(|&len| decoding.try_reserve(len).is_ok())
+            .ok_or(DecodeError::CountOverflow { at_byte })?;
+
+        push_bits(&mut decoding, decoded, run, current);
+        decoded += run;
+        current = !current;
+        i = next;
+    }
+
+    if decoded < bit_len {
+        return Err(DecodeError::TruncatedInput { at_byte: bytes.len() });
+    }
+
+    Ok((decoding, bit_len))
+}
+
+/// Return bit `i` of `bytes`, counting from the most significant bit of the
+/// first byte.
+fn bit(bytes: &[u8], i: u64) -> bool {
+    bytes[(i / 8) as usize] & (0x80 >> (i % 8)) != 0
+}
+
+/// Append `run` bits set to `value` to the `len` bits in `decoding`.
+fn push_bits(decoding: &mut Vec<u8>, mut len: u64, mut run: u64, value: bool) {
+    // Finish the partly written last byte a bit at a time.
+    while !len.is_multiple_of(8) && run > 0 {
+        if value {
+            *decoding.last_mut().unwrap() |= 0x80 >> (len % 8);
+        }
+
+        len += 1;
+        run -= 1;
+    }
+
+    // Room for the whole bytes was reserved by decode_bits.
+    let whole = (run / 8) as usize;
+    decoding.extend(core::iter::repeat_n(if value { 0xff } else { 0 }, whole));
+
+    // Start a new byte with what's left.
+    if !run.is_multiple_of(8) {
+        decoding.push(if value { !(0xff >> (run % 8)) } else { 0 });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn all_ones_start_with_an_empty_run() {
+        let bytes = vec![0xff; 1000];
+
+        assert_eq!(encode(&bytes), [0xc0, 0x3e, 0, 0xc0, 0x3e]);
+        assert_eq!(decode(&encode(&bytes)), Ok(bytes));
+    }
+
+    #[test]
+    fn empty_input() {
+        assert_eq!(encode(&[]), [0, 0]);
+        assert_eq!(decode_bits(&encode(&[])), Ok((vec![], 0)));
+    }
+
+    #[test]
+    fn bit_length_not_a_multiple_of_8() {
+        // 0b1011_0111 0b1...: 13 bits.
+        let bytes = [0xb7, 0xd5];
+        let encoded = encode_bits(&bytes, 13);
+
+        assert_eq!(encoded, [13, 0, 1, 1, 2, 1, 5, 1, 1, 1]);
+        assert_eq!(decode_bits(&encoded), Ok((vec![0xb7, 0xd0], 13)));
+    }
+
+    #[test]
+    fn round_trip_long_runs_across_bytes() {
+        let bytes = [vec![0; 3], vec![0x0f], vec![0xff; 300], vec![0x80, 0x55]].concat();
+
+        assert_eq!(decode(&encode(&bytes)), Ok(bytes));
+    }
+
+    #[test]
+    fn decode_malformed_input() {
+        assert_eq!(decode(&[]), Err(DecodeError::TruncatedInput { at_byte: 0 }));
+        assert_eq!(decode(&[8, 3]), Err(DecodeError::TruncatedInput { at_byte: 2 }));
+        assert_eq!(decode(&[8, 3, 0, 5]), Err(DecodeError::ZeroCount { at_byte: 2 }));
+        assert_eq!(decode(&[8, 3, 6]), Err(DecodeError::CountOverflow { at_byte: 2 }));
+        assert_eq!(decode(&[8, 3, 0x85]), Err(DecodeError::TruncatedInput { at_byte: 2 }));
+
+        // A stream of 2^63 bits, all 0s.
+        let huge = [0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01];
+        let encoded = [&huge[..], &huge].concat();
+        assert_eq!(decode(&encoded), Err(DecodeError::CountOverflow { at_byte: 10 }));
+    }
+}
@@ -0,0 +1,507 @@
+//! A container splitting data into blocks encoded independently of each
+//! other, so that they can be encoded on several threads at once.
+//!
+//! The data is cut into blocks of [`BLOCK_SIZE`] bytes, the last one
+//! possibly shorter, and each block is encoded on its own and written as a
+//! frame:
+//!
+//! | Bytes | Contents                                                 |
+//! |-------|----------------------------------------------------------|
+//! | 0..4  | The length of the block, as a little-endian `u32`        |
+//! | 4..8  | The length of its encoding, as a little-endian `u32`     |
+//! | 8..   | Its encoding                                             |
+//!
+//! Runs crossing the end of a block are split in two, which costs a pair at
+//! most but makes the encoding the same whatever the number of threads.
+//! Without the `std` feature there are no threads, and the blocks are encoded
+//! and decoded one after another.
+
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::{TryFrom, TryInto};
+#[cfg(feature = "std")]
+use std::io::{Read, Write};
+#[cfg(feature = "std")]
+use std::thread;
+
+#[cfg(feature = "std")]
+use crate::format::read_up_to;
+use crate::{DecodeError, Format};
+#[cfg(feature = "std")]
+use crate::RleError;
+
+/// The length of every block but the last.
+pub const BLOCK_SIZE: usize = 1 << 20;
+
+/// The length of the start of a frame, before the encoded block.
+const FRAME_HEADER_LEN: usize = 8;
+
+/// Encode `bytes` with `format` as a sequence of frames, a block at a time,
+/// on up to `threads` threads.
+///
+/// The result doesn't depend on `threads`, so frames encoded from
+/// consecutive slices whose lengths are multiples of [`BLOCK_SIZE`] can be
+/// concatenated.
+///
+/// * `bytes` - The bytes to be encoded.
+/// * `format` - The scheme to encode every block with.
+/// * `threads` - How many threads to encode on. 0 is treated as 1.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::{blocks, Format};
+///
+/// let encoded = blocks::encode(b"aaab", Format::Byte, 4);
+///
+/// assert_eq!(encoded, [4, 0, 0, 0, 4, 0, 0, 0, b'a', 3, b'b', 1]);
+/// assert_eq!(blocks::decode(&encoded, Format::Byte).unwrap(), b"aaab");
+/// ```
+pub fn encode(bytes: &[u8], format: Format, threads: usize) -> Vec<u8> {
+    let blocks: Vec<&[u8]> = bytes.chunks(BLOCK_SIZE).collect();
+    let threads = threads.clamp(1, blocks.len().max(1));
+    let mut encodings = vec![Vec::new(); blocks.len()];
+
+    // Thread `t` takes every block whose index is `t` more than a multiple of
+    // `threads`.
+    let results = run_all((0..threads).collect(), |t| {
+        blocks.iter().skip(t).step_by(threads).map(|b| format.encode(b)).collect::<Vec<_>>()
+    });
+
+    for (t, results) in results.into_iter().enumerate() {
+        for (i, encoding) in results.into_iter().enumerate() {
+            encodings[t + i * threads] = encoding;
+        }
+    }
+
+    let mut framed = Vec::with_capacity(
+        encodings.iter().map(|e| FRAME_HEADER_LEN + e.len()).sum()
+    );
+
+    for (block, encoding) in blocks.iter().zip(&encodings) {
+        push_frame(&mut framed, block.len(), encoding);
+    }
+
+    framed
+}
+
+/// Decode a sequence of frames, as produced by [`encode`], whose blocks were
+/// encoded with `format`.
+///
+/// * `bytes` - The frames to be decoded.
+/// * `format` - The scheme every block was encoded with.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::InvalidBlock`], naming the first block found to be
+/// corrupt, if the input ends in the middle of a frame, if a block doesn't
+/// decode to the length its frame records, or for any error found while
+/// decoding a block, and [`DecodeError::BlockTooLong`] if a frame records a
+/// block longer than [`BLOCK_SIZE`].
+pub fn decode(bytes: &[u8], format: Format) -> Result<Vec<u8>, DecodeError> {
+    decode_with_threads(bytes, format, 1)
+}
+
+/// Decode a sequence of frames, as [`decode`] does, on up to `threads`
+/// threads.
+///
+/// The output is allocated in full from the lengths in the frames, and every
+/// block decoded straight into its place in it.
+///
+/// * `bytes` - The frames to be decoded.
+/// * `format` - The scheme every block was encoded with.
+/// * `threads` - How many threads to decode on. 0 is treated as 1.
+///
+/// # Errors
+///
+/// Fails for the same reasons as [`decode`], whatever the number of threads.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::{blocks, Format};
+///
+/// let bytes = vec![7; 3 * blocks::BLOCK_SIZE];
+/// let encoded = blocks::encode(&bytes, Format::Varint, 3);
+///
+/// assert_eq!(blocks::decode_with_threads(&encoded, Format::Varint, 3).unwrap(), bytes);
+/// ```
+pub fn decode_with_threads(
+    bytes: &[u8], format: Format, threads: usize
+) -> Result<Vec<u8>, DecodeError> {
+    let mut frames = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let (len, encoding) = split_frame(bytes, i, frames.len())?;
+        frames.push((len, encoding));
+        i += FRAME_HEADER_LEN + encoding.len();
+    }
+
+    let mut decoding = vec![0; frames.iter().map(|&(len, _)| len).sum()];
+    decode_frames(&frames, 0, format, threads, &mut decoding)?;
+
+    Ok(decoding)
+}
+
+/// Decode the frames read from `r`, whose blocks were encoded with `format`,
+/// and write the result to `w`, a block at a time. Return the number of bytes
+/// written.
+///
+/// * `r` - The reader the frames come from.
+/// * `w` - The writer that receives the decoded bytes.
+/// * `format` - The scheme every block was encoded with.
+///
+/// # Errors
+///
+/// Returns [`RleError::Decode`] for the same reasons as [`decode`], and
+/// [`RleError::Io`] if reading or writing fails.
+#[cfg(feature = "std")]
+pub fn decode_stream<R: Read, W: Write>(r: R, w: W, format: Format) -> Result<u64, RleError> {
+    decode_stream_with_threads(r, w, format, 1)
+}
+
+/// Decode the frames read from `r`, as [`decode_stream`] does, on up to
+/// `threads` threads.
+///
+/// As many frames as there are threads are read at a time, decoded at once
+/// into a buffer allocated from their lengths, and written out in order.
+///
+/// * `r` - The reader the frames come from.
+/// * `w` - The writer that receives the decoded bytes.
+/// * `format` - The scheme every block was encoded with.
+/// * `threads` - How many threads to decode on. 0 is treated as 1.
+///
+/// # Errors
+///
+/// Fails for the same reasons as [`decode_stream`], whatever the number of
+/// threads. The blocks before the corrupt one may have been written.
+#[cfg(feature = "std")]
+pub fn decode_stream_with_threads<R: Read, W: Write>(
+    mut r: R, mut w: W, format: Format, threads: usize
+) -> Result<u64, RleError> {
+    let threads = threads.max(1);
+    let mut written = 0;
+    let mut at_byte = 0;
+    let mut block = 0;
+    let mut batch = Vec::with_capacity(threads);
+    let mut decoding = Vec::new();
+
+    loop {
+        batch.clear();
+
+        while batch.len() < threads {
+            match read_frame(&mut r, at_byte, block + batch.len())? {
+                Some((len, encoding)) => {
+                    at_byte += FRAME_HEADER_LEN + encoding.len();
+                    batch.push((len, encoding));
+                }
+                None => break,
+            }
+        }
+
+        if batch.is_empty() {
+            break;
+        }
+
+        let frames: Vec<(usize, &[u8])> = batch.iter().map(|(len, e)| (*len, &e[..])).collect();
+        decoding.clear();
+        decoding.resize(frames.iter().map(|&(len, _)| len).sum(), 0);
+        decode_frames(&frames, block, format, threads, &mut decoding)?;
+
+        w.write_all(&decoding)?;
+        written += decoding.len() as u64;
+        block += batch.len();
+    }
+
+    w.flush()?;
+
+    Ok(written)
+}
+
+/// Append the frame of a block of `len` bytes to `framed`.
+fn push_frame(framed: &mut Vec<u8>, len: usize, encoding: &[u8]) {
+    let len = u32::try_from(len).expect("blocks are shorter than 4 GiB");
+    let encoded_len = u32::try_from(encoding.len()).expect("encoded blocks are shorter than 4 GiB");
+
+    framed.extend(&len.to_le_bytes());
+    framed.extend(&encoded_len.to_le_bytes());
+    framed.extend(encoding);
+}
+
+/// Return the length recorded in the frame starting at `bytes[i]`, and the
+/// encoded block it holds.
+///
+/// * `bytes` - The frames.
+/// * `i` - The offset of the frame.
+/// * `block` - The index of the frame, for errors.
+fn split_frame(bytes: &[u8], i: usize, block: usize) -> Result<(usize, &[u8]), DecodeError> {
+    let truncated = invalid_block(block, DecodeError::TruncatedInput { at_byte: i });
+    let frame_header = bytes.get(i..i + FRAME_HEADER_LEN).ok_or(truncated.clone())?;
+    let (len, encoded_len) = parse_frame_header(frame_header.try_into().unwrap(), block)?;
+    let start = i + FRAME_HEADER_LEN;
+    let encoding = bytes.get(start..start + encoded_len).ok_or(truncated)?;
+
+    Ok((len, encoding))
+}
+
+/// Return the length of the frame that `bytes` starts with and that of the
+/// block it holds, without decoding it, or `None` if `bytes` ends before the
+/// start of the frame does or it records a block longer than [`BLOCK_SIZE`].
+///
+/// * `bytes` - The frames from the start of the frame on.
+pub(crate) fn frame_len(bytes: &[u8]) -> Option<(usize, u64)> {
+    let frame_header = bytes.get(..FRAME_HEADER_LEN)?;
+    let (len, encoded_len) = parse_frame_header(frame_header.try_into().unwrap(), 0).ok()?;
+
+    Some((FRAME_HEADER_LEN + encoded_len, len as u64))
+}
+
+/// Read the next frame from `r` and return the length it records and the
+/// encoded block it holds, or `None` if `r` has ended.
+///
+/// * `r` - The reader the frames come from.
+/// * `at_byte` - The offset of the frame, for errors.
+/// * `block` - The index of the frame, for errors.
+#[cfg(feature = "std")]
+fn read_frame(
+    r: &mut impl Read, at_byte: usize, block: usize
+) -> Result<Option<(usize, Vec<u8>)>, RleError> {
+    let truncated = invalid_block(block, DecodeError::TruncatedInput { at_byte });
+    let mut frame_header = [0; FRAME_HEADER_LEN];
+
+    match read_up_to(r, &mut frame_header)? {
+        0 => return Ok(None),
+        FRAME_HEADER_LEN => {}
+        _ => return Err(truncated.into()),
+    }
+
+    let (len, encoded_len) = parse_frame_header(frame_header, block)?;
+    let mut encoding = Vec::with_capacity(encoded_len.min(2 * BLOCK_SIZE));
+
+    if r.take(encoded_len as u64).read_to_end(&mut encoding)? < encoded_len {
+        return Err(truncated.into());
+    }
+
+    Ok(Some((len, encoding)))
+}
+
+/// Return the length of the block and of its encoding recorded in the start
+/// of a frame.
+///
+/// * `frame_header` - The start of the frame.
+/// * `block` - The index of the frame, for errors.
+fn parse_frame_header(
+    frame_header: [u8; FRAME_HEADER_LEN], block: usize
+) -> Result<(usize, usize), DecodeError> {
+    let len = u32::from_le_bytes(frame_header[..4].try_into().unwrap()) as usize;
+    let encoded_len = u32::from_le_bytes(frame_header[4..].try_into().unwrap()) as usize;
+
+    // The output is allocated from the lengths before any block is decoded,
+    // so they must not be trusted to be small.
+    if len > BLOCK_SIZE {
+        return Err(DecodeError::BlockTooLong { block, len: len as u64 });
+    }
+
+    Ok((len, encoded_len))
+}
+
+/// Decode `frames` into `decoding`, which is as long as all of their blocks,
+/// on up to `threads` threads, and return the error of the first corrupt
+/// block, if any.
+///
+/// * `frames` - The length and encoding of every block.
+/// * `first_block` - The index of the first of `frames`, for errors.
+/// * `format` - The scheme every block was encoded with.
+/// * `threads` - How many threads to decode on.
+/// * `decoding` - Where the blocks are decoded to.
+fn decode_frames(
+    frames: &[(usize, &[u8])],
+    first_block: usize,
+    format: Format,
+    threads: usize,
+    decoding: &mut [u8],
+) -> Result<(), DecodeError> {
+    let threads = threads.clamp(1, frames.len().max(1));
+    // Thread `t` takes every block whose index is `t` more than a multiple
+    // of `threads`, each with the part of the output it decodes to.
+    let mut work: Vec<Vec<_>> = (0..threads).map(|_| Vec::new()).collect();
+    let mut rest = decoding;
+
+    for (i, &(len, encoding)) in frames.iter().enumerate() {
+        let (output, after) = rest.split_at_mut(len);
+        work[i % threads].push((first_block + i, encoding, output));
+        rest = after;
+    }
+
+    let decode_all = |work: Vec<(usize, &[u8], &mut [u8])>| {
+        work.into_iter().try_for_each(|(block, encoding, output)| {
+            decode_block(encoding, format, output).map_err(|e| invalid_block(block, e))
+        })
+    };
+
+    let errors = run_all(work, decode_all).into_iter().filter_map(Result::err);
+
+    // Every thread stops at its first corrupt block, so the first of those is
+    // the first corrupt block of all.
+    match errors.min_by_key(|e| match e {
+        DecodeError::InvalidBlock { block, .. } => *block,
+        _ => usize::MAX,
+    }) {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Call `f` on every item of `work`, each on a thread of its own, and return
+/// the results in order. Without `std`, the calls are made one after another.
+#[cfg(feature = "std")]
+fn run_all<T: Send, R: Send>(work: Vec<T>, f: impl Fn(T) -> R + Sync) -> Vec<R> {
+    if work.len() <= 1 {
+        return work.into_iter().map(f).collect();
+    }
+
+    let f = &f;
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = work.into_iter().map(|item| scope.spawn(move || f(item))).collect();
+
+        workers.into_iter().map(|worker| worker.join().expect("a worker thread panicked")).collect()
+    })
+}
+
+#[cfg(not(feature = "std"))]
+fn run_all<T, R>(work: Vec<T>, f: impl Fn(T) -> R) -> Vec<R> {
+    work.into_iter().map(f).collect()
+}
+
+/// Decode a single block into `output`, checking that it has the length its
+/// frame records, which is that of `output`.
+fn decode_block(encoding: &[u8], format: Format, output: &mut [u8]) -> Result<(), DecodeError> {
+    let decoding = format.decode(encoding)?;
+
+    if decoding.len() != output.len() {
+        return Err(DecodeError::LengthMismatch {
+            expected: output.len() as u64,
+            actual: decoding.len() as u64,
+        });
+    }
+
+    output.copy_from_slice(&decoding);
+
+    Ok(())
+}
+
+/// Return `error` as found in the block at index `block`.
+fn invalid_block(block: usize, error: DecodeError) -> DecodeError {
+    DecodeError::InvalidBlock { block, error: Box::new(error) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    #[cfg(feature = "std")]
+    use std::io;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn encoding_does_not_depend_on_threads() {
+        // Runs of 1000 bytes, so that some cross the ends of blocks.
+        let bytes: Vec<u8> = (0..3 * BLOCK_SIZE + 5000).map(|i| (i / 1000) as u8).collect();
+        let encoded = encode(&bytes, Format::Byte, 1);
+
+        for threads in 2..6 {
+            assert_eq!(encode(&bytes, Format::Byte, threads), encoded);
+        }
+
+        assert_eq!(decode(&encoded, Format::Byte), Ok(bytes.clone()));
+
+        let mut decoded = Vec::new();
+        assert_eq!(decode_stream(&encoded[..], &mut decoded, Format::Byte).unwrap(), bytes.len() as u64);
+        assert_eq!(decoded, bytes);
+    }
+
+    #[test]
+    fn concatenated_frames_decode_as_one() {
+        let bytes: Vec<u8> = (0..2 * BLOCK_SIZE + 10).map(|i| (i / 300) as u8).collect();
+        let (first, second) = bytes.split_at(BLOCK_SIZE);
+        let encoded = [encode(first, Format::Varint, 2), encode(second, Format::Varint, 2)].concat();
+
+        assert_eq!(encoded, encode(&bytes, Format::Varint, 3));
+        assert_eq!(decode(&encoded, Format::Varint), Ok(bytes));
+    }
+
+    #[test]
+    fn empty_input_has_no_frames() {
+        assert_eq!(encode(&[], Format::Byte, 8), []);
+        assert_eq!(decode(&[], Format::Byte), Ok(vec![]));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn decoding_does_not_depend_on_threads() {
+        let bytes: Vec<u8> = (0..5 * BLOCK_SIZE + 5000).map(|i| (i / 700) as u8).collect();
+        let encoded = encode(&bytes, Format::Byte, 3);
+
+        for threads in 0..6 {
+            assert_eq!(decode_with_threads(&encoded, Format::Byte, threads), Ok(bytes.clone()));
+
+            let mut decoded = Vec::new();
+            decode_stream_with_threads(&encoded[..], &mut decoded, Format::Byte, threads).unwrap();
+            assert_eq!(decoded, bytes);
+        }
+    }
+
+    #[test]
+    fn decode_malformed_frames() {
+        let encoded = encode(b"aaabbbcc", Format::Byte, 1);
+        let truncated = invalid_block(0, DecodeError::TruncatedInput { at_byte: 0 });
+
+        assert_eq!(decode(&encoded[..5], Format::Byte), Err(truncated.clone()));
+        assert_eq!(decode(&encoded[..10], Format::Byte), Err(truncated));
+
+        let mut wrong_len = encoded.clone();
+        wrong_len[0] = 9;
+        assert_eq!(
+            decode(&wrong_len, Format::Byte),
+            Err(invalid_block(0, DecodeError::LengthMismatch { expected: 9, actual: 8 }))
+        );
+
+        let mut too_long = encoded.clone();
+        too_long[3] = 1;
+        assert_eq!(decode(&too_long, Format::Byte), Err(DecodeError::BlockTooLong { block: 0, len: 1 << 24 | 8 }));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn decode_stream_malformed_frames() {
+        let encoded = encode(b"aaabbbcc", Format::Byte, 1);
+        let e = decode_stream(&encoded[..10], io::sink(), Format::Byte).unwrap_err();
+
+        assert!(matches!(e, RleError::Decode(DecodeError::InvalidBlock { block: 0, .. })));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn decode_reports_the_first_corrupt_block() {
+        let bytes = vec![1; 6 * BLOCK_SIZE];
+        let mut encoded = encode(&bytes, Format::Byte, 1);
+        let frame_len = encoded.len() / 6;
+
+        // Blocks 2 and 4 start with a run of zero bytes.
+        for block in &[4, 2] {
+            encoded[block * frame_len + FRAME_HEADER_LEN + 1] = 0;
+        }
+
+        for threads in 1..5 {
+            let e = decode_with_threads(&encoded, Format::Byte, threads).unwrap_err();
+            assert_eq!(e, invalid_block(2, DecodeError::ZeroCount { at_byte: 0 }));
+            assert_eq!(e.to_string(), "block 2: invalid input: run at offset 0 has a count of zero");
+
+            let e = decode_stream_with_threads(&encoded[..], io::sink(), Format::Byte, threads);
+            assert!(matches!(e, Err(RleError::Decode(DecodeError::InvalidBlock { block: 2, .. }))));
+        }
+    }
+}
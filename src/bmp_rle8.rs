@@ -0,0 +1,306 @@
+//! The RLE8 compression of Windows BMP files with 8 bits per pixel.
+//!
+//! The data is a sequence of two-byte codes, each row of pixels at a time:
+//!
+//! * `[n, byte]`, for `n` from 1 to 255, is a run of `n` pixels.
+//! * `[0, 0]` ends a row, leaving the rest of it as pixel 0.
+//! * `[0, 1]` ends the bitmap, leaving the rest of it as pixel 0.
+//! * `[0, 2, dx, dy]` moves `dx` pixels right and `dy` rows down, skipping
+//!   over pixels that are left as 0.
+//! * `[0, n, bytes...]`, for `n` from 3 to 255, is `n` pixels as they are,
+//!   padded with a 0 to an even length.
+//!
+//! The rows are given in the order they're stored in the file, which is
+//! bottom-up for most bitmaps.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::DecodeError;
+
+/// The code ending a row.
+const END_OF_LINE: [u8; 2] = [0, 0];
+/// The code ending the bitmap.
+const END_OF_BITMAP: [u8; 2] = [0, 1];
+/// The escape byte that starts a move.
+const DELTA: u8 = 2;
+/// The fewest pixels that can be stored as they are, since fewer would be
+/// taken for the other escape codes.
+const MIN_LITERAL: usize = 3;
+
+/// Read the pixels of a `width` by `height` bitmap, a byte per pixel, and
+/// return their RLE8 encoding.
+///
+/// * `bytes` - The pixels, a row at a time.
+/// * `width` - The number of pixels in a row.
+/// * `height` - The number of rows.
+///
+/// # Panics
+///
+/// Panics if `bytes` doesn't hold exactly `width * height` pixels.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::bmp_rle8;
+///
+/// let pixels = [7, 7, 7, 7, 1, 2, 3, 4];
+///
+/// assert_eq!(bmp_rle8::encode(&pixels, 4, 2), [4, 7, 0, 0, 0, 4, 1, 2, 3, 4, 0, 1]);
+/// ```
+pub fn encode(bytes: &[u8], width: usize, height: usize) -> Vec<u8> {
+    assert_eq!(bytes.len(), width * height, "the bitmap must have width * height pixels");
+
+    let mut encoding = Vec::new();
+
+    for (y, row) in bytes.chunks(width.max(1)).enumerate() {
+        encode_row(row, &mut encoding);
+
+        if y + 1 < height {
+            encoding.extend(&END_OF_LINE);
+        }
+    }
+
+    encoding.extend(&END_OF_BITMAP);
+
+    encoding
+}
+
+/// Read the RLE8 encoding of a `width` by `height` bitmap and return its
+/// pixels, a byte per pixel.
+///
+/// Pixels the encoding skips over are 0. The bitmap may end without the
+/// code that ends it.
+///
+/// * `bytes` - The bytes to be decoded.
+/// * `width` - The number of pixels in a row.
+/// * `height` - The number of rows.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::TruncatedInput`] if the input ends in the middle of
+/// a code, and [`DecodeError::CountOverflow`] if a code goes past the end of
+/// a row or of the bitmap.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::bmp_rle8;
+///
+/// let encoded = [4, 7, 0, 0, 0, 4, 1, 2, 3, 4, 0, 1];
+///
+/// assert_eq!(bmp_rle8::decode(&encoded, 4, 2).unwrap(), [7, 7, 7, 7, 1, 2, 3, 4]);
+/// ```
+pub fn decode(bytes: &[u8], width: usize, height: usize) -> Result<Vec<u8>, DecodeError> {
+    let mut decoding = vec![0; width * height];
+    let (mut x, mut y) = (0, 0);
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let at_byte = i;
+        let code = bytes.get(i..i + 2).ok_or(DecodeError::TruncatedInput { at_byte })?;
+        let overflow = DecodeError::CountOverflow { at_byte };
+        i += 2;
+
+        match *code {
+            [0, 0] => {
+                x = 0;
+                y += 1;
+            }
+            [0, 1] => break,
+            [0, DELTA] => {
+                let delta = bytes.get(i..i + 2).ok_or(DecodeError::TruncatedInput { at_byte })?;
+                x += delta[0] as usize;
+                y += delta[1] as usize;
+                i += 2;
+
+                if x > width || y > height || y == height && x > 0 {
+                    return Err(overflow);
+                }
+            }
+            [0, n] => {
+                let n = n as usize;
+                let pixels = bytes.get(i..i + n).ok_or(DecodeError::TruncatedInput { at_byte })?;
+
+                if y >= height || x + n > width {
+                    return Err(overflow);
+                }
+
+                decoding[y * width + x..][..n].copy_from_slice(pixels);
+                x += n;
+                i += n + n % 2;
+            }
+            [n, byte] => {
+                let n = n as usize;
+
+                if y >= height || x + n > width {
+                    return Err(overflow);
+                }
+
+                decoding[y * width + x..][..n].fill(byte);
+                x += n;
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    Ok(decoding)
+}
+
+/// Append the codes of the pixels in `row` to `encoding`.
+fn encode_row(row: &[u8], encoding: &mut Vec<u8>) {
+    let mut i = 0;
+
+    while i < row.len() {
+        let run = run_len(&row[i..]);
+
+        if run >= MIN_LITERAL {
+            encoding.extend(&[run as u8, row[i]]);
+            i += run;
+            continue;
+        }
+
+        // Gather pixels up to the next run worth encoding as one.
+        let mut j = i;
+
+        while j < row.len() && j - i < 255 && run_len(&row[j..]) < MIN_LITERAL {
+            j = (j + run_len(&row[j..])).min(i + 255);
+        }
+
+        let literal = &row[i..j];
+        let runs = crate::runs::pairs(literal, u8::MAX).count();
+
+        // Store the pixels as they are only if that's shorter than their
+        // runs.
+        if literal.len() >= MIN_LITERAL && 2 + literal.len() + literal.len() % 2 < 2 * runs {
+            encoding.extend(&[0, literal.len() as u8]);
+            encoding.extend(literal);
+
+            if literal.len() % 2 == 1 {
+                encoding.push(0);
+            }
+        } else {
+            for (byte, count) in crate::runs::pairs(literal, u8::MAX) {
+                encoding.extend(&[count, byte]);
+            }
+        }
+
+        i = j;
+    }
+}
+
+/// Return the length of the run at the start of `pixels`, up to 255.
+fn run_len(pixels: &[u8]) -> usize {
+    pixels.iter().take(255).take_while(|&&pixel| pixel == pixels[0]).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Return a BMP file of a `width` by `height` bitmap with a grayscale
+    /// palette and the RLE8 encoding `data`.
+    fn bmp_file(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+        let offset = 14 + 40 + 256 * 4;
+        let mut file = Vec::new();
+
+        file.extend(b"BM");
+        file.extend(&((offset + data.len()) as u32).to_le_bytes());
+        file.extend(&[0; 4]);
+        file.extend(&(offset as u32).to_le_bytes());
+
+        file.extend(&40u32.to_le_bytes());
+        file.extend(&(width as i32).to_le_bytes());
+        file.extend(&(height as i32).to_le_bytes());
+        file.extend(&1u16.to_le_bytes());
+        file.extend(&8u16.to_le_bytes());
+        // BI_RLE8.
+        file.extend(&1u32.to_le_bytes());
+        file.extend(&(data.len() as u32).to_le_bytes());
+        file.extend(&[0; 8]);
+        file.extend(&256u32.to_le_bytes());
+        file.extend(&[0; 4]);
+
+        for i in 0..=255 {
+            file.extend(&[i, i, i, 0]);
+        }
+
+        file.extend(data);
+
+        file
+    }
+
+    /// Decode a BMP file with an independent implementation and return its
+    /// pixels in file order, bottom row first.
+    fn decode_with_image(file: &[u8], width: usize) -> Vec<u8> {
+        let image = image::load_from_memory_with_format(file, image::ImageFormat::Bmp).unwrap();
+        let rows: Vec<Vec<u8>> = image
+            .to_rgb8()
+            .pixels()
+            .map(|pixel| pixel[0])
+            .collect::<Vec<_>>()
+            .chunks(width)
+            .map(<[u8]>::to_vec)
+            .collect();
+
+        rows.into_iter().rev().flatten().collect()
+    }
+
+    #[test]
+    fn decode_reference_example() {
+        // The example from the BMP documentation, in a 32 by 3 bitmap.
+        let encoded = [
+            0x03, 0x04, 0x05, 0x06, 0x00, 0x03, 0x45, 0x56, 0x67, 0x00, 0x02, 0x78, 0x00, 0x02,
+            0x05, 0x01, 0x02, 0x78, 0x00, 0x00, 0x09, 0x1e, 0x00, 0x01,
+        ];
+        let mut expected = vec![0; 96];
+        expected[..13].copy_from_slice(&[4, 4, 4, 6, 6, 6, 6, 6, 0x45, 0x56, 0x67, 0x78, 0x78]);
+        // Moved 5 pixels right and a row down.
+        expected[32 + 18..][..2].copy_from_slice(&[0x78, 0x78]);
+        expected[64..][..9].copy_from_slice(&[0x1e; 9]);
+
+        assert_eq!(decode(&encoded, 32, 3), Ok(expected.clone()));
+        assert_eq!(decode_with_image(&bmp_file(&encoded, 32, 3), 32), expected);
+    }
+
+    #[test]
+    fn encoding_matches_image_decoder() {
+        let (width, height) = (37, 9);
+        let pixels: Vec<u8> = (0..width * height)
+            .map(|i| match i % width {
+                0..=9 => 200,
+                10..=20 => (i * 7 % 251) as u8,
+                21..=22 => 5,
+                _ => (i / width * 30) as u8,
+            })
+            .collect();
+        let encoded = encode(&pixels, width, height);
+
+        assert_eq!(decode(&encoded, width, height), Ok(pixels.clone()));
+        assert_eq!(decode_with_image(&bmp_file(&encoded, width, height), width), pixels);
+    }
+
+    #[test]
+    fn literals_only_when_shorter() {
+        assert_eq!(encode(&[1, 2, 2, 9, 9, 9], 6, 1), [1, 1, 2, 2, 3, 9, 0, 1]);
+        assert_eq!(encode(&[1, 2, 3, 4, 9, 9, 9], 7, 1), [0, 4, 1, 2, 3, 4, 3, 9, 0, 1]);
+    }
+
+    #[test]
+    fn long_rows_are_split() {
+        let pixels: Vec<u8> = (0..600).map(|i| if i < 300 { 1 } else { (i % 200) as u8 }).collect();
+        let encoded = encode(&pixels, 600, 1);
+
+        assert_eq!(&encoded[..4], [255, 1, 45, 1]);
+        assert_eq!(decode(&encoded, 600, 1), Ok(pixels));
+    }
+
+    #[test]
+    fn decode_malformed_input() {
+        assert_eq!(decode(&[3, 1, 0], 4, 1), Err(DecodeError::TruncatedInput { at_byte: 2 }));
+        assert_eq!(decode(&[0, 4, 1, 2], 4, 1), Err(DecodeError::TruncatedInput { at_byte: 0 }));
+        assert_eq!(decode(&[3, 1, 2, 1], 4, 1), Err(DecodeError::CountOverflow { at_byte: 2 }));
+        assert_eq!(decode(&[0, 0, 1, 1], 4, 1), Err(DecodeError::CountOverflow { at_byte: 2 }));
+        assert_eq!(decode(&[0, 2, 0, 2], 4, 1), Err(DecodeError::CountOverflow { at_byte: 0 }));
+    }
+}
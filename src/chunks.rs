@@ -0,0 +1,265 @@
+//! Data cut into blocks encoded independently of each other, each written
+//! after the length of its encoding, so that a single block can be decoded
+//! without decoding the ones before it:
+//!
+//! | Bytes | Contents                                                  |
+//! |-------|-----------------------------------------------------------|
+//! | 0..4  | The length of the encoded block, as a little-endian `u32` |
+//! | 4..   | The encoded block                                         |
+//!
+//! Finding a block only reads the prefixes of the ones before it, which is
+//! far faster than decoding them. Runs crossing the end of a block are split
+//! in two. [`encode_parallel`](crate::encode_parallel) writes the same
+//! layout, on several threads.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::ops::Range;
+
+use crate::DecodeError;
+
+/// The length of the prefix of an encoded block.
+pub(crate) const PREFIX_LEN: usize = 4;
+
+/// The longest block whose encoding always fits the `u32` prefix.
+pub(crate) const MAX_BLOCK_SIZE: usize = u32::MAX as usize / 2;
+
+/// Encode `bytes` a block of `block_size` bytes at a time, and return the
+/// length-prefixed encodings of the blocks one after another.
+///
+/// * `bytes` - The bytes to be encoded.
+/// * `block_size` - The length of every block but the last. It is kept
+///   between 1 and `u32::MAX / 2`, so that encodings fit their prefix.
+///
+/// # Examples
+///
+/// ```
+/// let encoded = rle_rs::encode_blocks(b"aaab", 2);
+///
+/// assert_eq!(encoded, [2, 0, 0, 0, b'a', 2, 4, 0, 0, 0, b'a', 1, b'b', 1]);
+/// assert_eq!(rle_rs::decode_block(&encoded, 1).unwrap(), b"ab");
+/// ```
+pub fn encode_blocks(bytes: &[u8], block_size: usize) -> Vec<u8> {
+    let mut encoding = Vec::new();
+
+    for block in bytes.chunks(block_size.clamp(1, MAX_BLOCK_SIZE)) {
+        let block = crate::encode(block);
+        encoding.extend(&(block.len() as u32).to_le_bytes());
+        encoding.extend(block);
+    }
+
+    encoding
+}
+
+/// Decode the block at `block_index` of the blocks written by
+/// [`encode_blocks`], skipping the ones before it.
+///
+/// * `encoded` - The encoded blocks.
+/// * `block_index` - The index of the block, counting from 0.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::TruncatedInput`] if `encoded` ends within a prefix
+/// or before the end of the block it announces,
+/// [`DecodeError::BlockOutOfRange`] if there are no more than `block_index`
+/// blocks, and [`DecodeError::InvalidBlock`] if the block isn't a valid
+/// encoding.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::DecodeError;
+///
+/// let encoded = rle_rs::encode_blocks(b"aaab", 2);
+///
+/// assert_eq!(rle_rs::decode_block(&encoded, 0).unwrap(), b"aa");
+/// assert_eq!(
+///     rle_rs::decode_block(&encoded, 2),
+///     Err(DecodeError::BlockOutOfRange { block: 2, blocks: 2 })
+/// );
+/// ```
+pub fn decode_block(encoded: &[u8], block_index: usize) -> Result<Vec<u8>, DecodeError> {
+    let mut at = 0;
+
+    for block in 0.. {
+        if at == encoded.len() {
+            return Err(DecodeError::BlockOutOfRange { block: block_index, blocks: block });
+        }
+
+        let (chunk, next) = next_block(encoded, at)?;
+
+        if block == block_index {
+            return crate::decode(chunk).map_err(|error| DecodeError::InvalidBlock { block, error: Box::new(error) });
+        }
+
+        at = next;
+    }
+
+    unreachable!("there are fewer blocks than indices")
+}
+
+/// Return the range of `encoded`, as written by [`encode_blocks`] with
+/// `block_size`, that decodes from the run holding the byte at offset
+/// `decoded_target` of the decoded data to the end of its block.
+///
+/// Every block but the last decodes to `block_size` bytes, so the block is
+/// found from the offset alone, by skipping the prefixes before it, and only
+/// the counts of that block before the byte are read.
+///
+/// * `encoded` - The encoded blocks.
+/// * `block_size` - The length the blocks were encoded with.
+/// * `decoded_target` - The offset of the byte in the decoded data.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::OffsetOutOfRange`] if the blocks decode to no more
+/// than `decoded_target` bytes, [`DecodeError::TruncatedInput`] for the same
+/// reasons as [`decode_block`], and [`DecodeError::InvalidBlock`] if the
+/// counts read are malformed.
+///
+/// # Examples
+///
+/// ```
+/// let encoded = rle_rs::encode_blocks(b"aaaabbbbcc", 4);
+/// let range = rle_rs::find_block_offset(&encoded, 4, 5).unwrap();
+///
+/// assert_eq!(range, 10..12);
+/// assert_eq!(rle_rs::decode(&encoded[range]).unwrap(), b"bbbb");
+/// ```
+pub fn find_block_offset(
+    encoded: &[u8], block_size: usize, decoded_target: usize
+) -> Result<Range<usize>, DecodeError> {
+    let block_size = block_size.clamp(1, MAX_BLOCK_SIZE);
+    let block_index = decoded_target / block_size;
+    let mut at = 0;
+    let mut decoded = 0;
+
+    for block in 0..=block_index {
+        if at == encoded.len() {
+            return Err(DecodeError::OffsetOutOfRange { offset: decoded_target, len: decoded });
+        }
+
+        let (chunk, next) = next_block(encoded, at)?;
+        let start = next - chunk.len();
+
+        if block < block_index {
+            // Only the last block can be shorter, and it's only decoded to
+            // tell how long the data is.
+            if next == encoded.len() {
+                let len = crate::decoded_len(chunk)
+                    .map_err(|error| DecodeError::InvalidBlock { block, error: Box::new(error) })?;
+
+                return Err(DecodeError::OffsetOutOfRange { offset: decoded_target, len: decoded + len });
+            }
+
+            decoded += block_size as u64;
+            at = next;
+            continue;
+        }
+
+        return match crate::find_encoded_offset(chunk, decoded_target - block_index * block_size) {
+            Ok(offset) => Ok(start + offset..next),
+            Err(DecodeError::OffsetOutOfRange { len, .. }) => {
+                Err(DecodeError::OffsetOutOfRange { offset: decoded_target, len: decoded + len })
+            }
+            Err(error) => Err(DecodeError::InvalidBlock { block, error: Box::new(error) }),
+        };
+    }
+
+    unreachable!("the last block is always returned from")
+}
+
+/// Return the encoded block starting at offset `at` of `bytes`, without its
+/// prefix, and the offset of the next one.
+pub(crate) fn next_block(bytes: &[u8], at: usize) -> Result<(&[u8], usize), DecodeError> {
+    let prefix = bytes
+        .get(at..at + PREFIX_LEN)
+        .ok_or(DecodeError::TruncatedInput { at_byte: bytes.len() })?;
+    let len = u32::from_le_bytes(prefix.try_into().unwrap()) as usize;
+    let start = at + PREFIX_LEN;
+    let block = bytes
+        .get(start..start.saturating_add(len))
+        .ok_or(DecodeError::TruncatedInput { at_byte: bytes.len() })?;
+
+    Ok((block, start + len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_each_block() {
+        let bytes: Vec<u8> = (0..10_000).map(|i| (i / 300) as u8).collect();
+        let encoded = encode_blocks(&bytes, 1000);
+
+        for (i, block) in bytes.chunks(1000).enumerate() {
+            assert_eq!(decode_block(&encoded, i).unwrap(), block);
+        }
+        assert_eq!(decode_block(&encoded, 10), Err(DecodeError::BlockOutOfRange { block: 10, blocks: 10 }));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn same_layout_as_encode_parallel() {
+        let bytes = b"aaaabbbbbbcdddddddddddd".repeat(100);
+
+        assert_eq!(encode_blocks(&bytes, 7), crate::encode_parallel(&bytes, 7));
+    }
+
+    #[test]
+    fn empty_input() {
+        assert!(encode_blocks(&[], 16).is_empty());
+        assert_eq!(decode_block(&[], 0), Err(DecodeError::BlockOutOfRange { block: 0, blocks: 0 }));
+    }
+
+    #[test]
+    fn earlier_blocks_are_not_decoded() {
+        // The first block is a corrupt encoding, but only its length is read.
+        let encoded = [1, 0, 0, 0, b'a', 2, 0, 0, 0, b'b', 2];
+
+        assert_eq!(decode_block(&encoded, 1).unwrap(), b"bb");
+        assert_eq!(
+            decode_block(&encoded, 0),
+            Err(DecodeError::InvalidBlock {
+                block: 0,
+                error: Box::new(DecodeError::TruncatedInput { at_byte: 0 }),
+            })
+        );
+    }
+
+    #[test]
+    fn find_block_offset_of_every_byte() {
+        let bytes: Vec<u8> = (0..3000).map(|i| (i / 7 % 3) as u8).collect();
+        let encoded = encode_blocks(&bytes, 256);
+
+        for target in 0..bytes.len() {
+            let range = find_block_offset(&encoded, 256, target).unwrap();
+            let block = target / 256;
+            let rest = crate::decode(&encoded[range]).unwrap();
+            let end = bytes.len().min((block + 1) * 256);
+
+            // Decoding the range gives the run holding the byte and the rest
+            // of its block.
+            assert!(rest.len() > end - target - 1 && rest.len() <= end - block * 256);
+            assert_eq!(rest, &bytes[end - rest.len()..end]);
+            assert!(bytes[end - rest.len()..=target].iter().all(|&b| b == bytes[target]));
+        }
+    }
+
+    #[test]
+    fn find_block_offset_past_the_end() {
+        let encoded = encode_blocks(b"aaaabbbbcc", 4);
+
+        assert_eq!(find_block_offset(&encoded, 4, 10), Err(DecodeError::OffsetOutOfRange { offset: 10, len: 10 }));
+        assert_eq!(find_block_offset(&encoded, 4, 40), Err(DecodeError::OffsetOutOfRange { offset: 40, len: 10 }));
+        assert_eq!(find_block_offset(&[], 4, 0), Err(DecodeError::OffsetOutOfRange { offset: 0, len: 0 }));
+    }
+
+    #[test]
+    fn decode_truncated_block() {
+        assert_eq!(decode_block(&[2, 0, 0, 0, b'a', 3, 4, 0], 1), Err(DecodeError::TruncatedInput { at_byte: 8 }));
+        assert_eq!(decode_block(&[2, 0, 0, 0, b'a'], 0), Err(DecodeError::TruncatedInput { at_byte: 5 }));
+    }
+}
@@ -0,0 +1,130 @@
+//! The CRC-32 checksum (as used by zlib, PNG and many others), which encoded
+//! files carry to detect corruption.
+
+/// The lookup table for the reflected polynomial `0xedb88320`.
+#[cfg(not(feature = "crc"))]
+const TABLE: [u32; 256] = table();
+
+#[cfg(not(feature = "crc"))]
+const fn table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 1 == 1 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 };
+            bit += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+}
+
+/// A CRC-32 computed incrementally, for data that arrives in pieces.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::Crc32;
+///
+/// let mut crc = Crc32::new();
+/// crc.update(b"1234");
+/// crc.update(b"56789");
+///
+/// assert_eq!(crc.value(), rle_rs::crc32(b"123456789"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crc32 {
+    state: u32,
+}
+
+impl Crc32 {
+    /// Create the checksum of no data.
+    pub fn new() -> Self {
+        Self { state: !0 }
+    }
+
+    /// Add `bytes` to the data checksummed so far.
+    ///
+    /// * `bytes` - The next piece of the data.
+    #[cfg(not(feature = "crc"))]
+    pub fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.state = TABLE[((self.state ^ byte as u32) & 0xff) as usize] ^ (self.state >> 8);
+        }
+    }
+
+    /// Add `bytes` to the data checksummed so far.
+    ///
+    /// * `bytes` - The next piece of the data.
+    #[cfg(feature = "crc")]
+    pub fn update(&mut self, bytes: &[u8]) {
+        // The hasher carries on from a checksum, which keeps this `Copy`.
+        let mut hasher = crc32fast::Hasher::new_with_initial(self.value());
+        hasher.update(bytes);
+        self.state = !hasher.finalize();
+    }
+
+    /// Return the checksum of the data so far.
+    pub fn value(&self) -> u32 {
+        !self.state
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Return the CRC-32 of a byte slice.
+///
+/// * `bytes` - The data to be checksummed.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(rle_rs::crc32(b"123456789"), 0xcbf4_3926);
+/// ```
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(bytes);
+
+    crc.value()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn crc32_of_nothing() {
+        assert_eq!(crc32(&[]), 0);
+    }
+
+    #[test]
+    fn crc32_known_values() {
+        assert_eq!(crc32(b"The quick brown fox jumps over the lazy dog"), 0x414f_a339);
+        assert_eq!(crc32(&[0; 32]), 0x190a_55ad);
+    }
+
+    #[test]
+    fn crc32_in_pieces() {
+        let bytes: Vec<u8> = (0..10_000).map(|i| (i * 7 % 251) as u8).collect();
+        let mut crc = Crc32::new();
+
+        for piece in bytes.chunks(333) {
+            crc.update(piece);
+        }
+
+        assert_eq!(crc.value(), crc32(&bytes));
+        assert_eq!(crc32(&bytes), 0x5fdc_1b6c);
+    }
+}
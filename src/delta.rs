@@ -0,0 +1,136 @@
+//! Delta encoding, to turn smooth data such as audio samples, gradients and
+//! sensor readings into long runs before it is run-length encoded.
+//!
+//! Each byte is replaced by its difference from the one before it, modulo
+//! 256, with the first byte kept as it is. A steady gradient thus becomes a
+//! single run of its step.
+
+use alloc::vec::Vec;
+
+use crate::DecodeError;
+#[cfg(feature = "std")]
+use crate::RleError;
+
+/// Read a byte slice and return the difference of each byte from the one
+/// before it, modulo 256.
+///
+/// * `bytes` - The bytes to be transformed.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(rle_rs::delta_encode(&[10, 11, 12, 13, 9]), [10, 1, 1, 1, 252]);
+/// ```
+pub fn delta_encode(bytes: &[u8]) -> Vec<u8> {
+    let mut previous = 0;
+
+    bytes
+        .iter()
+        .map(|&byte| {
+            let delta = byte.wrapping_sub(previous);
+            previous = byte;
+
+            delta
+        })
+        .collect()
+}
+
+/// Read the differences returned by [`delta_encode`] and return the bytes
+/// they were taken from, as their running sum modulo 256.
+///
+/// * `bytes` - The differences to be transformed back.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(rle_rs::delta_decode(&[10, 1, 1, 1, 252]), [10, 11, 12, 13, 9]);
+/// ```
+pub fn delta_decode(bytes: &[u8]) -> Vec<u8> {
+    let mut sum: u8 = 0;
+
+    bytes
+        .iter()
+        .map(|&delta| {
+            sum = sum.wrapping_add(delta);
+
+            sum
+        })
+        .collect()
+}
+
+/// Read a byte slice and return the run-length encoding of its differences.
+///
+/// * `bytes` - The bytes to be encoded.
+///
+/// # Examples
+///
+/// ```
+/// let gradient: Vec<u8> = (0..100).collect();
+///
+/// assert_eq!(rle_rs::encode_delta_rle(&gradient), [0, 1, 1, 99]);
+/// ```
+pub fn encode_delta_rle(bytes: &[u8]) -> Vec<u8> {
+    crate::encode(&delta_encode(bytes))
+}
+
+/// Read the run-length encoding of differences and return the bytes they
+/// were taken from.
+///
+/// * `bytes` - The bytes to be decoded, as produced by [`encode_delta_rle`].
+///
+/// # Errors
+///
+/// Returns [`RleError::Decode`] for the same reasons as
+/// [`decode`](crate::decode).
+///
+/// # Examples
+///
+/// ```
+/// let gradient: Vec<u8> = (0..100).collect();
+///
+/// assert_eq!(rle_rs::decode_rle_delta(&[0, 1, 1, 99]).unwrap(), gradient);
+/// ```
+#[cfg(feature = "std")]
+pub fn decode_rle_delta(bytes: &[u8]) -> Result<Vec<u8>, RleError> {
+    Ok(decode(bytes)?)
+}
+
+/// Decode the run-length encoding of differences, as [`decode_rle_delta`]
+/// does, with the error type of the other schemes.
+pub(crate) fn decode(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    crate::decode(bytes).map(|deltas| delta_decode(&deltas))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn differences_wrap_around() {
+        let bytes = [250, 255, 4, 9, 14, 3];
+
+        assert_eq!(delta_encode(&bytes), [250, 5, 5, 5, 5, 245]);
+        assert_eq!(delta_decode(&delta_encode(&bytes)), bytes);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn smooth_data_encodes_smaller() {
+        // A ramp wrapping around a few times, as from a steadily rising
+        // sensor reading.
+        let bytes: Vec<u8> = (0..1000).map(|i| (i * 3) as u8).collect();
+        let encoded = encode_delta_rle(&bytes);
+
+        assert!(encoded.len() * 10 < crate::encode(&bytes).len());
+        assert_eq!(decode_rle_delta(&encoded).unwrap(), bytes);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn decode_malformed_input() {
+        assert!(matches!(
+            decode_rle_delta(&[1]),
+            Err(RleError::Decode(DecodeError::TruncatedInput { at_byte: 0 }))
+        ));
+    }
+}
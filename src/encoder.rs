@@ -0,0 +1,175 @@
+//! An encoder fed its input a piece at a time.
+
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+use crate::runs;
+
+/// An encoder that takes its input a byte or a slice at a time, for input
+/// that arrives in pieces, such as network packets or UART frames, and
+/// doesn't have to be held in memory as a whole.
+///
+/// Runs may span any number of pieces, and each run is encoded as soon as it
+/// ends. The last run is only known to be complete once the input ends, so
+/// call [`Encoder::finish`] when done. The encoding is the one
+/// [`encode`](crate::encode) returns for the whole input.
+///
+/// With the `std` feature, the encoder is also a [`Write`](std::io::Write)
+/// sink, so that it can be copied into or wrapped in a
+/// [`BufWriter`](std::io::BufWriter).
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::Encoder;
+///
+/// let mut encoder = Encoder::new();
+/// encoder.push_slice(b"aa");
+/// encoder.push(b'a');
+/// encoder.push_slice(b"b");
+///
+/// assert_eq!(encoder.finish(), [b'a', 3, b'b', 1]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Encoder {
+    /// The byte of the current run and how many times it has occurred.
+    run: Option<(u8, u8)>,
+    /// The pairs of the runs that have ended.
+    out: Vec<u8>,
+}
+
+impl Encoder {
+    /// Create a new encoder, with no input yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Encode another byte of the input.
+    ///
+    /// * `byte` - The next byte of the input.
+    pub fn push(&mut self, byte: u8) {
+        self.push_run(byte, 1);
+    }
+
+    /// Encode the next bytes of the input.
+    ///
+    /// * `bytes` - The next bytes of the input.
+    pub fn push_slice(&mut self, bytes: &[u8]) {
+        for (byte, len) in runs(bytes) {
+            self.push_run(byte, len);
+        }
+    }
+
+    /// Encode the last run and return the encoding of the whole input.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.end_run();
+
+        self.out
+    }
+
+    /// Encode the current run, if any, as if the input ended here.
+    fn end_run(&mut self) {
+        if let Some((byte, occurrences)) = self.run.take() {
+            self.out.extend_from_slice(&[byte, occurrences]);
+        }
+    }
+
+    /// Encode a run of `len` bytes, which continues the current run if it's
+    /// of the same byte.
+    fn push_run(&mut self, byte: u8, mut len: usize) {
+        match self.run {
+            Some((b, occurrences)) if b == byte => len += usize::from(occurrences),
+            Some((b, occurrences)) => self.out.extend_from_slice(&[b, occurrences]),
+            None => {}
+        }
+
+        while len > 255 {
+            self.out.extend_from_slice(&[byte, 255]);
+            len -= 255;
+        }
+
+        self.run = Some((byte, len as u8));
+    }
+}
+
+#[cfg(feature = "std")]
+impl Write for Encoder {
+    /// Encode `buf`, as [`Encoder::push_slice`] does. Writing never fails.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.push_slice(buf);
+
+        Ok(buf.len())
+    }
+
+    /// Encode the current run, so that the encoding so far is complete. A run
+    /// continued by later writes is split in two pairs, which decode the same.
+    fn flush(&mut self) -> io::Result<()> {
+        self.end_run();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode;
+
+    #[test]
+    fn pieces_encode_as_a_whole() {
+        let bytes: Vec<u8> = (0..2000).map(|i| (i / 300) as u8).collect();
+
+        for piece in [1, 7, 255, 256, 1000] {
+            let mut encoder = Encoder::new();
+
+            for chunk in bytes.chunks(piece) {
+                encoder.push_slice(chunk);
+            }
+
+            assert_eq!(encoder.finish(), encode(&bytes));
+        }
+    }
+
+    #[test]
+    fn runs_split_at_255_across_pushes() {
+        let mut encoder = Encoder::new();
+
+        for _ in 0..256 {
+            encoder.push(7);
+        }
+
+        encoder.push_slice(&[7; 254]);
+
+        assert_eq!(encoder.finish(), [7, 255, 7, 255]);
+    }
+
+    #[test]
+    fn empty_input() {
+        let mut encoder = Encoder::new();
+        encoder.push_slice(&[]);
+
+        assert!(encoder.finish().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn copy_into_the_encoder() {
+        let bytes: Vec<u8> = (0..5000).map(|i| (i / 300) as u8).collect();
+        let mut encoder = io::BufWriter::with_capacity(64, Encoder::new());
+        io::copy(&mut &bytes[..], &mut encoder).unwrap();
+
+        assert_eq!(encoder.into_inner().unwrap().finish(), encode(&bytes));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn flush_ends_the_current_run() {
+        let mut encoder = Encoder::new();
+        encoder.write_all(b"aa").unwrap();
+        encoder.flush().unwrap();
+        encoder.write_all(b"ab").unwrap();
+
+        assert_eq!(encoder.finish(), [b'a', 2, b'a', 1, b'b', 1]);
+    }
+}
@@ -0,0 +1,376 @@
+//! The errors the library can return.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::error::Error;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+
+/// An error found while decoding malformed run-length encoded data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended in the middle of a run.
+    ///
+    /// * `at_byte` - The offset of the run left without a complete count.
+    TruncatedInput { at_byte: usize },
+    /// A run has a count of zero, which the encoder never produces.
+    ///
+    /// * `at_byte` - The offset of the run.
+    ZeroCount { at_byte: usize },
+    /// The decoded data would be longer than the limit set in
+    /// [`DecodeOptions`](crate::DecodeOptions).
+    ///
+    /// * `limit` - The limit, in bytes.
+    OutputTooLarge { limit: usize },
+    /// The decoded data has no byte at the offset looked for.
+    ///
+    /// * `offset` - The offset looked for.
+    /// * `len` - The length of the decoded data.
+    OffsetOutOfRange { offset: usize, len: u64 },
+    /// A run has a count too large to be decoded.
+    ///
+    /// * `at_byte` - The offset of the run.
+    CountOverflow { at_byte: usize },
+    /// A human-readable encoding is malformed.
+    ///
+    /// * `line` - The line of the problem, counting from 1.
+    /// * `column` - The column of the problem, counting from 1.
+    /// * `reason` - What the problem is.
+    InvalidText { line: usize, column: usize, reason: &'static str },
+    /// Hex input is malformed.
+    ///
+    /// * `at_byte` - The offset of the problem.
+    /// * `reason` - What the problem is.
+    InvalidHex { at_byte: usize, reason: &'static str },
+    /// Base64 input is malformed.
+    ///
+    /// * `at_byte` - The offset of the problem.
+    /// * `reason` - What the problem is.
+    InvalidBase64 { at_byte: usize, reason: &'static str },
+    /// A block of a file split into [`blocks`](crate::blocks) is corrupt.
+    ///
+    /// * `block` - The index of the block, counting from 0.
+    /// * `error` - What is wrong with it.
+    InvalidBlock { block: usize, error: Box<DecodeError> },
+    /// A frame of a file split into [`blocks`](crate::blocks) records a block
+    /// longer than any the encoder writes.
+    ///
+    /// * `block` - The index of the block, counting from 0.
+    /// * `len` - The length the frame records.
+    BlockTooLong { block: usize, len: u64 },
+    /// There is no block with the index asked for in the blocks written by
+    /// [`encode_blocks`](crate::encode_blocks).
+    ///
+    /// * `block` - The index asked for, counting from 0.
+    /// * `blocks` - The number of blocks there are.
+    BlockOutOfRange { block: usize, blocks: usize },
+    /// The table of an [archive](crate::create_archive) is malformed.
+    ///
+    /// * `reason` - What the problem is.
+    InvalidArchive { reason: &'static str },
+    /// An archive has no file with the name asked for: the name.
+    FileNotInArchive(String),
+    /// The input doesn't start with the magic number of an encoded file.
+    InvalidMagic,
+    /// The header names a version of the file format this library can't read.
+    UnsupportedVersion(u8),
+    /// The header names an encoding scheme this library doesn't know.
+    UnknownFormat(u8),
+    /// The header has flags set that this library doesn't know.
+    UnknownFlags(u8),
+    /// The input ended before the trailer its header calls for.
+    MissingTrailer,
+    /// The decoded data doesn't have the length recorded in the trailer.
+    ///
+    /// * `expected` - The length in the trailer.
+    /// * `actual` - The length of the decoded data.
+    LengthMismatch { expected: u64, actual: u64 },
+    /// The decoded data doesn't have the checksum recorded in the trailer.
+    ///
+    /// * `expected` - The checksum in the trailer.
+    /// * `actual` - The checksum of the decoded data.
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// Encoded files are followed by data that isn't one.
+    ///
+    /// * `offset` - The offset of the data after the last file.
+    TrailingData { offset: u64 },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TruncatedInput { at_byte } => write!(
+                f, "truncated input: run at offset {} has no count", at_byte
+            ),
+            Self::ZeroCount { at_byte } => write!(
+                f, "invalid input: run at offset {} has a count of zero", at_byte
+            ),
+            Self::OutputTooLarge { limit } => write!(
+                f, "invalid input: the decoded data would be longer than the limit of {} bytes", limit
+            ),
+            Self::OffsetOutOfRange { offset, len } => write!(
+                f, "no byte at offset {}: the decoded data is {} bytes long", offset, len
+            ),
+            Self::CountOverflow { at_byte } => write!(
+                f, "invalid input: run at offset {} has a count too large to decode", at_byte
+            ),
+            Self::InvalidText { line, column, reason } => write!(
+                f, "invalid input at line {}, column {}: {}", line, column, reason
+            ),
+            Self::InvalidHex { at_byte, reason } => write!(
+                f, "invalid hex at offset {}: {}", at_byte, reason
+            ),
+            Self::InvalidBase64 { at_byte, reason } => write!(
+                f, "invalid base64 at offset {}: {}", at_byte, reason
+            ),
+            Self::InvalidBlock { block, error } => write!(f, "block {}: {}", block, error),
+            Self::BlockTooLong { block, len } => write!(
+                f, "invalid input: block {} is {} bytes long, more than a block can be", block, len
+            ),
+            Self::BlockOutOfRange { block, blocks } => write!(
+                f, "no block {}: there are only {} blocks", block, blocks
+            ),
+            Self::InvalidArchive { reason } => write!(f, "invalid archive: {}", reason),
+            Self::FileNotInArchive(name) => write!(f, "no file '{}' in the archive", name),
+            Self::InvalidMagic => write!(f, "not an rle-rs file (bad magic)"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported file format version {}", v),
+            Self::UnknownFormat(id) => write!(f, "unknown encoding scheme {}", id),
+            Self::UnknownFlags(flags) => write!(f, "unknown header flags {:#04x}", flags),
+            Self::MissingTrailer => write!(f, "truncated input: the trailer is missing"),
+            Self::LengthMismatch { expected, actual } => write!(
+                f, "length mismatch: expected {} bytes, decoded {}", expected, actual
+            ),
+            Self::ChecksumMismatch { expected, actual } => write!(
+                f, "checksum mismatch: expected {:08x}, got {:08x}", expected, actual
+            ),
+            Self::TrailingData { offset } => write!(f, "trailing data at offset {} is not an encoded file", offset),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// An error found while reading pairs written as text by
+/// [`text::to_text`](crate::text::to_text).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// A pair isn't a byte in hex and a count: the pair.
+    InvalidFormat(String),
+    /// A count isn't a number from 0 to 255: the count.
+    InvalidCount(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidFormat(pair) => write!(f, "invalid pair '{}'", pair),
+            Self::InvalidCount(count) => write!(f, "invalid count '{}'", count),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// An error found while expanding runs with [`from_runs`](crate::from_runs),
+/// or turning one into a `[byte, count]` pair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunsError {
+    /// The runs add up to more bytes than can be held in memory.
+    ///
+    /// * `len` - Their total length, or `None` if it doesn't fit a `u64`.
+    TooLong { len: Option<u64> },
+    /// A run is empty or longer than 255 bytes, so no single pair holds it.
+    ///
+    /// * `len` - The length of the run.
+    NotAPair { len: u64 },
+}
+
+impl fmt::Display for RunsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TooLong { len: Some(len) } => {
+                write!(f, "the runs add up to {} bytes, more than fit in memory", len)
+            }
+            Self::TooLong { len: None } => write!(f, "the runs add up to more than 2^64 bytes"),
+            Self::NotAPair { len } => write!(f, "a run of {} bytes doesn't fit a single pair", len),
+        }
+    }
+}
+
+impl Error for RunsError {}
+
+/// An error found in the program's command-line arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// No argument was given at all.
+    NoArguments,
+    /// Only options were given.
+    NoFilepath,
+    /// An option that doesn't exist was given.
+    UnknownOption(String),
+    /// An option that takes a value was given none.
+    MissingValue(String),
+    /// An option was given a value it doesn't accept: the option and the
+    /// value.
+    InvalidValue(String, String),
+    /// `-o` was given along with more than one filepath, or with `-r`.
+    OutputWithManyFilepaths,
+    /// More than one of `-c`, `-o` and `--output-dir` was given.
+    ConflictingOutputs,
+    /// Two options that can't be used together were given.
+    ConflictingOptions(&'static str, &'static str),
+    /// `--suffix` was given an empty extension.
+    EmptySuffix,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoArguments => write!(f, "no argument was specified"),
+            Self::NoFilepath => write!(f, "no filepath was specified"),
+            Self::UnknownOption(o) => write!(f, "unknown option '{}'", o),
+            Self::MissingValue(o) => write!(f, "no value was specified for '{}'", o),
+            Self::InvalidValue(o, v) => write!(f, "invalid value '{}' for '{}'", v, o),
+            Self::OutputWithManyFilepaths => {
+                write!(f, "-o can't be used with more than one filepath or with -r")
+            }
+            Self::ConflictingOutputs => {
+                write!(f, "only one of -c, -o and --output-dir can be used")
+            }
+            Self::ConflictingOptions(a, b) => write!(f, "{} can't be used with {}", a, b),
+            Self::EmptySuffix => write!(f, "the suffix can't be empty"),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/// Any error the library or the program can return.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum RleError {
+    /// Reading or writing failed.
+    Io(io::Error),
+    /// Reading or writing a file other than the one being processed, such as
+    /// its output, failed.
+    ///
+    /// * `path` - The file.
+    /// * `error` - What went wrong.
+    File { path: PathBuf, error: io::Error },
+    /// The encoded data is malformed.
+    Decode(DecodeError),
+    /// The pairs written as text are malformed.
+    Parse(ParseError),
+    /// The program's command-line arguments are invalid.
+    Config(ConfigError),
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for RleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(e) => e.fmt(f),
+            Self::File { path, error } => write!(f, "'{}': {}", path.display(), error),
+            Self::Decode(e) => e.fmt(f),
+            Self::Parse(e) => e.fmt(f),
+            Self::Config(e) => e.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for RleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::File { error, .. } => Some(error),
+            Self::Decode(e) => Some(e),
+            Self::Parse(e) => Some(e),
+            Self::Config(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for RleError {
+    /// Readers and writers report malformed data as an
+    /// [`io::ErrorKind::InvalidData`] error wrapping a [`DecodeError`], which is
+    /// unwrapped here.
+    fn from(e: io::Error) -> Self {
+        match e.get_ref().and_then(|inner| inner.downcast_ref::<DecodeError>()) {
+            Some(decode) => Self::Decode(decode.clone()),
+            None => Self::Io(e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl RleError {
+    /// Return a function turning an error reading or writing `path` into a
+    /// [`RleError::File`], for use with [`Result::map_err`].
+    ///
+    /// * `path` - The file the error is about.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    ///
+    /// use rle_rs::RleError;
+    ///
+    /// let e = File::open("/no/such/file").map_err(RleError::at("/no/such/file")).unwrap_err();
+    ///
+    /// assert!(e.to_string().starts_with("'/no/such/file': "));
+    /// ```
+    pub fn at(path: impl Into<PathBuf>) -> impl FnOnce(io::Error) -> Self {
+        let path = path.into();
+
+        move |error| Self::File { path, error }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<DecodeError> for RleError {
+    fn from(e: DecodeError) -> Self {
+        Self::Decode(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<ParseError> for RleError {
+    fn from(e: ParseError) -> Self {
+        Self::Parse(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<ConfigError> for RleError {
+    fn from(e: ConfigError) -> Self {
+        Self::Config(e)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn io_errors_wrapping_decode_errors_are_unwrapped() {
+        let e = io::Error::new(io::ErrorKind::InvalidData, DecodeError::InvalidMagic);
+        assert!(matches!(RleError::from(e), RleError::Decode(DecodeError::InvalidMagic)));
+
+        let e = io::Error::other("disk on fire");
+        assert!(matches!(RleError::from(e), RleError::Io(_)));
+    }
+
+    #[test]
+    fn file_errors_name_the_file() {
+        let e = RleError::at("out/a.rle")(io::Error::new(io::ErrorKind::PermissionDenied, "denied"));
+
+        assert_eq!(e.to_string(), "'out/a.rle': denied");
+        assert_eq!(e.source().unwrap().to_string(), "denied");
+    }
+}
@@ -0,0 +1,131 @@
+//! An escape-byte run-length scheme, suited to text and other data with few
+//! runs.
+//!
+//! Bytes are copied as they are, except for:
+//!
+//! * Runs of 3 or more bytes, which become `ESCAPE, byte, count`.
+//! * The escape byte itself, which becomes `ESCAPE, ESCAPE`, even inside a
+//!   run.
+//!
+//! Unlike the byte-pair format, data without runs doesn't grow at all unless
+//! it contains the escape byte.
+
+use alloc::vec::Vec;
+
+use crate::DecodeError;
+
+/// The byte that starts a run, chosen for being rare in text.
+pub const ESCAPE: u8 = 0x1b;
+
+/// The shortest run worth encoding as one, since a run takes 3 bytes.
+const MIN_RUN: usize = 3;
+
+/// Read a byte slice and return its escape-byte encoding.
+///
+/// * `bytes` - The bytes to be encoded.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::escape::{self, ESCAPE};
+///
+/// assert_eq!(escape::encode(b"abbbbc"), [b'a', ESCAPE, b'b', 4, b'c']);
+/// assert_eq!(escape::encode(&[ESCAPE]), [ESCAPE, ESCAPE]);
+/// ```
+pub fn encode(bytes: &[u8]) -> Vec<u8> {
+    let mut encoding = Vec::with_capacity(bytes.len());
+
+    for (byte, count) in crate::runs::pairs(bytes, u8::MAX) {
+        if byte == ESCAPE {
+            for _ in 0..count {
+                encoding.extend(&[ESCAPE, ESCAPE]);
+            }
+        } else if count as usize >= MIN_RUN {
+            encoding.extend(&[ESCAPE, byte, count]);
+        } else {
+            encoding.extend(core::iter::repeat_n(byte, count as usize));
+        }
+    }
+
+    encoding
+}
+
+/// Read an escape-byte encoding and return its decoded contents.
+///
+/// * `bytes` - The bytes to be decoded.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::TruncatedInput`] if the input ends in the middle of
+/// an escape sequence.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::escape::{self, ESCAPE};
+///
+/// assert_eq!(escape::decode(&[b'a', ESCAPE, b'b', 4, b'c']).unwrap(), b"abbbbc");
+/// assert!(escape::decode(&[b'a', ESCAPE]).is_err());
+/// ```
+pub fn decode(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let mut decoding = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != ESCAPE {
+            decoding.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+
+        let truncated = DecodeError::TruncatedInput { at_byte: i };
+
+        match *bytes.get(i + 1).ok_or(truncated.clone())? {
+            ESCAPE => {
+                decoding.push(ESCAPE);
+                i += 2;
+            }
+            byte => {
+                let count = *bytes.get(i + 2).ok_or(truncated)?;
+                decoding.extend(core::iter::repeat_n(byte, count as usize));
+                i += 3;
+            }
+        }
+    }
+
+    Ok(decoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_runs_pass_through() {
+        assert_eq!(encode(b"aabcc"), b"aabcc");
+        assert_eq!(encode(&[]), []);
+    }
+
+    #[test]
+    fn long_runs_are_split() {
+        assert_eq!(encode(&[7; 300]), [ESCAPE, 7, 255, ESCAPE, 7, 45]);
+        assert_eq!(encode(&[7; 257]), [ESCAPE, 7, 255, 7, 7]);
+    }
+
+    #[test]
+    fn round_trip_with_escape_bytes() {
+        // Escape bytes on their own, in a run, and between runs.
+        let bytes = [
+            &b"text"[..], &[ESCAPE], b"more", &[ESCAPE; 5], b"aaaa", &[ESCAPE], b"bbbb", &[ESCAPE],
+        ]
+        .concat();
+
+        assert_eq!(decode(&encode(&bytes)), Ok(bytes));
+    }
+
+    #[test]
+    fn decode_dangling_escape_is_an_error() {
+        assert_eq!(decode(&[b'a', ESCAPE]), Err(DecodeError::TruncatedInput { at_byte: 1 }));
+        assert_eq!(decode(&[ESCAPE, b'a']), Err(DecodeError::TruncatedInput { at_byte: 0 }));
+    }
+}
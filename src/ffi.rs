@@ -0,0 +1,139 @@
+//! C bindings to the codec, built with the `ffi` feature. The header for them
+//! is generated into `include/rle.h`.
+//!
+//! Every function writes its result into a buffer supplied by the caller and
+//! returns [`RLE_OK`] or one of the negative `RLE_ERR_*` codes.
+
+use std::slice;
+
+/// The operation succeeded.
+pub const RLE_OK: i32 = 0;
+/// The input isn't valid run-length encoded data.
+pub const RLE_ERR_DECODE: i32 = -1;
+/// The output buffer is too small; `*output_len` is set to the size needed.
+pub const RLE_ERR_BUFFER_TOO_SMALL: i32 = -2;
+/// A pointer that may not be null was.
+pub const RLE_ERR_NULL_POINTER: i32 = -3;
+
+/// Run-length encode `input_len` bytes from `input` into `output`.
+///
+/// On success, and when the output buffer is too small, `*output_len` is set
+/// to the length of the encoding.
+///
+/// # Safety
+///
+/// `input` must point to `input_len` readable bytes (or may be null if
+/// `input_len` is 0), `output` to `output_max` writable bytes (or may be null
+/// if `output_max` is 0), and `output_len` to a writable `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn rle_encode(
+    input: *const u8, input_len: usize, output: *mut u8, output_max: usize, output_len: *mut usize
+) -> i32 {
+    match input_slice(input, input_len) {
+        Some(bytes) => write_output(&crate::encode(bytes), output, output_max, output_len),
+        None => RLE_ERR_NULL_POINTER,
+    }
+}
+
+/// Decode `input_len` run-length encoded bytes from `input` into `output`.
+///
+/// On success, and when the output buffer is too small, `*output_len` is set
+/// to the length of the decoded data.
+///
+/// # Safety
+///
+/// The same as for [`rle_encode`].
+#[no_mangle]
+pub unsafe extern "C" fn rle_decode(
+    input: *const u8, input_len: usize, output: *mut u8, output_max: usize, output_len: *mut usize
+) -> i32 {
+    let bytes = match input_slice(input, input_len) {
+        Some(bytes) => bytes,
+        None => return RLE_ERR_NULL_POINTER,
+    };
+
+    match crate::decode(bytes) {
+        Ok(decoded) => write_output(&decoded, output, output_max, output_len),
+        Err(_) => RLE_ERR_DECODE,
+    }
+}
+
+/// Return the `len` bytes at `ptr`, or `None` if `ptr` is null but `len`
+/// isn't 0.
+unsafe fn input_slice<'a>(ptr: *const u8, len: usize) -> Option<&'a [u8]> {
+    match (ptr.is_null(), len) {
+        (_, 0) => Some(&[]),
+        (true, _) => None,
+        (false, _) => Some(slice::from_raw_parts(ptr, len)),
+    }
+}
+
+/// Copy `result` into the caller's buffer and set `*output_len`.
+unsafe fn write_output(
+    result: &[u8], output: *mut u8, output_max: usize, output_len: *mut usize
+) -> i32 {
+    if output_len.is_null() {
+        return RLE_ERR_NULL_POINTER;
+    }
+
+    *output_len = result.len();
+
+    if result.len() > output_max {
+        return RLE_ERR_BUFFER_TOO_SMALL;
+    }
+
+    if !result.is_empty() {
+        if output.is_null() {
+            return RLE_ERR_NULL_POINTER;
+        }
+
+        output.copy_from_nonoverlapping(result.as_ptr(), result.len());
+    }
+
+    RLE_OK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    #[test]
+    fn round_trip() {
+        let (mut encoded, mut decoded) = ([0; 16], [0; 16]);
+        let (mut encoded_len, mut decoded_len) = (0, 0);
+
+        unsafe {
+            let status = rle_encode(b"aaab".as_ptr(), 4, encoded.as_mut_ptr(), 16, &mut encoded_len);
+            assert_eq!((status, &encoded[..encoded_len]), (RLE_OK, &[b'a', 3, b'b', 1][..]));
+
+            let status =
+                rle_decode(encoded.as_ptr(), encoded_len, decoded.as_mut_ptr(), 16, &mut decoded_len);
+            assert_eq!((status, &decoded[..decoded_len]), (RLE_OK, &b"aaab"[..]));
+        }
+    }
+
+    #[test]
+    fn buffer_too_small_reports_the_size_needed() {
+        let mut output = [0; 2];
+        let mut output_len = 0;
+
+        let status = unsafe { rle_encode(b"ab".as_ptr(), 2, output.as_mut_ptr(), 2, &mut output_len) };
+
+        assert_eq!((status, output_len), (RLE_ERR_BUFFER_TOO_SMALL, 4));
+    }
+
+    #[test]
+    fn errors() {
+        let mut output_len = 0;
+
+        unsafe {
+            assert_eq!(rle_decode([7].as_ptr(), 1, ptr::null_mut(), 0, &mut output_len), RLE_ERR_DECODE);
+            assert_eq!(rle_encode(ptr::null(), 1, ptr::null_mut(), 0, &mut output_len), RLE_ERR_NULL_POINTER);
+            assert_eq!(rle_encode(ptr::null(), 0, ptr::null_mut(), 0, ptr::null_mut()), RLE_ERR_NULL_POINTER);
+            assert_eq!(rle_encode(ptr::null(), 0, ptr::null_mut(), 0, &mut output_len), RLE_OK);
+        }
+
+        assert_eq!(output_len, 0);
+    }
+}
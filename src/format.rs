@@ -0,0 +1,1213 @@
+//! The file format: a small header naming the encoding scheme, followed by
+//! the encoded data and a trailer to check it with.
+//!
+//! | Bytes   | Contents                                         |
+//! |---------|--------------------------------------------------|
+//! | 0..4    | The magic number, `b"RLE\x01"`                   |
+//! | 4       | The format version, currently `2`                |
+//! | 5       | The [`Format`] id of the data                    |
+//! | 6       | Flags; bit 0 is set if the file has a trailer,   |
+//! |         | bit 1 if the data is split into [`blocks`]       |
+//! | 7..     | The encoded data                                 |
+//! | last 12 | The [`Trailer`]: the CRC-32 and length of the    |
+//! |         | decoded data, as little-endian `u32` and `u64`   |
+//!
+//! Version 1 headers have no flags byte, and their files no trailer.
+
+use alloc::vec::Vec;
+use core::convert::TryInto;
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
+
+use crate::{
+    bits, blocks, crc32, decode_u16, delta, encode_delta_rle, encode_u16, escape, packbits, pcx, rle0, text, units, varint,
+    Crc32, DecodeError,
+};
+
+/// The bytes every encoded file starts with.
+pub const MAGIC: [u8; 4] = *b"RLE\x01";
+
+/// The version of the file format written by this library.
+pub const VERSION: u8 = 2;
+
+/// The header flag set when the file ends with a [`Trailer`].
+const FLAG_CHECKSUM: u8 = 1;
+
+/// The header flag set when the data is a sequence of [`blocks`].
+const FLAG_BLOCKS: u8 = 2;
+
+/// The encoding schemes a file can be encoded with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// `[byte, count]` pairs, as produced by [`encode`](crate::encode).
+    Byte,
+    /// `[byte, count_lo, count_hi]` triples, as produced by
+    /// [`encode_u16`](crate::encode_u16).
+    U16,
+    /// Apple's PackBits, as produced by [`packbits::encode`].
+    PackBits,
+    /// The data as it is, for data that wouldn't shrink when encoded.
+    Stored,
+    /// Escaped runs among plain bytes, as produced by [`escape::encode`].
+    Escape,
+    /// Runs with variable-length counts, as produced by [`varint::encode`].
+    Varint,
+    /// Runs of zeros among plain bytes, as produced by [`rle0::encode`].
+    Rle0,
+    /// Runs of 2-byte units, as produced by [`units::encode`].
+    Unit2,
+    /// Runs of 4-byte units, as produced by [`units::encode`].
+    Unit4,
+    /// Runs of bits, as produced by [`bits::encode`].
+    Bits,
+    /// Human-readable runs, as produced by [`text::encode`].
+    Text,
+    /// The compression of PCX images, as produced by [`pcx::encode`].
+    Pcx,
+    /// `[byte, count]` pairs of differences between bytes, as produced by
+    /// [`encode_delta_rle`].
+    Delta,
+}
+
+impl Format {
+    /// Return the id identifying this scheme in a header.
+    pub fn id(self) -> u8 {
+        match self {
+            Self::Byte => 0,
+            Self::U16 => 1,
+            Self::PackBits => 2,
+            Self::Stored => 3,
+            Self::Escape => 4,
+            Self::Varint => 5,
+            Self::Rle0 => 6,
+            Self::Unit2 => 7,
+            Self::Unit4 => 8,
+            Self::Bits => 9,
+            Self::Text => 10,
+            Self::Pcx => 11,
+            Self::Delta => 12,
+        }
+    }
+
+    /// Return the scheme identified by `id` in a header, if any.
+    ///
+    /// * `id` - The id of the scheme.
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Self::Byte),
+            1 => Some(Self::U16),
+            2 => Some(Self::PackBits),
+            3 => Some(Self::Stored),
+            4 => Some(Self::Escape),
+            5 => Some(Self::Varint),
+            6 => Some(Self::Rle0),
+            7 => Some(Self::Unit2),
+            8 => Some(Self::Unit4),
+            9 => Some(Self::Bits),
+            10 => Some(Self::Text),
+            11 => Some(Self::Pcx),
+            12 => Some(Self::Delta),
+            _ => None,
+        }
+    }
+
+    /// Encode `bytes` with this scheme, without a header.
+    ///
+    /// * `bytes` - The bytes to be encoded.
+    pub fn encode(self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Byte => crate::encode(bytes),
+            Self::U16 => encode_u16(bytes),
+            Self::PackBits => packbits::encode(bytes),
+            Self::Stored => bytes.to_vec(),
+            Self::Escape => escape::encode(bytes),
+            Self::Varint => varint::encode(bytes),
+            Self::Rle0 => rle0::encode(bytes),
+            Self::Unit2 => units::encode(bytes, 2),
+            Self::Unit4 => units::encode(bytes, 4),
+            Self::Bits => bits::encode(bytes),
+            Self::Text => text::encode(bytes).into_bytes(),
+            Self::Pcx => pcx::encode(bytes),
+            Self::Delta => encode_delta_rle(bytes),
+        }
+    }
+
+    /// Decode `bytes`, which were encoded with this scheme and have no
+    /// header.
+    ///
+    /// * `bytes` - The bytes to be decoded.
+    pub fn decode(self, bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        match self {
+            Self::Byte => crate::decode(bytes),
+            Self::U16 => decode_u16(bytes),
+            Self::PackBits => packbits::decode(bytes),
+            Self::Stored => Ok(bytes.to_vec()),
+            Self::Escape => escape::decode(bytes),
+            Self::Varint => varint::decode(bytes),
+            Self::Rle0 => rle0::decode(bytes),
+            Self::Unit2 => units::decode(bytes, 2),
+            Self::Unit4 => units::decode(bytes, 4),
+            Self::Bits => bits::decode(bytes),
+            Self::Text => text::decode(bytes),
+            Self::Pcx => pcx::decode(bytes),
+            Self::Delta => delta::decode(bytes),
+        }
+    }
+}
+
+/// The header at the start of an encoded file.
+///
+/// * `version` - The version of the file format.
+/// * `format` - The scheme the data following the header is encoded with.
+/// * `checksum` - Whether the file ends with a [`Trailer`]. Always false for
+///   version 1 files.
+/// * `blocks` - Whether the data is a sequence of [`blocks`] encoded with
+///   `format`, instead of a single encoding. Always false for version 1
+///   files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub version: u8,
+    pub format: Format,
+    pub checksum: bool,
+    pub blocks: bool,
+}
+
+impl Header {
+    /// The length of the longest header, in bytes.
+    pub const MAX_LEN: usize = 7;
+
+    /// Create the header of a file in the current version of the format,
+    /// with a trailer and without blocks.
+    ///
+    /// * `format` - The scheme the data is encoded with.
+    pub fn new(format: Format) -> Self {
+        Self { version: VERSION, format, checksum: true, blocks: false }
+    }
+
+    /// Return the size of this header, in bytes.
+    pub fn size(self) -> usize {
+        if self.version == 1 { 6 } else { 7 }
+    }
+
+    /// Return the bytes of this header.
+    pub fn to_bytes(self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(&[self.version, self.format.id()]);
+
+        if self.version > 1 {
+            let checksum = if self.checksum { FLAG_CHECKSUM } else { 0 };
+            let blocks = if self.blocks { FLAG_BLOCKS } else { 0 };
+            bytes.push(checksum | blocks);
+        }
+
+        bytes
+    }
+
+    /// Read a header from the start of `bytes` and return it along with the
+    /// data that follows it.
+    ///
+    /// * `bytes` - The contents of an encoded file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DecodeError::InvalidMagic`] if `bytes` doesn't start with
+    /// [`MAGIC`], and [`DecodeError::UnsupportedVersion`],
+    /// [`DecodeError::UnknownFormat`] or [`DecodeError::UnknownFlags`] if the
+    /// header isn't one this library can read.
+    pub fn parse(bytes: &[u8]) -> Result<(Self, &[u8]), DecodeError> {
+        if !bytes.starts_with(&MAGIC) {
+            return Err(DecodeError::InvalidMagic);
+        }
+
+        let version = *bytes.get(4).ok_or(DecodeError::TruncatedInput { at_byte: bytes.len() })?;
+
+        if version == 0 || version > VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+
+        let len = if version == 1 { 6 } else { 7 };
+
+        if bytes.len() < len {
+            return Err(DecodeError::TruncatedInput { at_byte: bytes.len() });
+        }
+
+        let format = Format::from_id(bytes[5]).ok_or(DecodeError::UnknownFormat(bytes[5]))?;
+        let flags = if version == 1 { 0 } else { bytes[6] };
+
+        if flags & !(FLAG_CHECKSUM | FLAG_BLOCKS) != 0 {
+            return Err(DecodeError::UnknownFlags(flags));
+        }
+
+        let checksum = flags & FLAG_CHECKSUM != 0;
+        let blocks = flags & FLAG_BLOCKS != 0;
+
+        Ok((Self { version, format, checksum, blocks }, &bytes[len..]))
+    }
+
+    /// Decode `bytes`, the data following this header, without its trailer.
+    ///
+    /// * `bytes` - The encoded data.
+    pub fn decode(self, bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        if self.blocks {
+            blocks::decode(bytes, self.format)
+        } else {
+            self.format.decode(bytes)
+        }
+    }
+
+    /// Return the length of the run that `data`, data following this header,
+    /// starts with and the length of its decoding, from only as much of it
+    /// as tells them, so that the runs can be walked through without
+    /// decoding them.
+    ///
+    /// Returns `None` if `data` ends before that or the run is malformed, for
+    /// the bytes left over after the last run of the units schemes, which
+    /// aren't a run, and for the bits scheme, whose runs are of bits.
+    ///
+    /// * `data` - The encoded data from the start of the run on.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rle_rs::{Format, Header};
+    ///
+    /// assert_eq!(Header::new(Format::Byte).run_at(b"a\x03b\x01"), Some((2, 3)));
+    /// assert_eq!(Header::new(Format::Varint).run_at(&[b'a', 0xac, 0x02]), Some((3, 300)));
+    /// assert_eq!(Header::new(Format::Byte).run_at(b"a"), None);
+    /// ```
+    pub fn run_at(self, data: &[u8]) -> Option<(usize, u64)> {
+        if self.blocks {
+            return blocks::frame_len(data);
+        }
+
+        let first = *data.first()?;
+        let count = |i: usize| data.get(i).map(|&count| u64::from(count));
+
+        match self.format {
+            Format::Byte | Format::Delta => count(1).filter(|&count| count > 0).map(|count| (2, count)),
+            Format::U16 => {
+                let count = u16::from_le_bytes([*data.get(1)?, *data.get(2)?]);
+                (count > 0).then_some((3, count.into()))
+            }
+            Format::PackBits => Some(match first as i8 {
+                0..=127 => (usize::from(first) + 2, u64::from(first) + 1),
+                -128 => (1, 0),
+                header => (2, (1 - i64::from(header)) as u64),
+            }),
+            Format::Stored => Some((1, 1)),
+            Format::Escape if first != escape::ESCAPE => Some((1, 1)),
+            Format::Escape if *data.get(1)? == escape::ESCAPE => Some((2, 1)),
+            Format::Escape => count(2).map(|count| (3, count)),
+            Format::Varint => match varint::read_count(data, 1, 0) {
+                Ok((count, next)) if count > 0 => Some((next, count)),
+                _ => None,
+            },
+            Format::Rle0 if first == 0 => count(1).map(|count| (2, count + 1)),
+            Format::Rle0 => Some((1, 1)),
+            Format::Unit2 => count(2).map(|count| (3, 2 * count)),
+            Format::Unit4 => count(4).map(|count| (5, 4 * count)),
+            Format::Bits => None,
+            Format::Text => text::run_len(data),
+            Format::Pcx if first < pcx::COUNT_FLAG => Some((1, 1)),
+            Format::Pcx => {
+                data.get(1)?;
+                let count = u64::from(first & !pcx::COUNT_FLAG);
+                (count > 0).then_some((2, count))
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    /// Write this header to `w`.
+    ///
+    /// * `w` - The writer that receives the header.
+    pub fn write_to(self, w: &mut impl Write) -> io::Result<()> {
+        w.write_all(&self.to_bytes())
+    }
+
+    /// Read a header from `r`, leaving it at the start of the encoded data.
+    ///
+    /// * `r` - The reader the encoded file comes from.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`], wrapping a [`DecodeError`],
+    /// if `r` doesn't start with a valid header. See [`Header::parse`].
+    #[cfg(feature = "std")]
+    pub fn read_from(r: &mut impl Read) -> io::Result<Self> {
+        let mut bytes = [0; Self::MAX_LEN];
+        // Only version 1 headers are shorter than the longest one, so the
+        // version is read before the rest.
+        let mut len = read_up_to(r, &mut bytes[..5])?;
+
+        if len == 5 {
+            let rest = if bytes[4] == 1 { 6 } else { Self::MAX_LEN };
+            len += read_up_to(r, &mut bytes[5..rest])?;
+        }
+
+        Self::parse(&bytes[..len])
+            .map(|(header, _)| header)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Fill as much of `buf` as possible from `r` and return how much was read,
+/// which is less than `buf.len()` only if `r` ended.
+#[cfg(feature = "std")]
+pub(crate) fn read_up_to(r: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+
+    while len < buf.len() {
+        match r.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(len)
+}
+
+/// The trailer at the end of an encoded file, describing the decoded data.
+///
+/// * `crc` - The CRC-32 of the decoded data.
+/// * `len` - The length of the decoded data, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trailer {
+    pub crc: u32,
+    pub len: u64,
+}
+
+impl Trailer {
+    /// The length of a trailer, in bytes.
+    pub const LEN: usize = 12;
+
+    /// Return the trailer describing `bytes`.
+    ///
+    /// * `bytes` - The decoded data.
+    pub fn of(bytes: &[u8]) -> Self {
+        Self { crc: crc32(bytes), len: bytes.len() as u64 }
+    }
+
+    /// Return the bytes of this trailer.
+    pub fn to_bytes(self) -> [u8; Self::LEN] {
+        let mut bytes = [0; Self::LEN];
+        bytes[..4].copy_from_slice(&self.crc.to_le_bytes());
+        bytes[4..].copy_from_slice(&self.len.to_le_bytes());
+
+        bytes
+    }
+
+    /// Read a trailer from its bytes.
+    ///
+    /// * `bytes` - The bytes of the trailer.
+    pub fn from_bytes(bytes: [u8; Self::LEN]) -> Self {
+        Self {
+            crc: u32::from_le_bytes(bytes[..4].try_into().unwrap()),
+            len: u64::from_le_bytes(bytes[4..].try_into().unwrap()),
+        }
+    }
+
+    /// Split the trailer off the end of `bytes` and return the data before
+    /// it along with the trailer.
+    ///
+    /// * `bytes` - The encoded data, trailer included.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DecodeError::MissingTrailer`] if `bytes` is too short to
+    /// hold a trailer.
+    pub fn split_from(bytes: &[u8]) -> Result<(&[u8], Self), DecodeError> {
+        if bytes.len() < Self::LEN {
+            return Err(DecodeError::MissingTrailer);
+        }
+
+        let (data, trailer) = bytes.split_at(bytes.len() - Self::LEN);
+
+        Ok((data, Self::from_bytes(trailer.try_into().unwrap())))
+    }
+
+    /// Check that the decoded data matches this trailer.
+    ///
+    /// * `actual` - The trailer of the data actually decoded.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DecodeError::LengthMismatch`] or
+    /// [`DecodeError::ChecksumMismatch`] if the data doesn't match.
+    pub fn check(self, actual: Self) -> Result<(), DecodeError> {
+        self.check_len(actual.len)?;
+
+        if self.crc != actual.crc {
+            return Err(DecodeError::ChecksumMismatch { expected: self.crc, actual: actual.crc });
+        }
+
+        Ok(())
+    }
+
+    /// Check that the decoded data has the length recorded in this trailer,
+    /// for when its checksum isn't wanted.
+    ///
+    /// * `len` - The length of the data actually decoded.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DecodeError::LengthMismatch`] if the lengths differ.
+    pub fn check_len(self, len: u64) -> Result<(), DecodeError> {
+        if self.len != len {
+            return Err(DecodeError::LengthMismatch { expected: self.len, actual: len });
+        }
+
+        Ok(())
+    }
+}
+
+/// Encode `bytes` with `format` and return the result between a header and a
+/// trailer.
+///
+/// * `bytes` - The bytes to be encoded.
+/// * `format` - The scheme to encode with.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::{Format, MAGIC};
+///
+/// let encoded = rle_rs::encode_with_header(b"aaab", Format::Byte);
+///
+/// assert!(encoded.starts_with(&MAGIC));
+/// assert_eq!(rle_rs::decode_with_header(&encoded).unwrap(), b"aaab");
+/// ```
+pub fn encode_with_header(bytes: &[u8], format: Format) -> Vec<u8> {
+    let mut encoding = Header::new(format).to_bytes();
+    encoding.extend(format.encode(bytes));
+    encoding.extend(&Trailer::of(bytes).to_bytes());
+
+    encoding
+}
+
+/// Decode the contents of an encoded file, using the scheme named by its
+/// header, and check the result against its trailer.
+///
+/// Files without a trailer, from version 1 of the format, are decoded without
+/// being checked.
+///
+/// * `bytes` - The contents of the file, header included.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::InvalidMagic`] if `bytes` doesn't start with a
+/// header, [`DecodeError::ChecksumMismatch`] or
+/// [`DecodeError::LengthMismatch`] if the result doesn't match the trailer,
+/// or any error found while reading the header or decoding the data.
+pub fn decode_with_header(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let (header, data) = Header::parse(bytes)?;
+
+    if !header.checksum {
+        return header.decode(data);
+    }
+
+    let (data, trailer) = Trailer::split_from(data)?;
+
+    // A run may decode to far more than memory holds, so the length is
+    // checked first, from the runs.
+    if let Some(len) = decoded_len(header, data) {
+        trailer.check_len(len)?;
+    }
+
+    let decoding = header.decode(data)?;
+    trailer.check(Trailer::of(&decoding))?;
+
+    Ok(decoding)
+}
+
+/// Decode the first of the encoded files that `data` starts with, the data
+/// following `header`, and return its decoding along with the length of its
+/// data, without its trailer.
+///
+/// Nothing records where the data of a file ends, so it's taken to end at
+/// the first trailer that checks out and is followed by another file or the
+/// end of `data`, or failing that, at the last that checks out. The runs are walked through with
+/// [`Header::run_at`], and only decoded where a trailer recording the length
+/// decoded so far follows them, so that finding the end takes a single pass
+/// whatever follows it. A file without a trailer, from version 1 of the
+/// format, runs to the end.
+///
+/// * `header` - The header of the file.
+/// * `data` - The data following the header, and whatever follows the file.
+///
+/// # Errors
+///
+/// Returns the errors [`decode_with_header`] would for the header followed
+/// by all of `data`, if no trailer checks out.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::{Format, Header};
+///
+/// let mut encoded = rle_rs::encode_with_header(b"aaab", Format::Byte);
+/// encoded.extend(b"more");
+/// let (header, data) = Header::parse(&encoded).unwrap();
+///
+/// assert_eq!(rle_rs::decode_member(header, data).unwrap(), (b"aaab".to_vec(), 4));
+/// ```
+pub fn decode_member(header: Header, data: &[u8]) -> Result<(Vec<u8>, usize), DecodeError> {
+    if !header.checksum {
+        return header.decode(data).map(|decoding| (decoding, data.len()));
+    }
+
+    let walked = if header.format == Format::Bits && !header.blocks { walk_bits(data) } else { walk(header, data) };
+
+    let walked_len = match walked {
+        Ok(member) => return Ok(member),
+        Err(len) => len,
+    };
+
+    let (encoded, trailer) = Trailer::split_from(data)?;
+
+    // The runs before the last trailer were walked through, so their length
+    // is known without decoding them.
+    if let Some(len) = walked_len {
+        trailer.check_len(len)?;
+    }
+
+    let decoding = header.decode(encoded)?;
+    trailer.check(Trailer::of(&decoding))?;
+
+    Ok((decoding, encoded.len()))
+}
+
+/// Decode several encoded files one after another, as written by
+/// `cat a.rle b.rle`, and return their decodings one after another.
+///
+/// Each file is taken to end at the trailer [`decode_member`] finds. A file without a trailer, from version 1 of
+/// the format, runs to the end.
+///
+/// * `bytes` - The contents of the files, headers included.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::TrailingData`] if the files are followed by data
+/// that doesn't start with a header, and the same errors as
+/// [`decode_with_header`] for whichever file they're found in.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::Format;
+///
+/// let mut encoded = rle_rs::encode_with_header(b"aaab", Format::Byte);
+/// encoded.extend(rle_rs::encode_with_header(b"cd", Format::PackBits));
+///
+/// assert_eq!(rle_rs::decode_members(&encoded).unwrap(), b"aaabcd");
+/// ```
+pub fn decode_members(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    members(bytes, false).map(|(decoding, _)| decoding)
+}
+
+/// Decode as many encoded files as `bytes` starts with, as [`decode_members`]
+/// does, and return their decodings along with the offset of the data after
+/// them that isn't a file, or `bytes.len()` if there's none.
+///
+/// * `bytes` - The contents of the files, headers included.
+///
+/// # Errors
+///
+/// Returns the same errors as [`decode_with_header`] if the first file is
+/// malformed.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::Format;
+///
+/// let mut encoded = rle_rs::encode_with_header(b"aaab", Format::Byte);
+/// let len = encoded.len();
+/// encoded.extend(b"garbage");
+///
+/// assert_eq!(rle_rs::decode_members_lenient(&encoded).unwrap(), (b"aaab".to_vec(), len));
+/// ```
+pub fn decode_members_lenient(bytes: &[u8]) -> Result<(Vec<u8>, usize), DecodeError> {
+    members(bytes, true)
+}
+
+/// Decode the files `bytes` is made of, and return their decodings along
+/// with the offset of the end of the last one.
+///
+/// * `bytes` - The contents of the files, headers included.
+/// * `lenient` - Whether the files may be followed by other data.
+fn members(bytes: &[u8], lenient: bool) -> Result<(Vec<u8>, usize), DecodeError> {
+    let mut decoding = Vec::new();
+    let mut at = 0;
+
+    loop {
+        let member = Header::parse(&bytes[at..]).and_then(|(header, data)| {
+            let trailer_len = if header.checksum { Trailer::LEN } else { 0 };
+
+            decode_member(header, data).map(|(decoding, len)| (decoding, header.size() + len + trailer_len))
+        });
+
+        match member {
+            Ok((member, len)) => {
+                decoding.extend(member);
+                at += len;
+            }
+            // Whatever follows the files that decode is other data.
+            Err(_) if lenient && at > 0 => return Ok((decoding, at)),
+            Err(DecodeError::InvalidMagic) if at > 0 => return Err(DecodeError::TrailingData { offset: at as u64 }),
+            Err(e) => return Err(e),
+        }
+
+        if at == bytes.len() {
+            return Ok((decoding, at));
+        }
+    }
+}
+
+/// Walk through the runs of the data of a file, `data` along with whatever
+/// follows it, to the first run a trailer that checks out follows, and
+/// return the decoding of the runs before it and their length. A trailer
+/// followed by neither another file nor the end of `data` only ends it if
+/// it's the last that checks out, and no other does.
+///
+/// Otherwise, return the length of the decoding of the runs before the last
+/// [`Trailer::LEN`] bytes of `data`, if the runs walked through end there.
+///
+/// * `header` - The header of the file, which has a trailer.
+/// * `data` - The data following the header.
+fn walk(header: Header, data: &[u8]) -> Result<(Vec<u8>, usize), Option<u64>> {
+    // The bytes left over after the last run of the units schemes, fewer than
+    // a unit, end the data too.
+    let left_over = match header.format {
+        _ if header.blocks => 0,
+        Format::Unit2 => 1,
+        Format::Unit4 => 3,
+        _ => 0,
+    };
+    let end = data.len().checked_sub(Trailer::LEN).ok_or(None)?;
+    let mut decoding = Vec::new();
+    let mut crc = Crc32::new();
+    // The runs before `done` are decoded, and those before `at` decode to
+    // `len` bytes.
+    let mut done = 0;
+    let mut at = 0;
+    let mut len = 0;
+    // The length of the decoding of the runs before the last trailer that
+    // checked out but isn't followed by another file, their end and how many
+    // bytes are left over after them.
+    let mut last = None;
+
+    let walked_len = 'walk: loop {
+        for extra in 0..=left_over.min(end - at) {
+            let trailer = Trailer::from_bytes(data[at + extra..at + extra + Trailer::LEN].try_into().unwrap());
+
+            if trailer.len != len + extra as u64 {
+                continue;
+            }
+
+            // Only runs a plausible trailer follows are decoded, each once.
+            if done < at {
+                let more = match decode_after(header, &decoding, &data[done..at]) {
+                    Ok(more) => more,
+                    Err(_) => break 'walk None,
+                };
+                crc.update(&more);
+                decoding.extend(more);
+                done = at;
+            }
+
+            let tail = &data[at..at + extra];
+            let mut checked = crc;
+            checked.update(tail);
+
+            if checked.value() != trailer.crc {
+                continue;
+            }
+
+            if starts_member(&data[at + extra + Trailer::LEN..]) {
+                decoding.extend(tail);
+                return Ok((decoding, at + extra));
+            }
+
+            last = Some((decoding.len(), at, extra));
+        }
+
+        match header.run_at(&data[at..]) {
+            Some((run, run_len)) if run <= end - at => {
+                at += run;
+                len += run_len;
+            }
+            _ => break Some(len).filter(|_| at == end),
+        }
+    };
+
+    match last {
+        Some((decoded, at, extra)) => {
+            decoding.truncate(decoded);
+            decoding.extend(&data[at..at + extra]);
+
+            Ok((decoding, at + extra))
+        }
+        None => Err(walked_len),
+    }
+}
+
+/// Return whether `bytes`, what follows a trailer, is the end of the data or
+/// another file.
+///
+/// * `bytes` - The bytes after the trailer.
+fn starts_member(bytes: &[u8]) -> bool {
+    bytes.is_empty() || !matches!(Header::parse(bytes), Err(DecodeError::InvalidMagic))
+}
+
+/// Return the length of the decoding of `data`, all of the data of a file
+/// following `header`, by walking through its runs without decoding them, or
+/// `None` if they're malformed.
+///
+/// * `header` - The header of the file.
+/// * `data` - The data of the file, without its trailer.
+fn decoded_len(header: Header, data: &[u8]) -> Option<u64> {
+    if header.format == Format::Bits && !header.blocks {
+        let (bit_len, _) = varint::read_count(data, 0, 0).ok()?;
+
+        return Some(bit_len / 8 + u64::from(bit_len % 8 != 0));
+    }
+
+    // The bytes left over after the last run of the units schemes are
+    // decoded as they are.
+    let left_over = match header.format {
+        _ if header.blocks => 0,
+        Format::Unit2 => 1,
+        Format::Unit4 => 3,
+        _ => 0,
+    };
+    let mut at = 0;
+    let mut len = 0u64;
+
+    while data.len() - at > left_over {
+        match header.run_at(&data[at..])? {
+            (run, run_len) if run <= data.len() - at => {
+                at += run;
+                len = len.checked_add(run_len)?;
+            }
+            _ => return None,
+        }
+    }
+
+    Some(len + (data.len() - at) as u64)
+}
+
+/// Walk through the runs of the data of a file of the bits scheme, as
+/// [`walk`] does for the other schemes.
+///
+/// Its runs are of bits, so its data can only end after the runs adding up
+/// to the number of bits it starts with.
+///
+/// * `data` - The data following the header.
+fn walk_bits(data: &[u8]) -> Result<(Vec<u8>, usize), Option<u64>> {
+    let end = data.len().checked_sub(Trailer::LEN).ok_or(None)?;
+    let (bit_len, mut at) = varint::read_count(data, 0, 0).map_err(|_| None)?;
+    let len = bit_len / 8 + u64::from(bit_len % 8 != 0);
+    let mut bits = 0;
+
+    while at <= end {
+        if bits == bit_len {
+            let trailer = Trailer::from_bytes(data[at..at + Trailer::LEN].try_into().unwrap());
+
+            if trailer.len == len {
+                match bits::decode(&data[..at]) {
+                    Ok(decoding) if Trailer::of(&decoding) == trailer => return Ok((decoding, at)),
+                    _ => {}
+                }
+            }
+
+            if at == end {
+                return Err(Some(len));
+            }
+        }
+
+        match varint::read_count(data, at, at) {
+            // Only the first run may be empty.
+            Ok((run, next)) if run <= bit_len - bits && (run > 0 || bits == 0) => {
+                bits += run;
+                at = next;
+            }
+            _ => break,
+        }
+    }
+
+    Err(None)
+}
+
+/// Decode `runs`, runs of the data following `header` that come after those
+/// decoding to `decoding`.
+///
+/// * `header` - The header of the file.
+/// * `decoding` - The decoding of the runs before.
+/// * `runs` - The runs to be decoded.
+fn decode_after(header: Header, decoding: &[u8], runs: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let mut more = header.decode(runs)?;
+
+    // The differences carry on from the last byte decoded, but start over in
+    // every block.
+    if header.format == Format::Delta && !header.blocks {
+        let last = decoding.last().copied().unwrap_or(0);
+        more.iter_mut().for_each(|byte| *byte = byte.wrapping_add(last));
+    }
+
+    Ok(more)
+}
+
+/// The schemes [`encode_auto`] chooses from, in the order they're preferred
+/// in when their encodings are as long.
+const AUTO_FORMATS: [Format; 3] = [Format::Byte, Format::PackBits, Format::Stored];
+
+/// Encode `bytes` with whichever of the byte and PackBits schemes gives the
+/// shorter result, or store them as they are if neither shrinks them, and
+/// return the result between a header naming the scheme and a trailer.
+///
+/// The byte scheme is best for data made of long runs, and PackBits for data
+/// mixing runs with stretches of bytes that don't repeat; storing the data
+/// keeps incompressible data from growing by more than the header and
+/// trailer.
+///
+/// * `bytes` - The bytes to be encoded.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::{Format, Header};
+///
+/// let encoded = rle_rs::encode_auto(b"aaaaaaaaab");
+/// assert_eq!(Header::parse(&encoded).unwrap().0.format, Format::Byte);
+///
+/// let encoded = rle_rs::encode_auto(b"abcdefghijklmnopqrstuvwxyzzzz");
+/// assert_eq!(Header::parse(&encoded).unwrap().0.format, Format::PackBits);
+///
+/// assert_eq!(rle_rs::decode_auto(&encoded).unwrap(), b"abcdefghijklmnopqrstuvwxyzzzz");
+/// ```
+pub fn encode_auto(bytes: &[u8]) -> Vec<u8> {
+    let (format, data) = AUTO_FORMATS
+        .iter()
+        .map(|&format| (format, format.encode(bytes)))
+        .min_by_key(|(_, data)| data.len())
+        .unwrap_or_else(|| (Format::Stored, bytes.to_vec()));
+
+    let mut encoding = Header::new(format).to_bytes();
+    encoding.extend(data);
+    encoding.extend(&Trailer::of(bytes).to_bytes());
+
+    encoding
+}
+
+/// Decode the result of [`encode_auto`], with the scheme named by its
+/// header.
+///
+/// This is [`decode_with_header`], which reads any scheme the header can
+/// name, under the name matching [`encode_auto`].
+///
+/// * `bytes` - The contents of the file, header included.
+///
+/// # Errors
+///
+/// Returns the same errors as [`decode_with_header`].
+pub fn decode_auto(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    decode_with_header(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    const FORMATS: [Format; 13] = [
+        Format::Byte,
+        Format::U16,
+        Format::PackBits,
+        Format::Stored,
+        Format::Escape,
+        Format::Varint,
+        Format::Rle0,
+        Format::Unit2,
+        Format::Unit4,
+        Format::Bits,
+        Format::Text,
+        Format::Pcx,
+        Format::Delta,
+    ];
+
+    #[test]
+    fn format_ids_round_trip() {
+        for &format in &FORMATS {
+            assert_eq!(Format::from_id(format.id()), Some(format));
+        }
+
+        assert_eq!(Format::from_id(255), None);
+    }
+
+    #[test]
+    fn encode_auto_picks_the_shortest_encoding() {
+        let long_runs = [vec![1; 1000], vec![2; 1000]].concat();
+        // Bytes that don't repeat, which the byte scheme doubles, with a run.
+        let mixed: Vec<u8> = (0..200).chain([7; 20]).collect();
+        let random: Vec<u8> = (0..1000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+
+        for (bytes, format) in [(&long_runs, Format::Byte), (&mixed, Format::PackBits), (&random, Format::Stored)] {
+            let encoded = encode_auto(bytes);
+            let (header, data) = Header::parse(&encoded).unwrap();
+            let shortest = AUTO_FORMATS.iter().map(|f| f.encode(bytes).len()).min().unwrap();
+
+            assert_eq!(header.format, format);
+            assert_eq!(data.len(), shortest + Trailer::LEN);
+            assert_eq!(decode_auto(&encoded).unwrap(), *bytes);
+        }
+    }
+
+    #[test]
+    fn encode_auto_never_grows_by_more_than_the_header_and_trailer() {
+        for bytes in [&[][..], b"a", b"ab", b"abc"] {
+            let encoded = encode_auto(bytes);
+
+            assert!(encoded.len() <= bytes.len() + Header::MAX_LEN + Trailer::LEN);
+            assert_eq!(decode_auto(&encoded).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let legacy = Header { version: 1, format: Format::PackBits, checksum: false, blocks: false };
+        let blocked = Header { blocks: true, ..Header::new(Format::Varint) };
+
+        for &header in &[Header::new(Format::U16), legacy, blocked] {
+            let mut bytes = header.to_bytes();
+            assert_eq!(bytes.len(), header.size());
+            bytes.push(42);
+
+            assert_eq!(Header::parse(&bytes), Ok((header, &[42][..])));
+            #[cfg(feature = "std")]
+            assert_eq!(Header::read_from(&mut &bytes[..]).unwrap(), header);
+        }
+    }
+
+    #[test]
+    fn run_at_walks_the_runs_of_every_format() {
+        let bytes: Vec<u8> = (0..300).map(|i| (i / 7 % 5) as u8).chain(*b"\x1b\x1b\\\n").collect();
+
+        for &format in FORMATS.iter().filter(|&&format| format != Format::Bits) {
+            for &header in &[Header::new(format), Header { blocks: true, ..Header::new(format) }] {
+                let data = if header.blocks { blocks::encode(&bytes, format, 1) } else { format.encode(&bytes) };
+                let (mut at, mut len) = (0, 0);
+
+                while let Some((run, run_len)) = header.run_at(&data[at..]) {
+                    at += run;
+                    len += run_len as usize;
+                }
+
+                // Only the bytes left over after the runs of the units
+                // schemes aren't a run.
+                assert_eq!(data.len() - at, bytes.len() - len, "{:?}", header);
+            }
+        }
+
+        assert_eq!(Header::new(Format::Bits).run_at(&[8, 8]), None);
+    }
+
+    #[test]
+    fn decode_member_stops_at_the_first_trailer_that_checks_out() {
+        let bytes: Vec<u8> = (0..300).map(|i| (i / 7 % 5) as u8).collect();
+
+        for &format in &FORMATS {
+            for &header in &[Header::new(format), Header { blocks: true, ..Header::new(format) }] {
+                let data = if header.blocks { blocks::encode(&bytes, format, 1) } else { format.encode(&bytes) };
+                let tail = [&Trailer::of(&bytes).to_bytes()[..], b"\0\0\0\0more that isn't the file"].concat();
+
+                assert_eq!(decode_member(header, &[&data[..], &tail].concat()), Ok((bytes.clone(), data.len())));
+            }
+        }
+    }
+
+    #[test]
+    fn decode_member_without_a_trailer_that_checks_out() {
+        let encoded = encode_with_header(b"aaab", Format::Byte);
+        let (header, data) = Header::parse(&encoded).unwrap();
+        let mut longer = data.to_vec();
+        longer[1] = 4;
+
+        assert_eq!(decode_member(header, &longer), Err(DecodeError::LengthMismatch { expected: 4, actual: 5 }));
+        assert_eq!(decode_member(header, &data[..13]), Err(DecodeError::TruncatedInput { at_byte: 0 }));
+        assert_eq!(decode_member(header, &data[..3]), Err(DecodeError::MissingTrailer));
+
+        // A run too long to decode followed by a trailer recording a short
+        // decoding is found not to match without decoding it.
+        let header = Header::new(Format::Varint);
+        let run = [b'a', 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x0f];
+        let data = [&run[..], &Trailer { crc: 0, len: 1 }.to_bytes()].concat();
+
+        assert_eq!(decode_member(header, &data), Err(DecodeError::LengthMismatch { expected: 1, actual: (1 << 53) - 1 }));
+    }
+
+    #[test]
+    fn decode_with_header_checks_the_length_before_decoding() {
+        // A run too long to decode, which the trailer doesn't match.
+        let run = [b'a', 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x0f];
+        let file = [&Header::new(Format::Varint).to_bytes()[..], &run, &Trailer { crc: 0, len: 1 }.to_bytes()].concat();
+
+        assert_eq!(decode_with_header(&file), Err(DecodeError::LengthMismatch { expected: 1, actual: (1 << 53) - 1 }));
+
+        // The walk finds the length of every scheme's data, left over bytes
+        // included.
+        let bytes: Vec<u8> = (0..501).map(|i| (i / 7) as u8).collect();
+
+        for &format in &FORMATS {
+            let file = encode_with_header(&bytes, format);
+            let (header, data) = Header::parse(&file).unwrap();
+
+            assert_eq!(decoded_len(header, &data[..data.len() - Trailer::LEN]), Some(501), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn decode_members_of_every_format() {
+        let files: Vec<(Vec<u8>, Vec<u8>)> = FORMATS
+            .iter()
+            .enumerate()
+            .map(|(i, &format)| {
+                let bytes: Vec<u8> = (0..300).map(|j| ((i + j / 7) % 5) as u8).collect();
+                (encode_with_header(&bytes, format), bytes)
+            })
+            .collect();
+        let encoded: Vec<u8> = files.iter().flat_map(|(encoded, _)| encoded.clone()).collect();
+        let decoded: Vec<u8> = files.iter().flat_map(|(_, bytes)| bytes.clone()).collect();
+
+        assert_eq!(decode_members(&encoded).unwrap(), decoded);
+        assert_eq!(decode_members_lenient(&encoded).unwrap(), (decoded, encoded.len()));
+    }
+
+    #[test]
+    fn decode_members_skips_headers_within_data() {
+        // A file of several, stored as it is, holds their headers and
+        // trailers in its own data.
+        let mut inner = encode_with_header(b"aaab", Format::Byte);
+        inner.extend(encode_with_header(b"cd", Format::Byte));
+        let mut encoded = encode_with_header(&inner, Format::Stored);
+        encoded.extend(encode_with_header(b"ef", Format::Byte));
+
+        assert_eq!(decode_members(&encoded).unwrap(), [&inner[..], b"ef"].concat());
+    }
+
+    #[test]
+    fn decode_members_skips_trailers_within_data() {
+        // A trailer that checks out within the data of a file, followed by
+        // neither another file nor the end, doesn't end it.
+        let random: Vec<u8> = (0..200u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+        let bytes = [&random[..], &Trailer::of(&random).to_bytes(), &random[..50]].concat();
+        let encoded = encode_with_header(&bytes, Format::Stored);
+
+        assert_eq!(decode_with_header(&encoded).unwrap(), bytes);
+        assert_eq!(decode_members(&encoded).unwrap(), bytes);
+        assert_eq!(decode_members(&[&encoded[..], &encoded].concat()).unwrap(), [&bytes[..], &bytes].concat());
+    }
+
+    #[test]
+    fn decode_members_with_trailing_data() {
+        let mut encoded = encode_with_header(b"aaab", Format::Byte);
+        encoded.extend(encode_with_header(b"cd", Format::PackBits));
+        let len = encoded.len();
+        encoded.extend(b"\0\0\0\0garbage");
+
+        assert_eq!(decode_members(&encoded), Err(DecodeError::TrailingData { offset: len as u64 }));
+        assert_eq!(decode_members_lenient(&encoded).unwrap(), (b"aaabcd".to_vec(), len));
+
+        // A file cut short is trailing data too, but only after another.
+        let mut truncated = encode_with_header(b"aaab", Format::Byte);
+        truncated.extend(&encode_with_header(b"cd", Format::Byte)[..9]);
+
+        assert_eq!(decode_members_lenient(&truncated).unwrap(), (b"aaab".to_vec(), truncated.len() - 9));
+        assert!(decode_members_lenient(&encode_with_header(b"aaab", Format::Byte)[..9]).is_err());
+    }
+
+    #[test]
+    fn parse_invalid_headers() {
+        assert_eq!(Header::parse(b""), Err(DecodeError::InvalidMagic));
+        assert_eq!(Header::parse(&[b'a', 3]), Err(DecodeError::InvalidMagic));
+        assert_eq!(Header::parse(b"RLE\x01"), Err(DecodeError::TruncatedInput { at_byte: 4 }));
+        assert_eq!(Header::parse(b"RLE\x01\x09\x00"), Err(DecodeError::UnsupportedVersion(9)));
+        assert_eq!(Header::parse(b"RLE\x01\x01\x7f"), Err(DecodeError::UnknownFormat(0x7f)));
+        assert_eq!(Header::parse(b"RLE\x01\x02\x00"), Err(DecodeError::TruncatedInput { at_byte: 6 }));
+        assert_eq!(Header::parse(b"RLE\x01\x02\x00\x80"), Err(DecodeError::UnknownFlags(0x80)));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn read_from_invalid_header() {
+        let e = Header::read_from(&mut &b"RL"[..]).unwrap_err();
+
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn round_trip_with_header() {
+        let bytes: Vec<u8> = (0..100).flat_map(|n| vec![n as u8; n % 5 + 1]).collect();
+
+        for &format in &FORMATS {
+            assert_eq!(decode_with_header(&encode_with_header(&bytes, format)), Ok(bytes.clone()));
+        }
+    }
+
+    #[test]
+    fn round_trip_with_blocks_header() {
+        let bytes: Vec<u8> = (0..100).flat_map(|n| vec![n as u8; n % 5 + 1]).collect();
+        let header = Header { blocks: true, ..Header::new(Format::PackBits) };
+        let encoded = [
+            &header.to_bytes()[..],
+            &blocks::encode(&bytes, Format::PackBits, 2),
+            &Trailer::of(&bytes).to_bytes(),
+        ]
+        .concat();
+
+        assert_eq!(decode_with_header(&encoded), Ok(bytes));
+    }
+
+    #[test]
+    fn count_width_comes_from_the_header() {
+        let mut bytes = encode_with_header(&[5; 300], Format::U16);
+        assert_eq!(decode_with_header(&bytes), Ok(vec![5; 300]));
+
+        // The 16-bit counts can't be misread as 8-bit ones without an error.
+        bytes[5] = Format::Byte.id();
+        assert!(decode_with_header(&bytes).is_err());
+    }
+
+    #[test]
+    fn decode_legacy_file_without_trailer() {
+        let header = Header { version: 1, format: Format::Byte, checksum: false, blocks: false };
+        let bytes = [&header.to_bytes()[..], &[b'a', 3]].concat();
+
+        assert_eq!(decode_with_header(&bytes), Ok(b"aaa".to_vec()));
+    }
+
+    #[test]
+    fn decode_corrupt_files() {
+        let mut bytes = encode_with_header(b"aaab", Format::Byte);
+        let len = bytes.len();
+
+        // Swapping the runs keeps the length but changes the checksum.
+        bytes[7..11].copy_from_slice(&[b'b', 1, b'a', 3]);
+        assert!(matches!(decode_with_header(&bytes), Err(DecodeError::ChecksumMismatch { .. })));
+
+        bytes[8] = 2;
+        assert_eq!(
+            decode_with_header(&bytes),
+            Err(DecodeError::LengthMismatch { expected: 4, actual: 5 })
+        );
+
+        assert_eq!(decode_with_header(&bytes[..len - 13]), Err(DecodeError::MissingTrailer));
+    }
+
+    #[test]
+    fn decode_without_header_is_an_error() {
+        assert_eq!(decode_with_header(&crate::encode(b"aaab")), Err(DecodeError::InvalidMagic));
+    }
+}
@@ -0,0 +1,334 @@
+//! Hexadecimal transport for encoded data, so that it can be pasted into
+//! text documents.
+//!
+//! Unlike the encoding schemes, this changes nothing but how the bytes are
+//! written, so it can wrap any of them, header and trailer included.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
+
+use crate::DecodeError;
+
+/// The digits bytes are written with.
+const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// The size of the chunks [`HexReader`] reads its input in.
+#[cfg(feature = "std")]
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Return `bytes` as lowercase hex, without line breaks.
+///
+/// * `bytes` - The bytes to be written.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(rle_rs::hex::encode(&[b'a', 3, 0xff]), "6103ff");
+/// ```
+pub fn encode(bytes: &[u8]) -> String {
+    bytes.iter().flat_map(|&byte| pair(byte)).map(char::from).collect()
+}
+
+/// Return the bytes written as hex in `text`, ignoring whitespace.
+///
+/// * `text` - The hex digits.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::InvalidHex`] with the offset of the problem if
+/// `text` has something other than hex digits and whitespace, or an odd
+/// number of digits.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(rle_rs::hex::decode(b"6103\nff\n").unwrap(), [b'a', 3, 0xff]);
+/// assert!(rle_rs::hex::decode(b"610").is_err());
+/// ```
+pub fn decode(text: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let mut decoding = Vec::with_capacity(text.len() / 2);
+    let mut high = None;
+
+    for (at_byte, &c) in text.iter().enumerate() {
+        if let Some(digit) = digit(c, at_byte)? {
+            match high.take() {
+                None => high = Some((at_byte, digit)),
+                Some((_, high)) => decoding.push(high << 4 | digit),
+            }
+        }
+    }
+
+    match high {
+        Some((at_byte, _)) => Err(odd_digits(at_byte)),
+        None => Ok(decoding),
+    }
+}
+
+/// A writer that writes everything written to it as hex to an inner writer,
+/// breaking it into lines if asked to.
+///
+/// Call [`HexWriter::finish`] when done, to end the last line.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use rle_rs::hex::HexWriter;
+///
+/// let mut writer = HexWriter::with_line_len(Vec::new(), 4);
+/// writer.write_all(&[b'a', 3, 0xff]).unwrap();
+///
+/// assert_eq!(writer.finish().unwrap(), b"6103\nff\n");
+/// ```
+#[cfg(feature = "std")]
+pub struct HexWriter<W: Write> {
+    inner: W,
+    /// The most digits on a line, if lines are broken at all.
+    line_len: Option<usize>,
+    /// The number of digits on the current line.
+    column: usize,
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> HexWriter<W> {
+    /// Create a new writer writing hex to `inner` on a single line.
+    ///
+    /// * `inner` - The writer that receives the hex digits.
+    pub fn new(inner: W) -> Self {
+        Self { inner, line_len: None, column: 0 }
+    }
+
+    /// Create a new writer writing hex to `inner`, in lines of at most
+    /// `line_len` digits.
+    ///
+    /// * `inner` - The writer that receives the hex digits.
+    /// * `line_len` - The most digits on a line, rounded up to an even
+    ///   number so that bytes aren't split across lines.
+    pub fn with_line_len(inner: W, line_len: usize) -> Self {
+        Self { inner, line_len: Some(line_len.max(1).div_ceil(2) * 2), column: 0 }
+    }
+
+    /// End the last line, if lines are broken and it has anything on it, and
+    /// return the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.line_len.is_some() && self.column > 0 {
+            self.inner.write_all(b"\n")?;
+        }
+
+        Ok(self.inner)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Write for HexWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut text = Vec::with_capacity(buf.len() * 2 + buf.len() / 32 + 1);
+
+        for &byte in buf {
+            if self.line_len == Some(self.column) {
+                text.push(b'\n');
+                self.column = 0;
+            }
+
+            text.extend(&pair(byte));
+            self.column += 2;
+        }
+
+        self.inner.write_all(&text)?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A reader that reads hex from an inner reader, ignoring whitespace, and
+/// returns the bytes it stands for.
+///
+/// # Errors
+///
+/// Reading fails with [`io::ErrorKind::InvalidData`], wrapping a
+/// [`DecodeError::InvalidHex`], if the input is malformed as described for
+/// [`decode`].
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Read;
+/// use rle_rs::hex::HexReader;
+///
+/// let mut bytes = Vec::new();
+/// HexReader::new(&b"6103\nff\n"[..]).read_to_end(&mut bytes).unwrap();
+///
+/// assert_eq!(bytes, [b'a', 3, 0xff]);
+/// ```
+#[cfg(feature = "std")]
+pub struct HexReader<R: Read> {
+    inner: R,
+    /// Text read from `inner` but not decoded yet.
+    buf: Box<[u8]>,
+    start: usize,
+    end: usize,
+    /// How much text has been decoded so far.
+    offset: usize,
+    /// The offset and value of a digit whose pair hasn't been read yet.
+    high: Option<(usize, u8)>,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> HexReader<R> {
+    /// Create a new reader reading hex from `inner`.
+    ///
+    /// * `inner` - The reader the hex digits come from.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buf: vec![0; CHUNK_SIZE].into_boxed_slice(),
+            start: 0,
+            end: 0,
+            offset: 0,
+            high: None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Read for HexReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let mut written = 0;
+
+        while written < out.len() {
+            if self.start == self.end {
+                // Don't block on the inner reader if there is something to
+                // return already.
+                if written > 0 {
+                    break;
+                }
+
+                match self.inner.read(&mut self.buf) {
+                    Ok(0) => match self.high {
+                        Some((at_byte, _)) => return Err(invalid_data(odd_digits(at_byte))),
+                        None => break,
+                    },
+                    Ok(n) => {
+                        self.start = 0;
+                        self.end = n;
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+
+                continue;
+            }
+
+            let at_byte = self.offset;
+            let c = self.buf[self.start];
+            self.start += 1;
+            self.offset += 1;
+
+            if let Some(digit) = digit(c, at_byte).map_err(invalid_data)? {
+                match self.high.take() {
+                    None => self.high = Some((at_byte, digit)),
+                    Some((_, high)) => {
+                        out[written] = high << 4 | digit;
+                        written += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(written)
+    }
+}
+
+/// Return the two hex digits of `byte`.
+fn pair(byte: u8) -> [u8; 2] {
+    [DIGITS[(byte >> 4) as usize], DIGITS[(byte & 0xf) as usize]]
+}
+
+/// Return the value of the hex digit `c`, or `None` if it's whitespace.
+fn digit(c: u8, at_byte: usize) -> Result<Option<u8>, DecodeError> {
+    if c.is_ascii_whitespace() {
+        return Ok(None);
+    }
+
+    match (c as char).to_digit(16) {
+        Some(digit) => Ok(Some(digit as u8)),
+        None => Err(DecodeError::InvalidHex { at_byte, reason: "not a hex digit" }),
+    }
+}
+
+fn odd_digits(at_byte: usize) -> DecodeError {
+    DecodeError::InvalidHex { at_byte, reason: "odd number of hex digits" }
+}
+
+#[cfg(feature = "std")]
+fn invalid_data(e: DecodeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[cfg(feature = "std")]
+    fn write_hex(bytes: &[u8], line_len: usize) -> Vec<u8> {
+        let mut writer = HexWriter::with_line_len(Vec::new(), line_len);
+
+        // Write a byte at a time, so that lines span writes.
+        for &byte in bytes {
+            writer.write_all(&[byte]).unwrap();
+        }
+
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn lines_are_broken_between_bytes() {
+        assert_eq!(write_hex(&[1, 2, 3, 4, 5], 4), b"0102\n0304\n05\n");
+        assert_eq!(write_hex(&[1, 2, 3, 4], 3), b"0102\n0304\n");
+        assert_eq!(write_hex(&[], 4), b"");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn round_trip() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let text = write_hex(&bytes, 76);
+
+        assert_eq!(decode(&text), Ok(bytes.clone()));
+        assert_eq!(decode(encode(&bytes).as_bytes()), Ok(bytes.clone()));
+
+        let mut read = Vec::new();
+        HexReader::new(&text[..]).read_to_end(&mut read).unwrap();
+        assert_eq!(read, bytes);
+    }
+
+    #[test]
+    fn decode_accepts_uppercase() {
+        assert_eq!(decode(b"AbCd"), Ok(vec![0xab, 0xcd]));
+    }
+
+    #[test]
+    fn decode_malformed_input() {
+        let invalid = |at_byte, reason| Err(DecodeError::InvalidHex { at_byte, reason });
+
+        assert_eq!(decode(b"61 0"), invalid(3, "odd number of hex digits"));
+        assert_eq!(decode(b"61 0x"), invalid(4, "not a hex digit"));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn hex_reader_malformed_input() {
+        let e = HexReader::new(&b"6103\nf"[..]).read_to_end(&mut Vec::new()).unwrap_err();
+
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(e.to_string(), DecodeError::InvalidHex { at_byte: 5, reason: "odd number of hex digits" }.to_string());
+    }
+}
@@ -0,0 +1,784 @@
+//! A run-length encoding library.
+//!
+//! Encoded data is a sequence of `[byte, count]` pairs, where `count` is the
+//! number of times `byte` repeats in a row. Runs longer than 255 bytes are
+//! split into several pairs.
+//!
+//! ```
+//! let encoded = rle_rs::encode(b"aaab");
+//! assert_eq!(encoded, [b'a', 3, b'b', 1]);
+//! assert_eq!(rle_rs::decode(&encoded).unwrap(), b"aaab");
+//! ```
+//!
+//! Encoded files also start with a [`Header`] naming the scheme they were
+//! encoded with, and end with a [`Trailer`] to check them with; see
+//! [`encode_with_header`] and [`decode_with_header`].
+//!
+//! Without the default `std` feature, the library only needs `alloc`, so the
+//! codecs can be used in firmware and kernels; the readers and writers, and
+//! running on several threads, need `std`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+mod archive;
+pub mod base64;
+pub mod bits;
+pub mod blocks;
+pub mod bmp_rle8;
+mod chunks;
+mod crc;
+mod delta;
+mod encoder;
+mod error;
+pub mod escape;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod format;
+pub mod hex;
+mod mtf;
+pub mod packbits;
+#[cfg(feature = "std")]
+mod parallel;
+pub mod pcx;
+pub mod rle0;
+mod runs;
+mod stats;
+#[cfg(feature = "std")]
+mod stream;
+pub mod text;
+pub mod tga_rle;
+pub mod units;
+pub mod varint;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use crate::archive::create_archive;
+#[cfg(feature = "std")]
+pub use crate::archive::{extract_file, list_archive};
+pub use crate::chunks::{decode_block, encode_blocks, find_block_offset};
+pub use crate::crc::{crc32, Crc32};
+#[cfg(feature = "std")]
+pub use crate::delta::decode_rle_delta;
+pub use crate::delta::{delta_decode, delta_encode, encode_delta_rle};
+pub use crate::encoder::Encoder;
+#[cfg(feature = "std")]
+pub use crate::error::RleError;
+pub use crate::error::{ConfigError, DecodeError, ParseError, RunsError};
+pub use crate::format::{
+    decode_auto, decode_member, decode_members, decode_members_lenient, decode_with_header, encode_auto,
+    encode_with_header, Format, Header, Trailer, MAGIC, VERSION,
+};
+#[cfg(feature = "std")]
+pub use crate::mtf::decode_rle_mtf;
+pub use crate::mtf::{encode_mtf_rle, mtf_decode, mtf_encode};
+#[cfg(feature = "std")]
+pub use crate::parallel::{decode_parallel, encode_parallel};
+pub use crate::runs::{decode_pairs, encode_runs, encoded_runs, from_runs, runs, to_runs, Run};
+#[cfg(feature = "std")]
+pub use crate::stats::{byte_entropy, is_likely_compressible};
+pub use crate::stats::{encode_with_stats, Analysis, Analyzer, Stats};
+#[cfg(feature = "std")]
+pub use crate::stream::{
+    decode_from_reader, decode_stream, decode_to_writer, encode_from_reader, encode_stream,
+    encode_stream_with_opts, encode_to_writer, DecoderReader, RleDecoder, RleEncoder, RunDecoder,
+    RunEncoder,
+};
+
+/// Options to tune the encoder with.
+///
+/// * `max_run` - The longest run a single pair may hold. Longer runs are split
+///   into several pairs. A `max_run` of 0 is treated as 1. Only used in
+///   [`EncoderMode::Basic`].
+/// * `mode` - How bytes that don't repeat are encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodeOptions {
+    pub max_run: u8,
+    pub mode: EncoderMode,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self { max_run: 255, mode: EncoderMode::Basic }
+    }
+}
+
+/// Options to limit the decoder with.
+///
+/// * `limit` - The longest the decoded data may be, in bytes, to guard
+///   against small inputs that decode to more than fits in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeOptions {
+    pub limit: usize,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self { limit: usize::MAX }
+    }
+}
+
+/// How the encoder handles bytes that don't repeat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncoderMode {
+    /// Every byte is part of a `[byte, count]` pair, so data without runs
+    /// doubles in size.
+    Basic,
+    /// Bytes that don't repeat are gathered into literal runs copied as they
+    /// are, as in [`packbits`], so data without runs barely grows.
+    Adaptive,
+}
+
+impl EncoderMode {
+    /// Return the scheme encodings in this mode are in, to record in a
+    /// header or decode them with.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rle_rs::{EncodeOptions, EncoderMode};
+    ///
+    /// let opts = EncodeOptions { mode: EncoderMode::Adaptive, ..EncodeOptions::default() };
+    /// let encoded = rle_rs::encode_with_opts(b"abcdddd", &opts);
+    ///
+    /// assert_eq!(encoded.len(), 6);
+    /// assert_eq!(opts.mode.format().decode(&encoded).unwrap(), b"abcdddd");
+    /// ```
+    pub fn format(self) -> Format {
+        match self {
+            Self::Basic => Format::Byte,
+            Self::Adaptive => Format::PackBits,
+        }
+    }
+}
+
+/// Read a byte slice and return its run-length encoding.
+///
+/// * `bytes` - The bytes to be encoded.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(rle_rs::encode(b"!!!?"), [b'!', 3, b'?', 1]);
+/// assert_eq!(rle_rs::encode(b""), []);
+/// ```
+pub fn encode(bytes: &[u8]) -> Vec<u8> {
+    encode_with_opts(bytes, &EncodeOptions::default())
+}
+
+/// Read a byte slice and return its run-length encoding, as tuned by `opts`.
+///
+/// * `bytes` - The bytes to be encoded.
+/// * `opts` - The options to encode with.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::EncodeOptions;
+///
+/// let opts = EncodeOptions { max_run: 2, ..EncodeOptions::default() };
+///
+/// assert_eq!(rle_rs::encode_with_opts(b"!!!?", &opts), [b'!', 2, b'!', 1, b'?', 1]);
+/// ```
+pub fn encode_with_opts(bytes: &[u8], opts: &EncodeOptions) -> Vec<u8> {
+    if opts.mode == EncoderMode::Adaptive {
+        return packbits::encode(bytes);
+    }
+
+    let mut encoding = Vec::new();
+    push_pairs(bytes, opts.max_run.max(1), &mut encoding);
+
+    encoding
+}
+
+/// Run-length encode `input` into `out`, replacing its contents, as
+/// [`encode`] does.
+///
+/// `out` keeps its capacity across calls, so encoding many small inputs
+/// into the same buffer doesn't allocate once it's large enough.
+///
+/// * `input` - The bytes to be encoded.
+/// * `out` - The buffer that receives the encoding.
+///
+/// # Examples
+///
+/// ```
+/// let mut out = Vec::new();
+///
+/// rle_rs::encode_into(b"!!!?", &mut out);
+/// assert_eq!(out, [b'!', 3, b'?', 1]);
+///
+/// rle_rs::encode_into(b"aa", &mut out);
+/// assert_eq!(out, [b'a', 2]);
+/// ```
+pub fn encode_into(input: &[u8], out: &mut Vec<u8>) {
+    out.clear();
+    push_pairs(input, 255, out);
+}
+
+/// Append the `[byte, count]` pairs of `bytes` to `out`, splitting runs
+/// longer than `max_run`.
+fn push_pairs(bytes: &[u8], max_run: u8, out: &mut Vec<u8>) {
+    let max_run = usize::from(max_run);
+
+    for (byte, mut len) in runs(bytes) {
+        while len > max_run {
+            out.extend_from_slice(&[byte, max_run as u8]);
+            len -= max_run;
+        }
+
+        out.extend_from_slice(&[byte, len as u8]);
+    }
+}
+
+/// Read a run-length encoding and return its decoded contents.
+///
+/// * `bytes` - The bytes to be decoded.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::TruncatedInput`] if `bytes` does not consist of
+/// whole `[byte, count]` pairs, and [`DecodeError::ZeroCount`] if a pair has
+/// a count of zero, which the encoder never produces.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::DecodeError;
+///
+/// assert_eq!(rle_rs::decode(&[b'!', 3, b'?', 1]).unwrap(), b"!!!?");
+/// assert_eq!(rle_rs::decode(&[b'!']), Err(DecodeError::TruncatedInput { at_byte: 0 }));
+/// assert_eq!(rle_rs::decode(&[b'!', 3, b'?', 0]), Err(DecodeError::ZeroCount { at_byte: 2 }));
+/// ```
+pub fn decode(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    decode_with_opts(bytes, &DecodeOptions::default())
+}
+
+/// Read a run-length encoding and return its decoded contents, as limited by
+/// `opts`.
+///
+/// * `bytes` - The bytes to be decoded.
+/// * `opts` - The options to decode with.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::OutputTooLarge`] if the decoded contents would be
+/// longer than `opts.limit`, which is found before any of them is decoded,
+/// and the same errors as [`decode`] otherwise.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::{DecodeError, DecodeOptions};
+///
+/// let opts = DecodeOptions { limit: 4 };
+///
+/// assert_eq!(rle_rs::decode_with_opts(&[b'!', 3, b'?', 1], &opts).unwrap(), b"!!!?");
+/// assert_eq!(
+///     rle_rs::decode_with_opts(&[b'!', 255, b'?', 1], &opts),
+///     Err(DecodeError::OutputTooLarge { limit: 4 })
+/// );
+/// ```
+pub fn decode_with_opts(bytes: &[u8], opts: &DecodeOptions) -> Result<Vec<u8>, DecodeError> {
+    let mut decoding = Vec::new();
+    decode_pairs_into(bytes, &mut decoding, opts.limit)?;
+
+    Ok(decoding)
+}
+
+/// Decode the run-length encoding `input` into `out`, replacing its
+/// contents, as [`decode`] does.
+///
+/// `out` keeps its capacity across calls, so decoding many small inputs
+/// into the same buffer doesn't allocate once it's large enough.
+///
+/// * `input` - The bytes to be decoded.
+/// * `out` - The buffer that receives the decoded bytes.
+///
+/// # Errors
+///
+/// Returns the same errors as [`decode`], in which case `out` is left empty.
+///
+/// # Examples
+///
+/// ```
+/// let mut out = Vec::new();
+///
+/// rle_rs::decode_into(&[b'!', 3, b'?', 1], &mut out).unwrap();
+/// assert_eq!(out, b"!!!?");
+///
+/// assert!(rle_rs::decode_into(&[b'!'], &mut out).is_err());
+/// assert!(out.is_empty());
+/// ```
+pub fn decode_into(input: &[u8], out: &mut Vec<u8>) -> Result<(), DecodeError> {
+    decode_pairs_into(input, out, usize::MAX)
+}
+
+/// Decode `input` into `out`, replacing its contents, as [`decode_into`]
+/// does, unless the result would be longer than `limit`.
+///
+/// * `input` - The bytes to be decoded.
+/// * `out` - The buffer that receives the decoded bytes.
+/// * `limit` - The longest the result may be, in bytes.
+fn decode_pairs_into(input: &[u8], out: &mut Vec<u8>, limit: usize) -> Result<(), DecodeError> {
+    out.clear();
+
+    if !input.len().is_multiple_of(2) {
+        return Err(DecodeError::TruncatedInput { at_byte: input.len() - 1 });
+    }
+
+    // The pairs are checked and measured before anything is decoded, so that
+    // nothing is allocated for input that turns out to be malformed.
+    let pairs = input.chunks_exact(2);
+    let mut len: usize = 0;
+
+    for (i, pair) in pairs.clone().enumerate() {
+        if pair[1] == 0 {
+            return Err(DecodeError::ZeroCount { at_byte: 2 * i });
+        }
+
+        len = len
+            .checked_add(usize::from(pair[1]))
+            .filter(|&len| len <= limit)
+            .ok_or(DecodeError::OutputTooLarge { limit })?;
+    }
+
+    out.reserve(len);
+
+    for pair in pairs {
+        // Repeat pair[0], pair[1] times in a row.
+        // e.g.: "!!" equals to 33 times "!" ("!" value in ASCII).
+        out.extend(core::iter::repeat_n(pair[0], pair[1] as usize));
+    }
+
+    Ok(())
+}
+
+/// Return the length of the run-length encoding of `input`, as produced by
+/// [`encode`], without encoding it.
+///
+/// * `input` - The bytes that would be encoded.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(rle_rs::encoded_len(b"!!!?"), 4);
+/// assert_eq!(rle_rs::encoded_len(&[0; 300]), 4);
+/// ```
+pub fn encoded_len(input: &[u8]) -> usize {
+    let mut iter = input.iter();
+    let mut current = match iter.next() {
+        Some(&first) => first,
+        None => return 0,
+    };
+    let mut pairs = 1;
+    let mut occurrences: u8 = 1;
+
+    for &byte in iter {
+        if byte == current && occurrences < u8::MAX {
+            occurrences += 1;
+        } else {
+            pairs += 1;
+            current = byte;
+            occurrences = 1;
+        }
+    }
+
+    2 * pairs
+}
+
+/// Return the length `encoded` decodes to, as [`decode`] would, without
+/// decoding it.
+///
+/// The length is a `u64`, since on 32-bit targets a slice of pairs can
+/// decode to more bytes than a `usize` can count.
+///
+/// * `encoded` - The bytes that would be decoded.
+///
+/// # Errors
+///
+/// Returns the same errors as [`decode`].
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(rle_rs::decoded_len(&[b'!', 3, b'?', 1]).unwrap(), 4);
+/// assert!(rle_rs::decoded_len(&[b'!']).is_err());
+/// ```
+pub fn decoded_len(encoded: &[u8]) -> Result<u64, DecodeError> {
+    if !encoded.len().is_multiple_of(2) {
+        return Err(DecodeError::TruncatedInput { at_byte: encoded.len() - 1 });
+    }
+
+    if let Some(i) = encoded.chunks_exact(2).position(|pair| pair[1] == 0) {
+        return Err(DecodeError::ZeroCount { at_byte: 2 * i });
+    }
+
+    Ok(encoded.chunks_exact(2).map(|pair| u64::from(pair[1])).sum())
+}
+
+/// Return the offset in `encoded` of the pair holding the byte at offset
+/// `decoded_target` of its decoding, reading only the counts before it, so
+/// that decoding can start from there instead of from the beginning.
+///
+/// Decoding from the offset starts with the whole run holding the byte,
+/// which may have begun before it.
+///
+/// * `encoded` - The bytes that would be decoded.
+/// * `decoded_target` - The offset of the byte in the decoded data.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::OffsetOutOfRange`] if `encoded` decodes to no more
+/// than `decoded_target` bytes, and the same errors as [`decode`] for the
+/// pairs up to the one found.
+///
+/// # Examples
+///
+/// ```
+/// let encoded = rle_rs::encode(b"aaabbbbc");
+/// let offset = rle_rs::find_encoded_offset(&encoded, 5).unwrap();
+///
+/// assert_eq!(offset, 2);
+/// assert_eq!(rle_rs::decode(&encoded[offset..]).unwrap(), b"bbbbc");
+/// ```
+pub fn find_encoded_offset(encoded: &[u8], decoded_target: usize) -> Result<usize, DecodeError> {
+    let mut decoded = 0;
+
+    for (i, pair) in encoded.chunks(2).enumerate() {
+        let count = match *pair {
+            [_, 0] => return Err(DecodeError::ZeroCount { at_byte: 2 * i }),
+            [_, count] => count,
+            _ => return Err(DecodeError::TruncatedInput { at_byte: 2 * i }),
+        };
+        decoded += u64::from(count);
+
+        if decoded > decoded_target as u64 {
+            return Ok(2 * i);
+        }
+    }
+
+    Err(DecodeError::OffsetOutOfRange { offset: decoded_target, len: decoded })
+}
+
+/// Read a byte slice and return its run-length encoding with 16-bit counts.
+///
+/// Each run is stored as `[byte, count_lo, count_hi]`, the count being a
+/// little-endian `u16`, so runs up to 65535 bytes long take a single triple.
+///
+/// * `bytes` - The bytes to be encoded.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(rle_rs::encode_u16(&[0; 300]), [0, 44, 1]);
+/// ```
+pub fn encode_u16(bytes: &[u8]) -> Vec<u8> {
+    let mut encoding = Vec::new();
+    let mut iter = bytes.iter().peekable();
+
+    while let Some(&byte) = iter.next() {
+        let mut occurrences: u16 = 1;
+
+        while occurrences < u16::MAX && iter.next_if_eq(&&byte).is_some() {
+            occurrences += 1;
+        }
+
+        encoding.push(byte);
+        encoding.extend(&occurrences.to_le_bytes());
+    }
+
+    encoding
+}
+
+/// Read a run-length encoding with 16-bit counts and return its decoded
+/// contents.
+///
+/// * `bytes` - The bytes to be decoded, as produced by [`encode_u16`].
+///
+/// # Errors
+///
+/// Returns [`DecodeError::TruncatedInput`] if `bytes` does not consist of
+/// whole `[byte, count_lo, count_hi]` triples, and [`DecodeError::ZeroCount`]
+/// if a triple has a count of zero, which [`encode_u16`] never produces.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(rle_rs::decode_u16(&[b'!', 3, 0]).unwrap(), b"!!!");
+/// assert!(rle_rs::decode_u16(&[b'!', 3]).is_err());
+/// assert!(rle_rs::decode_u16(&[b'!', 0, 0]).is_err());
+/// ```
+pub fn decode_u16(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    if !bytes.len().is_multiple_of(3) {
+        return Err(DecodeError::TruncatedInput { at_byte: bytes.len() - bytes.len() % 3 });
+    }
+
+    let mut decoding = Vec::<u8>::new();
+
+    for (i, triple) in bytes.chunks_exact(3).enumerate() {
+        let occurrences = u16::from_le_bytes([triple[1], triple[2]]);
+
+        if occurrences == 0 {
+            return Err(DecodeError::ZeroCount { at_byte: 3 * i });
+        }
+
+        decoding.extend(core::iter::repeat_n(triple[0], occurrences as usize));
+    }
+
+    Ok(decoding)
+}
+
+/// Read a byte slice and return its run-length encoding with unsigned
+/// LEB128 counts, so that runs of 1 to 127 bytes take 2 bytes and no run is
+/// ever split.
+///
+/// This is the [`varint`] scheme, recorded in a header as
+/// [`Format::Varint`].
+///
+/// * `bytes` - The bytes to be encoded.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(rle_rs::encode_leb128(b"aaab"), [b'a', 3, b'b', 1]);
+/// assert_eq!(rle_rs::encode_leb128(&[0; 200]), [0, 0xc8, 0x01]);
+/// ```
+pub fn encode_leb128(bytes: &[u8]) -> Vec<u8> {
+    varint::encode(bytes)
+}
+
+/// Read a run-length encoding with unsigned LEB128 counts and return its
+/// decoded contents.
+///
+/// * `bytes` - The bytes to be decoded, as produced by [`encode_leb128`].
+///
+/// # Errors
+///
+/// Fails for the same reasons as [`varint::decode`].
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(rle_rs::decode_leb128(&[0, 0xc8, 0x01]).unwrap(), [0; 200]);
+/// assert!(rle_rs::decode_leb128(&[0, 0xc8]).is_err());
+/// ```
+pub fn decode_leb128(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    varint::decode(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use proptest::prelude::*;
+
+    #[test]
+    fn encode_empty() {
+        assert_eq!(encode(&[]), []);
+    }
+
+    #[test]
+    fn encode_single_byte() {
+        assert_eq!(encode(&[7]), [7, 1]);
+    }
+
+    #[test]
+    fn encode_splits_runs_longer_than_255() {
+        assert_eq!(encode(&[0; 600]), [0, 255, 0, 255, 0, 90]);
+    }
+
+    #[test]
+    fn encode_with_opts_max_run() {
+        let opts = |max_run| EncodeOptions { max_run, ..EncodeOptions::default() };
+
+        assert_eq!(encode_with_opts(&[1; 7], &opts(3)), [1, 3, 1, 3, 1, 1]);
+        assert_eq!(encode_with_opts(&[1; 2], &opts(0)), [1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn into_buffers_keep_their_capacity() {
+        let mut encoded = Vec::with_capacity(64);
+        let mut decoded = Vec::with_capacity(64);
+
+        for message in &[&b"aaaabbbc"[..], b"", b"xyz", &[9; 40]] {
+            encode_into(message, &mut encoded);
+            decode_into(&encoded, &mut decoded).unwrap();
+
+            assert_eq!(encoded, encode(message));
+            assert_eq!(&decoded, message);
+            assert_eq!(encoded.capacity(), 64);
+            assert_eq!(decoded.capacity(), 64);
+        }
+    }
+
+    #[test]
+    fn adaptive_mode_does_not_double_data_without_runs() {
+        let opts = EncodeOptions { mode: EncoderMode::Adaptive, ..EncodeOptions::default() };
+        let bytes: Vec<u8> = (0..200).chain(vec![7; 50]).collect();
+        let encoded = encode_with_opts(&bytes, &opts);
+
+        // 2 literal runs and a repeat run.
+        assert_eq!(encoded.len(), 1 + 128 + 1 + 72 + 2);
+        assert_eq!(opts.mode.format().decode(&encoded), Ok(bytes));
+    }
+
+    #[test]
+    fn decode_empty() {
+        assert_eq!(decode(&[]), Ok(vec![]));
+    }
+
+    #[test]
+    fn decode_single_pair() {
+        assert_eq!(decode(&[7, 1]), Ok(vec![7]));
+    }
+
+    #[test]
+    fn decode_single_byte_is_an_error() {
+        assert_eq!(decode(&[7]), Err(DecodeError::TruncatedInput { at_byte: 0 }));
+    }
+
+    #[test]
+    fn decode_dangling_final_byte_is_an_error() {
+        assert_eq!(
+            decode(&[7, 1, 8, 2, 9]),
+            Err(DecodeError::TruncatedInput { at_byte: 4 })
+        );
+    }
+
+    #[test]
+    fn decode_zero_count_is_an_error() {
+        let mut out = vec![1, 2, 3];
+
+        assert_eq!(decode(&[7, 1, 8, 0, 9, 0]), Err(DecodeError::ZeroCount { at_byte: 2 }));
+        assert_eq!(decode_into(&[7, 0], &mut out), Err(DecodeError::ZeroCount { at_byte: 0 }));
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn find_encoded_offset_of_every_byte() {
+        let bytes: Vec<u8> = (0..2000).map(|i| (i / 7 % 3) as u8).chain([9; 600]).collect();
+        let encoded = encode(&bytes);
+
+        for target in 0..bytes.len() {
+            let offset = find_encoded_offset(&encoded, target).unwrap();
+            let before = decoded_len(&encoded[..offset]).unwrap() as usize;
+            let after = decode(&encoded[offset..]).unwrap();
+
+            // Decoding from the offset starts with the run holding the byte.
+            assert!(before <= target && target < before + usize::from(encoded[offset + 1]));
+            assert_eq!(after, &bytes[before..]);
+        }
+    }
+
+    #[test]
+    fn find_encoded_offset_past_the_end() {
+        assert_eq!(
+            find_encoded_offset(&[b'a', 3], 3),
+            Err(DecodeError::OffsetOutOfRange { offset: 3, len: 3 })
+        );
+        assert_eq!(find_encoded_offset(&[], 0), Err(DecodeError::OffsetOutOfRange { offset: 0, len: 0 }));
+        // Only the pairs up to the one found are read.
+        assert_eq!(find_encoded_offset(&[b'a', 3, b'b'], 2), Ok(0));
+        assert_eq!(find_encoded_offset(&[b'a', 3, b'b'], 3), Err(DecodeError::TruncatedInput { at_byte: 2 }));
+        assert_eq!(find_encoded_offset(&[b'a', 3, b'b', 0], 3), Err(DecodeError::ZeroCount { at_byte: 2 }));
+    }
+
+    #[test]
+    fn decode_with_opts_enforces_the_limit() {
+        let encoded = encode(&[5; 1000]);
+        let opts = |limit| DecodeOptions { limit };
+
+        assert_eq!(decode_with_opts(&encoded, &opts(1000)).unwrap(), [5; 1000]);
+        assert_eq!(decode_with_opts(&encoded, &opts(999)), Err(DecodeError::OutputTooLarge { limit: 999 }));
+        assert_eq!(decode_with_opts(&[], &opts(0)).unwrap(), []);
+        assert_eq!(decode_with_opts(&encoded, &DecodeOptions::default()), decode(&encoded));
+    }
+
+    #[test]
+    fn encode_u16_splits_runs_longer_than_65535() {
+        assert_eq!(encode_u16(&[9; 70000]), [9, 255, 255, 9, 0x71, 0x11]);
+    }
+
+    #[test]
+    fn decode_u16_malformed_input() {
+        assert_eq!(
+            decode_u16(&[7, 1, 0, 8, 2]),
+            Err(DecodeError::TruncatedInput { at_byte: 3 })
+        );
+        assert_eq!(decode_u16(&[7, 1, 0, 8, 0, 0]), Err(DecodeError::ZeroCount { at_byte: 3 }));
+        // A count of 256 has a zero low byte.
+        assert_eq!(decode_u16(&[8, 0, 1]).map(|decoded| decoded.len()), Ok(256));
+    }
+
+    #[test]
+    fn encode_u16_run_length_edges() {
+        assert_eq!(encode_u16(&[5; 255]), [5, 255, 0]);
+        assert_eq!(encode_u16(&[5; 256]), [5, 0, 1]);
+        assert_eq!(encode_u16(&[5; 65535]), [5, 255, 255]);
+        assert_eq!(encode_u16(&[5; 65536]), [5, 255, 255, 5, 1, 0]);
+
+        for &len in &[255, 256, 65535, 65536] {
+            assert_eq!(decode_u16(&encode_u16(&vec![5; len])), Ok(vec![5; len]));
+        }
+    }
+
+    #[test]
+    fn round_trip_u16() {
+        let bytes: Vec<u8> = [vec![1; 256], vec![2; 1], vec![3; 1000]].concat();
+
+        assert_eq!(decode_u16(&encode_u16(&bytes)), Ok(bytes));
+        assert_eq!(encode_u16(&[]), []);
+    }
+
+    #[test]
+    fn round_trip_runs_longer_than_255() {
+        let bytes: Vec<u8> = [vec![1; 256], vec![2; 1], vec![3; 1000]].concat();
+
+        assert_eq!(decode(&encode(&bytes)), Ok(bytes));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_round_trip(bytes: Vec<u8>) {
+            prop_assert_eq!(decode(&encode(&bytes)), Ok(bytes));
+        }
+
+        #[test]
+        fn prop_encoding_has_even_length(bytes: Vec<u8>) {
+            prop_assert_eq!(encode(&bytes).len() % 2, 0);
+        }
+
+        #[test]
+        fn prop_encoded_len_matches_encode(bytes: Vec<u8>) {
+            prop_assert_eq!(encoded_len(&bytes), encode(&bytes).len());
+        }
+
+        #[test]
+        fn prop_encoded_len_matches_encode_with_long_runs(
+            runs in proptest::collection::vec((any::<u8>(), 1..600usize), 0..20)
+        ) {
+            let bytes: Vec<u8> = runs.into_iter().flat_map(|(b, n)| vec![b; n]).collect();
+
+            prop_assert_eq!(encoded_len(&bytes), encode(&bytes).len());
+        }
+
+        #[test]
+        fn prop_decoded_len_matches_decode(encoded: Vec<u8>) {
+            let expected = decode(&encoded).map(|decoded| decoded.len() as u64);
+
+            prop_assert_eq!(decoded_len(&encoded), expected);
+        }
+
+        #[test]
+        fn prop_counts_are_never_zero(bytes: Vec<u8>) {
+            let encoded = encode(&bytes);
+
+            prop_assert!(encoded.chunks_exact(2).all(|pair| pair[1] != 0));
+        }
+    }
+}
+
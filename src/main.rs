@@ -1,144 +1,4838 @@
+use std::convert::TryInto;
 use std::env;
-use std::fs;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
 
-fn main() {
-    let config;
+use memmap2::Mmap;
+use rle_rs::{
+    base64, blocks, decode_parallel, decode_stream, encode_parallel, encode_stream_with_opts, encode_with_opts,
+    encode_with_stats, hex, text, varint, Analysis, Analyzer, ConfigError, Crc32, DecodeError, EncodeOptions, Format,
+    Header, RleError, Stats, Trailer, MAGIC,
+};
+use rle_rs::base64::{Base64Reader, Base64Writer};
+use rle_rs::hex::{HexReader, HexWriter};
+
+/// The size of the chunks files are read in, and of the most of a file held
+/// in memory while encoding it.
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// The size of the files whose progress is shown without `--progress`.
+const PROGRESS_THRESHOLD: u64 = 256 << 20;
+
+/// The shortest time between two updates of the progress line.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The exit status when every file was processed.
+const EXIT_OK: i32 = 0;
+
+/// The exit status when encoded data is malformed, or anything else failed
+/// that has no status of its own.
+const EXIT_DATA: i32 = 1;
+
+/// The exit status when the command-line arguments are invalid.
+const EXIT_USAGE: i32 = 2;
+
+/// The exit status when reading or writing a file failed.
+const EXIT_IO: i32 = 3;
 
-    match Config::new(env::args().collect::<Vec<String>>().as_slice()) {
-        Ok(c) => config = c,
+/// The exit status when decoded data doesn't match its trailer, or an
+/// encoding doesn't pass `--verify`.
+const EXIT_INTEGRITY: i32 = 4;
+
+/// The exit status when some files were processed and others failed.
+const EXIT_PARTIAL: i32 = 5;
+
+/// The usage text printed along with invalid arguments, and by `--help`.
+const USAGE: &str = concat!(
+    "usage: [options] <filepath>...",
+    "\n       archive [options] <archive> <filepath>...",
+    "\n       extract [options] <archive> [<name>...]",
+    "\n    <filepath> may be - to read stdin and write stdout, or a glob pattern",
+    "\n    such as *.log when no file has that very name; options may come before",
+    "\n    or after the filepaths, and every argument after -- is a filepath",
+    "\n    archive bundles the files into <archive> under their file names, each",
+    "\n    encoded on its own; extract writes the files named, or all of them,",
+    "\n    out of <archive> into the current directory, --output-dir, or stdout",
+    "\n    with -c",
+    "\noptions:",
+    "\n    -h, --help - print this text and exit",
+    "\n    -V, --version - print the version and exit",
+    "\n    -e, --encode - encode, even a file that looks encoded",
+    "\n    -d, --decode - decode; the output is the input path without its .rle",
+    "\n        suffix, or with .dat appended if that file exists (unless -f) or",
+    "\n        there's no suffix; a d before the filepaths does the same, but is",
+    "\n        deprecated",
+    "\n    without -e or -d, files starting with the header are decoded and the",
+    "\n    rest encoded",
+    "\n    -t, --test - decode each file without writing the result, checking that",
+    "\n        it's intact, and print OK or FAILED for it",
+    "\n    --no-checksum - when encoding, write no CRC-32 and length after the",
+    "\n        data; when decoding, check only the length",
+    "\n    --lenient - when decoding, ignore data after the last encoded file in",
+    "\n        a file that isn't one, with a warning, instead of failing",
+    "\n    -c, --stdout - write the result to stdout",
+    "\n    -o, --output <path> - write the result to <path>, to stdout if <path> is",
+    "\n        -, or into <path> if it's a directory",
+    "\n    --output-dir <dir> - write the result into <dir>, creating it if needed;",
+    "\n        with -r, under the subdirectories each file was found in",
+    "\n    -f, --force - overwrite existing output files",
+    "\n    --append - encode onto the end of the output file, after the encoded",
+    "\n        files it holds, instead of replacing it; it decodes to every file",
+    "\n        appended, one after another",
+    "\n    --suffix <ext> - use .<ext> instead of .rle for encoded files",
+    "\n    -r, --recursive - process the files in any directory <filepath>, and in",
+    "\n        its subdirectories; encoding skips files that already have the",
+    "\n        encoded suffix, decoding only takes those that do, and symbolic",
+    "\n        links are skipped; a summary is printed at the end",
+    "\n    -n, --dry-run - process each file without writing the result, and print",
+    "\n        where it would go and how big it would be",
+    "\n    --verify - after encoding a file, decode the result and check that it",
+    "\n        matches the file, printing OK or where they first differ; with --rm,",
+    "\n        the file is only removed if they match",
+    "\n    --rm - remove each input file once its output is written; failing to",
+    "\n        remove it is reported, but doesn't change the exit status",
+    "\n    --format <name> - use the byte (the default), u16, packbits, escape,",
+    "\n        varint or rle0 scheme; decoding reads the scheme from the header",
+    "\n    --u16 - the same as --format u16: use 16-bit run counts",
+    "\n    --leb128 - the same as --format varint: use LEB128 run counts, which",
+    "\n        take 1 byte up to 127, 2 up to 16383, and so on",
+    "\n    --counts <8|16> - the same as --format byte or --format u16: use 8 or",
+    "\n        16-bit run counts",
+    "\n    --unit <1|2|4> - find runs of <n>-byte units, such as 16-bit samples or",
+    "\n        32-bit pixels, instead of bytes; bytes left over at the end of a",
+    "\n        file are stored as they are",
+    "\n    --bits - find runs of bits instead of bytes, for bi-level data such as",
+    "\n        1-bit-per-pixel scans",
+    "\n    --text - write each run as its byte and a decimal count, e.g. A11B1C4,",
+    "\n        without the header, to be read by people; decoding such a file",
+    "\n        needs --text too",
+    "\n    --hex - write the encoded file as hex, in lines of 76 digits, and read",
+    "\n        it back as hex when decoding, ignoring whitespace",
+    "\n    --base64 - the same as --hex, but in base64, which is denser",
+    "\n    --pairs - write the [byte, count] pairs of the default scheme as text,",
+    "\n        e.g. 0x41×3 0x20×1, without the header, to paste into issue reports",
+    "\n        and tests; decoding such a file needs --pairs too",
+    "\n    --packbits - the same as --format packbits: use the PackBits scheme",
+    "\n    --pcx - use the scheme of PCX images",
+    "\n    --delta - encode the differences between bytes instead of the bytes,",
+    "\n        for smooth data such as audio samples and gradients",
+    "\n    --no-header, --raw - neither write nor expect the file header, as in",
+    "\n        files from older versions; decoding then needs the scheme the file",
+    "\n        was encoded with",
+    "\n    --max-run <n> - split runs longer than <n> (1 to 255) bytes, when using",
+    "\n        the default scheme",
+    "\n    --stats - print how well each file would encode without writing",
+    "\n        anything: its size and that of its encoding, its entropy in bits",
+    "\n        per byte, its runs and their lengths, the longest of them, and how",
+    "\n        much of it is in runs of 3 bytes or more; with -e, encode the files",
+    "\n        and print the sizes, ratio and runs of each encoding to stderr,",
+    "\n        only with the default scheme",
+    "\n    --threads <n> - split files into blocks of 1 MiB encoded on <n> threads",
+    "\n        at once, or on as many as there are CPUs if <n> is 0; the result is",
+    "\n        the same whatever <n>; decoding such a file with --threads decodes",
+    "\n        its blocks on <n> threads too",
+    "\n    --parallel - split files into chunks of 1 MiB encoded on every CPU at",
+    "\n        once, each written after the length of its encoding, without the",
+    "\n        header; decoding such a file needs --parallel too",
+    "\n    --chunk-size <n> - the same as --parallel, with chunks of <n> bytes",
+    "\n    --mmap - map each input file into memory instead of reading it, which",
+    "\n        saves a copy of large files; falls back to reading files that can't",
+    "\n        be mapped, such as pipes and empty files; a file changed by another",
+    "\n        program while it's mapped gives an undefined result",
+    "\n    --no-preserve - don't give each output the permissions and modification",
+    "\n        time of its input, which it gets by default (or with --preserve);",
+    "\n        never done for stdin or stdout, and only the time is kept on Windows",
+    "\n    --progress - show how much of each file was read, how fast and how long",
+    "\n        it has left on a line of stderr, updated a few times per second;",
+    "\n        shown without --progress for files over 256 MiB, and never when",
+    "\n        stderr isn't a terminal or for files read with --mmap",
+    "\n    -q, --quiet - print nothing to stderr but errors: no warnings, progress",
+    "\n        or summaries",
+    "\n    -v, --verbose - also print the size of each file and of its result to",
+    "\n        stderr once it's processed; -vv also prints the schemes chosen and",
+    "\n        how long each file took",
+    "\n    --json - print the outcome of each file to stdout as a JSON object on a",
+    "\n        line of its own, instead of any other report; errors are still",
+    "\n        reported to stderr too; the output can't then go to stdout",
+    "\njson objects:",
+    "\n    path - the file, as given",
+    "\n    operation - encode, decode, test or stats, or null if a file failed",
+    "\n        before that was known",
+    "\n    input_size, output_size - the bytes read and written, or that would",
+    "\n        have been written, or null on failure; with --stats, output_size",
+    "\n        is the size of the encoding",
+    "\n    ratio - output_size relative to input_size, or null on failure",
+    "\n    duration_ms - how long the file took, in milliseconds",
+    "\n    status - ok or error",
+    "\n    error - what went wrong, or null",
+    "\n    with --stats, also: runs, mean_run, median_run, long_run_fraction (the",
+    "\n        share of bytes in runs of 3 or more) and longest_run, an object",
+    "\n        with its byte, len and offset, or null for an empty file, and",
+    "\n        entropy, in bits per byte",
+    "\nexit status:",
+    "\n    a failure is reported with the file's name and doesn't stop the",
+    "\n    remaining files; the status is that of the first failure, or 5 if",
+    "\n    other files were processed",
+    "\n    0 - every file was processed",
+    "\n    1 - encoded data is malformed, or anything else failed",
+    "\n    2 - the arguments are invalid",
+    "\n    3 - reading or writing a file failed",
+    "\n    4 - decoded data doesn't match its checksum or length, or an encoding",
+    "\n        doesn't pass --verify",
+    "\n    5 - some files were processed and others failed"
+);
+
+fn main() {
+    process::exit(match try_main() {
+        Ok(status) => status,
+        Err(e @ RleError::Config(_)) => {
+            report::bail(&format!("invalid arguments: {}\n{}", e, USAGE));
+            exit_status(&e)
+        }
         Err(e) => {
-            let e_msg = format!(concat!(
-                "invalid arguments: {}",
-                "\nusage: [options] <filepath>",
-                "\noptions:",
-                "\n    d - decode"
-            ), e);
+            report::bail(&e.to_string());
+            exit_status(&e)
+        }
+    })
+}
+
+/// Run the program on every file named by the command-line arguments and
+/// return the exit status.
+fn try_main() -> Result<i32, RleError> {
+    let args = env::args().collect::<Vec<String>>();
 
-            process::exit( bail(&e_msg));
+    match info_request(&args) {
+        Some(Info::Help) => {
+            writeln!(io::stdout(), "{}", USAGE)?;
+            return Ok(EXIT_OK);
         }
+        Some(Info::Version) => {
+            writeln!(io::stdout(), "rle-rs {}", env!("CARGO_PKG_VERSION"))?;
+            return Ok(EXIT_OK);
+        }
+        None => {}
+    }
+
+    let config = Config::new(&args)?;
+    interrupt::install();
+
+    if config.legacy_decode {
+        report::warn(&config, "'d' is deprecated, use -d or --decode instead");
     }
 
-    process::exit(
-        match run(&config.path, config.do_encode) {
-            Ok(_) => 0,
-            Err(e) => { bail(&e.to_string()) }
+    match config.command {
+        Command::Convert => {}
+        Command::Archive => return archive(&config).map(|_| EXIT_OK),
+        Command::Extract => return extract(&config).map(|_| EXIT_OK),
+    }
+
+    // Keep going past files that fail, but report the failure in the exit
+    // code.
+    let mut tally = Tally::default();
+
+    for pattern in &config.paths {
+        let paths = match expand(pattern, &config) {
+            Ok(paths) => paths,
+            Err(e) => {
+                tally.record(pattern, Err(e.into()), Duration::ZERO, &config);
+                continue;
+            }
+        };
+
+        for path in &paths {
+            if config.recursive && Path::new(path).is_dir() {
+                run_dir(Path::new(path), Path::new(path), &config, &mut tally);
+            } else {
+                tally.run(path, Path::new(""), &config);
+            }
         }
-    )
+    }
+
+    if config.recursive {
+        report::note(&config, &tally.to_string());
+    }
+
+    Ok(tally.exit_status())
 }
 
-/// Run the program.
+/// Return the exit status reporting `e`, as described by [`USAGE`].
 ///
-/// * `path` - The path to the file.
-/// * `do_encode` - Indicate whether the file should be encoded or decoded.
-fn run(path: &str, do_encode: bool) -> Result<(), std::io::Error> {
-    let func: fn(&[u8]) -> Vec<u8>;
-    // The extension of the final file. Doesn't try to replace the previous one.
-    // e.g.: encoding a .txt produces a .txt.rle, which then becomes
-    // a .txt.rle.dat on decoding.
-    let ext;
-
-    if do_encode {
-        func = encode;
-        ext = "rle";
-    } else {
-        func = decode;
-        ext = "dat";
-    }
-    
-    fs::read(path)
-        .map(|bytes| func(&bytes))
-        .and_then(|result| {
-            fs::write(format!("{}.{}", path, ext), result) }
+/// * `e` - The error the program failed with.
+fn exit_status(e: &RleError) -> i32 {
+    match e {
+        RleError::Config(_) => EXIT_USAGE,
+        RleError::Decode(DecodeError::ChecksumMismatch { .. } | DecodeError::LengthMismatch { .. }) => {
+            EXIT_INTEGRITY
+        }
+        RleError::Decode(_) | RleError::Parse(_) => EXIT_DATA,
+        RleError::Io(e) if e.get_ref().is_some_and(|inner| inner.is::<Mismatch>()) => EXIT_INTEGRITY,
+        // Errors about a file may wrap what is wrong with its data.
+        RleError::File { error, .. } => match error.get_ref().and_then(|inner| inner.downcast_ref::<DecodeError>()) {
+            Some(e) => exit_status(&RleError::Decode(e.clone())),
+            None => EXIT_IO,
+        },
+        RleError::Io(_) => EXIT_IO,
+    }
+}
+
+/// What the program prints instead of processing any file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Info {
+    /// The usage text.
+    Help,
+    /// The version of the program.
+    Version,
+}
+
+/// Return what the command-line arguments ask to be printed instead of
+/// processing any file, if anything, whatever the other arguments.
+///
+/// * `args` - The command-line arguments, as given to [`Config::new`].
+fn info_request(args: &[String]) -> Option<Info> {
+    args.iter()
+        .skip(1)
+        .take_while(|arg| *arg != "--")
+        .find_map(|arg| match arg.as_str() {
+            "-h" | "--help" => Some(Info::Help),
+            "-V" | "--version" => Some(Info::Version),
+            _ => None,
+        })
+}
+
+/// Count the files processed and the failures over a whole run.
+///
+/// * `processed` - How many files were processed successfully.
+/// * `failed` - How many files, patterns or directories failed.
+/// * `first_failure` - The exit status reporting the first failure, if any.
+#[derive(Debug, Default)]
+struct Tally {
+    processed: usize,
+    failed: usize,
+    first_failure: Option<i32>,
+}
+
+impl Tally {
+    /// Run the program on a single file and record the outcome.
+    ///
+    /// * `path` - The path to the file, or `-` for stdin and stdout.
+    /// * `within` - The directory the file is in, relative to the directory
+    ///   walked with `-r` it was found under.
+    /// * `config` - How to run the program.
+    fn run(&mut self, path: &str, within: &Path, config: &Config) {
+        let start = Instant::now();
+        let result = run(path, within, config);
+        report::debug(config, &format!("{}: took {:.3} ms", path, start.elapsed().as_secs_f64() * 1e3));
+
+        self.record(path, result, start.elapsed(), config);
+    }
+
+    /// Record the outcome of processing a file, reporting it if it failed or
+    /// with `-v`, and printing it as JSON with `--json`.
+    ///
+    /// * `path` - The path the outcome is about.
+    /// * `result` - The outcome.
+    /// * `elapsed` - How long processing the file took.
+    /// * `config` - How the program is being run.
+    fn record(
+        &mut self, path: &str, result: Result<Outcome, RleError>, elapsed: Duration, config: &Config
+    ) {
+        if config.json {
+            println!("{}", json_report(path, &result, elapsed, config));
+        }
+
+        match result {
+            Ok(outcome) => {
+                report::info(config, &format!("{}: {}", path, summary(&outcome)));
+                self.processed += 1;
+            }
+            Err(e) => {
+                report::bail(&format!("{}: {}", path, e));
+                self.failed += 1;
+                self.first_failure = self.first_failure.or(Some(exit_status(&e)));
+            }
+        }
+    }
+
+    /// Return the exit status of the whole run, as described by [`USAGE`].
+    fn exit_status(&self) -> i32 {
+        match self.first_failure {
+            None => EXIT_OK,
+            Some(_) if self.processed > 0 => EXIT_PARTIAL,
+            Some(status) => status,
+        }
+    }
+}
+
+impl fmt::Display for Tally {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} file{} processed, {} error{}",
+            self.processed,
+            if self.processed == 1 { "" } else { "s" },
+            self.failed,
+            if self.failed == 1 { "" } else { "s" },
         )
+    }
 }
 
-/// Read a byte vector and return its run-length encoding.
+/// Return the sizes of a file and of its result, as printed with `-v`, e.g.
+/// `83,412,992 -> 1,204,551 bytes (1.4%)`.
 ///
-/// * `bytes` - The bytes to be encoded.
-fn encode(bytes: &[u8]) -> Vec<u8> {
-    let mut encoding;
+/// * `outcome` - The outcome of processing the file.
+fn summary(outcome: &Outcome) -> String {
+    let ratio = match outcome.input_size {
+        0 => 0.0,
+        input_size => outcome.output_size as f64 / input_size as f64,
+    };
 
-    if bytes.first().is_none() {
-        return vec![];
-    } else {
-        encoding = vec![*bytes.first().unwrap()];
+    format!(
+        "{} -> {} bytes ({:.1}%)",
+        separated(outcome.input_size),
+        separated(outcome.output_size),
+        ratio * 100.0
+    )
+}
+
+/// Return `n` in decimal, with its digits in groups of 3 separated by commas.
+///
+/// * `n` - The number to write.
+fn separated(n: u64) -> String {
+    let digits = n.to_string();
+    let mut separated = String::with_capacity(digits.len() + digits.len() / 3);
+
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            separated.push(',');
+        }
+
+        separated.push(digit);
     }
 
-    let mut occurrences = 1;
-    
-    for byte in bytes.iter().skip(1) {
-        if byte == encoding.last().unwrap() && occurrences < 255 {
-            occurrences += 1;
-        } else {
-            encoding.extend(&[occurrences, *byte]);
-            occurrences = 1;
+    separated
+}
+
+/// Return the outcome of processing a file as a single line of JSON, in the
+/// schema described by [`USAGE`].
+///
+/// * `path` - The path the outcome is about.
+/// * `result` - The outcome.
+/// * `elapsed` - How long processing the file took.
+/// * `config` - How the program is being run.
+fn json_report(
+    path: &str, result: &Result<Outcome, RleError>, elapsed: Duration, config: &Config
+) -> String {
+    let operation = match result {
+        Ok(outcome) => Some(outcome.operation),
+        // Without -e or -d, what a file would have gone through may not be
+        // known yet.
+        Err(_) if config.analyze => Some("stats"),
+        Err(_) if config.test => Some("test"),
+        Err(_) => match config.mode {
+            Mode::Encode => Some("encode"),
+            Mode::Decode => Some("decode"),
+            Mode::Auto => None,
+        },
+    };
+    let mut fields = vec![
+        ("path", json_string(path)),
+        ("operation", operation.map_or("null".into(), json_string)),
+    ];
+
+    match result {
+        Ok(outcome) => {
+            let ratio = match outcome.input_size {
+                0 => 0.0,
+                size => outcome.output_size as f64 / size as f64,
+            };
+
+            fields.push(("input_size", outcome.input_size.to_string()));
+            fields.push(("output_size", outcome.output_size.to_string()));
+            fields.push(("ratio", format!("{:.6}", ratio)));
+        }
+        Err(_) => {
+            fields.push(("input_size", "null".into()));
+            fields.push(("output_size", "null".into()));
+            fields.push(("ratio", "null".into()));
+        }
+    }
+
+    fields.push(("duration_ms", format!("{:.3}", elapsed.as_secs_f64() * 1000.0)));
+    fields.push(("status", json_string(if result.is_ok() { "ok" } else { "error" })));
+    fields.push((
+        "error",
+        result.as_ref().err().map_or("null".into(), |e| json_string(&e.to_string())),
+    ));
+
+    if let Ok(Outcome { analysis: Some(analysis), .. }) = result {
+        let longest_run = match analysis.longest_run {
+            Some(run) => format!(
+                "{{\"byte\":{},\"len\":{},\"offset\":{}}}",
+                run.byte, run.len, analysis.longest_run_offset
+            ),
+            None => "null".into(),
+        };
+
+        fields.push(("runs", analysis.run_count.to_string()));
+        fields.push(("mean_run", format!("{:.6}", analysis.mean_run())));
+        fields.push(("median_run", analysis.median_run().to_string()));
+        fields.push(("longest_run", longest_run));
+        fields.push(("long_run_fraction", format!("{:.6}", analysis.long_run_fraction())));
+        fields.push(("entropy", format!("{:.6}", analysis.entropy())));
+    }
+
+    let fields: Vec<String> = fields
+        .into_iter()
+        .map(|(name, value)| format!("{}:{}", json_string(name), value))
+        .collect();
+
+    format!("{{{}}}", fields.join(","))
+}
+
+/// Return `s` as a JSON string, quoted and escaped.
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c < ' ' => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+
+    json
+}
+
+/// Return the paths matching a glob pattern, in sorted order, for shells that
+/// don't expand patterns themselves.
+///
+/// `path` itself is returned if it exists or isn't a valid pattern, so files
+/// with `*` and the like in their names can still be named.
+///
+/// * `path` - The path or pattern given on the command line.
+/// * `config` - How the program is being run.
+fn expand(path: &str, config: &Config) -> Result<Vec<String>, io::Error> {
+    let pattern = match glob::Pattern::new(path) {
+        Ok(pattern) if path != "-" && !Path::new(path).exists() => pattern,
+        _ => return Ok(vec![path.to_owned()]),
+    };
+
+    // A path with nothing to expand is left to fail as a missing file.
+    if pattern.as_str() == glob::Pattern::escape(path) {
+        return Ok(vec![path.to_owned()]);
+    }
+
+    let mut paths = Vec::new();
+
+    for entry in glob::glob(path).map_err(io::Error::other)? {
+        let entry = entry.map_err(|e| io::Error::other(e.to_string()))?;
+
+        match entry.into_os_string().into_string() {
+            Ok(path) => paths.push(path),
+            Err(p) => {
+                return Err(io::Error::other(format!("{:?}: path is not valid UTF-8", p)))
+            }
         }
     }
 
-    encoding.push(occurrences);
+    paths.sort();
+
+    if paths.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no files match the pattern"));
+    }
+
+    if config.output.is_some() && paths.len() > 1 {
+        return Err(io::Error::other("-o can't be used with a pattern matching several files"));
+    }
 
-    encoding
-} 
+    Ok(paths)
+}
 
-/// Read a run-length encoding and return its decoded contents.
+/// Run the program on every file under a directory it applies to, recording
+/// each outcome without stopping at failures.
+///
+/// Encoding skips files that already have the encoded suffix, and decoding
+/// skips those that don't. Symbolic links aren't followed, so a link to a
+/// parent directory can't send the walk into a loop.
 ///
-/// * `bytes` - The bytes to be decoded.
-fn decode(bytes: &[u8]) -> Vec<u8> {
-    let mut decoding = Vec::<u8>::new();
+/// * `root` - The directory given on the command line, which the outputs
+///   mirror the subdirectories of under `--output-dir`.
+/// * `dir` - The directory to walk.
+/// * `config` - How to run the program.
+/// * `tally` - Where the outcomes are recorded.
+fn run_dir(root: &Path, dir: &Path, config: &Config, tally: &mut Tally) {
+    // Listing the directory before processing it keeps the walk from running
+    // into the files it creates.
+    let mut entries = match fs::read_dir(dir).and_then(|d| {
+        d.map(|entry| entry.map(|e| e.path())).collect::<io::Result<Vec<_>>>()
+    }) {
+        Ok(entries) => entries,
+        Err(e) => {
+            tally.record(&dir.to_string_lossy(), Err(e.into()), Duration::ZERO, config);
+            return;
+        }
+    };
+    entries.sort();
+
+    let suffix = config.suffix.as_deref().unwrap_or("rle");
+
+    for entry in entries {
+        let file_type = match fs::symlink_metadata(&entry) {
+            Ok(metadata) => metadata.file_type(),
+            Err(e) => {
+                tally.record(&entry.to_string_lossy(), Err(e.into()), Duration::ZERO, config);
+                continue;
+            }
+        };
+
+        if file_type.is_dir() {
+            run_dir(root, &entry, config, tally);
+            continue;
+        }
 
-    for (i, byte) in bytes.iter().enumerate() {
-        if i % 2 != 0 {
+        let path = match entry.to_str() {
+            Some(path) if file_type.is_file() => path,
+            Some(_) => continue,
+            None => {
+                let e = io::Error::other("path is not valid UTF-8");
+                tally.record(&entry.to_string_lossy(), Err(e.into()), Duration::ZERO, config);
+                continue;
+            }
+        };
+
+        // Only decoding takes files with the suffix, so that a second run
+        // doesn't encode the outputs of the first.
+        if strip_suffix(path, suffix).is_some() != (config.mode == Mode::Decode) {
             continue;
         }
 
-        // Repeat bytes[i], bytes[i+1] times in a row.
-        // e.g.: "!!" equals to 33 times "!" ("!" value in ASCII).
-        for _j in 0..bytes[i+1] {
-            decoding.push(*byte)
+        tally.run(path, dir.strip_prefix(root).unwrap_or(dir), config);
+    }
+}
+
+/// Bundle the files named by the filepaths after the first into an archive
+/// at the first, under their file names.
+///
+/// * `config` - How the program is being run.
+fn archive(config: &Config) -> Result<(), RleError> {
+    let (archive, patterns) = config.paths.split_first().ok_or(ConfigError::NoFilepath)?;
+    let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+
+    for pattern in patterns {
+        for path in expand(pattern, config)? {
+            // Only a file's name is kept, which extracting it can't take out
+            // of the directory it goes to.
+            let name = match Path::new(&path).file_name().and_then(OsStr::to_str) {
+                Some(name) if name.len() <= 255 => name.to_owned(),
+                _ => {
+                    let e = io::Error::new(io::ErrorKind::InvalidInput, "its name can't be stored in an archive");
+                    return Err(RleError::at(&path)(e));
+                }
+            };
+
+            if files.iter().any(|(other, _)| *other == name) {
+                let e = io::Error::new(io::ErrorKind::InvalidInput, format!("another file is archived as '{}'", name));
+                return Err(RleError::at(&path)(e));
+            }
+
+            let bytes = fs::read(&path).map_err(RleError::at(&path))?;
+            report::info(config, &format!("{}: archived as '{}'", path, name));
+            files.push((name, bytes));
         }
     }
 
-    decoding
+    let files: Vec<(&str, &[u8])> = files.iter().map(|(name, bytes)| (name.as_str(), &bytes[..])).collect();
+    let mut output = Output::create(Path::new(archive), config.force)?;
+    output.file.write_all(&rle_rs::create_archive(&files)).map_err(RleError::at(output.written_path()))?;
+
+    output.persist(config.force)
 }
 
-/// Print an error message to stderr and return 1.
+/// Write the files named by the filepaths after the first out of the archive
+/// at the first, or all of its files if none is named, into the current
+/// directory or `--output-dir`, or to stdout with `-c`.
 ///
-/// * `msg` - The message that will be printed.
-fn bail(msg: &str) -> i32 {
-    eprintln!("rle-rs: error: {}", msg);
-    1
+/// * `config` - How the program is being run.
+fn extract(config: &Config) -> Result<(), RleError> {
+    let (archive, names) = config.paths.split_first().ok_or(ConfigError::NoFilepath)?;
+    let bytes = fs::read(archive).map_err(RleError::at(archive))?;
+    let invalid = |e| match e {
+        RleError::Decode(e) => RleError::at(archive)(io::Error::new(io::ErrorKind::InvalidData, e)),
+        e => e,
+    };
+    let names = if names.is_empty() { rle_rs::list_archive(&bytes).map_err(invalid)? } else { names.to_vec() };
+    let dir = Path::new(config.output_dir.as_deref().unwrap_or("."));
+
+    if !config.stdout {
+        fs::create_dir_all(dir).map_err(RleError::at(dir))?;
+    }
+
+    for name in &names {
+        // Archives from elsewhere may name files such as ../../.bashrc.
+        if !config.stdout && Path::new(name).file_name() != Some(OsStr::new(name)) {
+            let e = io::Error::new(io::ErrorKind::InvalidData, format!("the file name '{}' in it is a path", name));
+            return Err(RleError::at(archive)(e));
+        }
+
+        let contents = rle_rs::extract_file(&bytes, name).map_err(invalid)?;
+
+        if config.stdout {
+            io::stdout().write_all(&contents)?;
+            continue;
+        }
+
+        let mut output = Output::create(&dir.join(name), config.force)?;
+        output.file.write_all(&contents).map_err(RleError::at(output.written_path()))?;
+        output.persist(config.force)?;
+        report::info(config, &format!("{}: extracted '{}'", archive, name));
+    }
+
+    Ok(())
 }
 
-/// Hold configuration information needed for the program to run.
+/// What processing a file did, as reported with `--json`.
 ///
-/// * `do_encode` - Wheter the file shall be encoded (true) or decoded (false).
-/// * `path` - The filepath.
-struct Config {
-    do_encode: bool,
-    path: String,
+/// * `operation` - What was done to the file: `encode`, `decode`, `test` or
+///   `stats`.
+/// * `input_size` - How many bytes were read.
+/// * `output_size` - How many bytes were written, or would have been.
+/// * `analysis` - The analysis of the file, if it was only analyzed.
+#[derive(Debug)]
+struct Outcome {
+    operation: &'static str,
+    input_size: u64,
+    output_size: u64,
+    analysis: Option<Analysis>,
 }
 
-impl Config {
-    /// Create a new Config struct based on user input.
-    ///
-    /// * `args` - The command-line arguments used to create the struct.
-    ///     Usage: [options] <filepath>
-    ///     Options:
-    ///         d - decode
-    fn new(args: &[String]) -> Result<Self, &str> {
-        if args.len() < 2 {
-            return Err("no argument was specified")
+/// Run the program on a single file.
+///
+/// * `path` - The path to the file, or `-` for stdin and stdout.
+/// * `within` - The directory the file is in, relative to the directory
+///   walked with `-r` it was found under.
+/// * `config` - How to run the program.
+fn run(path: &str, within: &Path, config: &Config) -> Result<Outcome, RleError> {
+    let file = if path == "-" { None } else { Some(File::open(path)?) };
+    let mapped = file.as_ref().filter(|_| config.mmap).and_then(map_file);
+
+    let metadata = file.as_ref().and_then(|f| f.metadata().ok());
+    // Only regular files have a size to show the progress against.
+    let total = metadata.as_ref().filter(|m| m.is_file()).map(|m| m.len());
+    let progress = config.verbosity > Verbosity::Quiet
+        && (config.progress || total.is_some_and(|len| len > PROGRESS_THRESHOLD))
+        && io::stderr().is_terminal();
+
+    let input: Box<dyn Read> = if let Some(map) = &mapped {
+        Box::new(&map[..])
+    } else if let Some(file) = file {
+        if progress {
+            Box::new(BufReader::with_capacity(CHUNK_SIZE, Progress::new(file, path, total)))
+        } else {
+            Box::new(BufReader::with_capacity(CHUNK_SIZE, file))
+        }
+    } else {
+        let stdin = io::stdin();
+
+        if stdin.is_terminal() {
+            return Err(io::Error::other("no input: stdin is a terminal").into());
+        }
+
+        if progress {
+            Box::new(Progress::new(stdin.lock(), path, None))
+        } else {
+            Box::new(stdin.lock())
+        }
+    };
+
+    // A mapped input is mostly used as it is, without reading from `input`.
+    let mut counted = Counted::new(input);
+    let input_size = |counted: &Counted<_>| mapped.as_ref().map_or(counted.len, |m| m.len() as u64);
+    let input: Box<dyn Read + '_> = Box::new(&mut counted);
+
+    // Analyzing only reads the input, so no output is created.
+    if config.analyze {
+        let analysis = match &mapped {
+            Some(map) => Analysis::of(map),
+            None => analyze(input)?,
+        };
+
+        if !config.json {
+            println!("{}:", path);
+
+            for line in analysis.to_string().lines() {
+                println!("    {}", line);
+            }
+        }
+
+        return Ok(Outcome {
+            operation: "stats",
+            input_size: analysis.input_size,
+            output_size: analysis.encoded_size,
+            analysis: Some(analysis),
+        });
+    }
+
+    let (input, encode) = match config.mode {
+        Mode::Encode => (input, true),
+        Mode::Decode => (input, false),
+        // Without headers there's nothing to tell an encoded file by.
+        Mode::Auto if !config.header => (input, true),
+        Mode::Auto => {
+            let expected = config.transport.magic();
+            let mut magic = Vec::with_capacity(expected.len());
+            let mut input = input;
+            input.by_ref().take(expected.len() as u64).read_to_end(&mut magic)?;
+            let encode = magic != expected;
+
+            // Put the bytes back in front of the rest of the input.
+            (Box::new(io::Cursor::new(magic).chain(input)) as Box<dyn Read + '_>, encode)
+        }
+    };
+    let operation = if encode { "encode" } else { "decode" };
+
+    if encode {
+        report::debug(config, &format!("{}: encoding with the {} scheme", path, scheme_name(config.format)));
+    }
+
+    // Testing decodes into nothing, so no output is created.
+    if config.test {
+        let mut output = Counted::new(io::sink());
+        let result = convert_input(path, mapped.as_deref(), input, &mut output, false, config);
+
+        if !config.json {
+            println!("{}: {}", path, if result.is_ok() { "OK" } else { "FAILED" });
+        }
+
+        result?;
+
+        return Ok(Outcome {
+            operation: "test",
+            input_size: input_size(&counted),
+            output_size: output.len,
+            analysis: None,
+        });
+    }
+
+    let destination = destination(path, within, encode, config)?;
+
+    // Verifying reads the input and output again, which only files allow.
+    let verify = config.verify && encode;
+
+    if verify && (path == "-" || destination.is_none()) {
+        return Err(io::Error::other("--verify needs both the input and output to be files").into());
+    }
+
+    if config.dry_run {
+        let mut output = Counted::new(io::sink());
+        convert(path, input, &mut output, encode, config)?;
+        let input_size = counted.len;
+
+        if !config.json {
+            let ratio = if input_size == 0 { 0.0 } else { output.len as f64 / input_size as f64 };
+
+            println!(
+                "{} -> {}: {} -> {} bytes ({:.2}%)",
+                path,
+                destination.as_ref().map_or("stdout".into(), |o| o.to_string_lossy()),
+                input_size,
+                output.len,
+                ratio * 100.0
+            );
+        }
+
+        return Ok(Outcome { operation, input_size, output_size: output.len, analysis: None });
+    }
+
+    // The results are written to stdout, where they'd mix with the output.
+    if config.json && destination.is_none() {
+        return Err(io::Error::other("--json needs the output to be a file").into());
+    }
+
+    let (stats, output_size) = match &destination {
+        None => {
+            let mut output = Counted::new(BufWriter::new(io::stdout().lock()));
+            let stats = convert_input(path, mapped.as_deref(), input, &mut output, encode, config)?;
+
+            (stats, output.len)
         }
+        Some(o) => {
+            // The directories under --output-dir mirror those walked.
+            if let Some(dir) = o.parent().filter(|_| config.output_dir.is_some() || within != Path::new("")) {
+                fs::create_dir_all(dir).map_err(RleError::at(dir))?;
+            }
+
+            let mut file = if config.append {
+                check_appendable(o, config)?;
+                Output::append(o)?
+            } else {
+                Output::create(o, config.force)?
+            };
+            let mut output = Counted::new(BufWriter::new(&file.file));
+            let stats = convert_input(path, mapped.as_deref(), input, &mut output, encode, config)?;
+            let output_size = output.len;
+            drop(output);
+
+            // A result that fails verification never takes the output's name.
+            if verify {
+                self::verify(path, file.written_path(), config)?;
 
-        if args[1] == "d" {
-            if args.get(1).is_none() {
-                return Err("no filepath was specified")
+                if !config.json {
+                    println!("{}: OK", path);
+                }
             }
 
-            return Ok(Self { do_encode: false, path: args[2].clone() })
+            // A file appended to keeps its own permissions and time.
+            if let Some(metadata) = metadata.as_ref().filter(|_| config.preserve && !config.append) {
+                // The result is there all the same, so this is no failure.
+                if let Err(e) = file.copy_attributes(metadata) {
+                    let message = format!("{}: couldn't preserve the permissions and time of '{}': {}", path, o.display(), e);
+                    report::warn(config, &message);
+                }
+            }
+
+            file.persist(config.force || config.append)?;
+
+            (stats, output_size)
+        }
+    };
+
+    if let Some(stats) = stats {
+        report::note(config, &format!("{}: {}", path, stats));
+    }
+
+    if config.remove && destination.is_some() && path != "-" {
+        // The conversion itself succeeded, so this is only worth a warning.
+        if let Err(e) = fs::remove_file(path) {
+            report::warn(config, &format!("{}: couldn't remove input: {}", path, e));
+        }
+    }
+
+    Ok(Outcome { operation, input_size: input_size(&counted), output_size, analysis: None })
+}
+
+/// Check that the file at `path`, if there is one, is made of whole encoded
+/// files, by decoding it, so that another can be appended to it.
+///
+/// * `path` - The path to the file.
+/// * `config` - How the program is being run.
+fn check_appendable(path: &Path, config: &Config) -> Result<(), RleError> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(RleError::at(path)(e)),
+    };
+    let metadata = file.metadata().map_err(RleError::at(path))?;
+
+    // Devices are only written to, and an empty file has no end to check.
+    if !metadata.is_file() || metadata.len() == 0 {
+        return Ok(());
+    }
+
+    let input = BufReader::with_capacity(CHUNK_SIZE, file);
+
+    match convert(&path.to_string_lossy(), input, io::sink(), false, config) {
+        Ok(_) => Ok(()),
+        Err(e @ RleError::File { .. }) => Err(e),
+        Err(e) => {
+            let message = format!("it doesn't end with a whole encoded file, so nothing can be appended to it: {}", e);
+
+            Err(RleError::at(path)(io::Error::new(io::ErrorKind::InvalidData, message)))
+        }
+    }
+}
+
+/// Analyze everything read from `input`, a chunk at a time.
+///
+/// * `input` - Where the bytes to be analyzed come from.
+fn analyze(mut input: impl Read) -> io::Result<Analysis> {
+    let mut analyzer = Analyzer::new();
+    let mut chunk = vec![0; CHUNK_SIZE];
+
+    loop {
+        match input.read(&mut chunk) {
+            Ok(0) => return Ok(analyzer.finish()),
+            Ok(n) => analyzer.push_slice(&chunk[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Map `file` into memory, or return `None` if it can't be, as with pipes, or
+/// is empty, which can't be mapped at all.
+///
+/// * `file` - The file to map.
+fn map_file(file: &File) -> Option<Mmap> {
+    if file.metadata().ok()?.len() == 0 {
+        return None;
+    }
+
+    // SAFETY: the mapping is only read from, but another program can still
+    // change the file while it's mapped, which the user accepts by asking for
+    // --mmap.
+    unsafe { Mmap::map(file) }.ok()
+}
+
+/// Decode the encoding of a file and check that it matches the file, reading
+/// both a chunk at a time.
+///
+/// * `path` - The path to the file.
+/// * `output` - The path to its encoding.
+/// * `config` - How the program is being run.
+fn verify(path: &str, output: &Path, config: &Config) -> Result<(), RleError> {
+    let mut original = Compare::new(BufReader::new(File::open(path)?));
+    let encoding = File::open(output).map_err(RleError::at(output))?;
+    convert(path, encoding, &mut original, false, config)?;
+
+    Ok(original.finish()?)
+}
+
+/// A writer that checks what's written to it against what's read from a
+/// reader.
+///
+/// * `expected` - The reader the written bytes must match.
+/// * `offset` - How many bytes matched so far.
+struct Compare<R> {
+    expected: R,
+    offset: u64,
+}
+
+impl<R: Read> Compare<R> {
+    fn new(expected: R) -> Self {
+        Self { expected, offset: 0 }
+    }
+
+    /// Check that nothing more was expected than what was written.
+    fn finish(mut self) -> Result<(), io::Error> {
+        let mut byte = [0];
+
+        match self.expected.read(&mut byte)? {
+            0 => Ok(()),
+            _ => Err(self.mismatch(0, Some(byte[0]), None)),
+        }
+    }
+
+    /// Return the error reporting a difference `at` bytes past the offset,
+    /// where `None` stands for the end of the data.
+    fn mismatch(&self, at: usize, expected: Option<u8>, actual: Option<u8>) -> io::Error {
+        let show = |byte: Option<u8>| byte.map_or("the end".to_owned(), |b| format!("{:#04x}", b));
+
+        io::Error::new(io::ErrorKind::InvalidData, Mismatch(format!(
+            "verification failed at offset {}: the input has {}, the decoded output {}",
+            self.offset + at as u64,
+            show(expected),
+            show(actual)
+        )))
+    }
+}
+
+/// A difference found by [`Compare`], which tells a failed verification
+/// apart from other errors: the message reporting it.
+#[derive(Debug)]
+struct Mismatch(String);
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Mismatch {}
+
+impl<R: Read> Write for Compare<R> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut expected = Vec::with_capacity(buf.len());
+        (&mut self.expected).take(buf.len() as u64).read_to_end(&mut expected)?;
+
+        if let Some(at) = buf.iter().zip(&expected).position(|(a, b)| a != b) {
+            return Err(self.mismatch(at, Some(expected[at]), Some(buf[at])));
+        }
+
+        // A shorter read means the input ended where the output goes on.
+        if expected.len() < buf.len() {
+            return Err(self.mismatch(expected.len(), None, Some(buf[expected.len()])));
         }
 
-        Ok(Self { do_encode: true, path: args[1].clone() })
+        self.offset += buf.len() as u64;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Return the path the result of processing a file is written to, or `None`
+/// if it is written to stdout.
+///
+/// * `path` - The path to the input file, or `-` for stdin.
+/// * `within` - The directory the file is in, relative to the directory
+///   walked with `-r` it was found under, which the output goes into under
+///   an output directory.
+/// * `encode` - Whether the file is being encoded (true) or decoded (false).
+/// * `config` - How the program is being run.
+fn destination(path: &str, within: &Path, encode: bool, config: &Config) -> Result<Option<PathBuf>, io::Error> {
+    // An existing directory given to -o is treated like one given to
+    // --output-dir.
+    let output_dir = match &config.output {
+        Some(o) if o == "-" => return Ok(None),
+        Some(o) if Path::new(o).is_dir() => Some(o),
+        Some(o) => {
+            // Creating the output truncates it, which would destroy the input
+            // before it is read.
+            if is_same_file(path, o) {
+                return Err(io::Error::other(format!("output '{}' is the input file", o)));
+            }
+
+            return Ok(Some(PathBuf::from(o)));
+        }
+        None => config.output_dir.as_ref(),
+    };
+
+    if path == "-" || config.stdout {
+        return Ok(None);
+    }
+
+    if let Some(dir) = output_dir {
+        let name = Path::new(path)
+            .file_name()
+            .ok_or_else(|| io::Error::other(format!("'{}' does not name a file", path)))?;
+
+        return Ok(Some(Path::new(dir).join(within).join(output_path(&name.to_string_lossy(), encode, config))));
+    }
+
+    Ok(Some(PathBuf::from(output_path(path, encode, config))))
+}
+
+/// Encode or decode everything read from `input` and write the result to
+/// `output`, reading or writing the encoded side as hex, base64 or pairs if
+/// asked to.
+///
+/// * `path` - The path to the input file, or `-` for stdin.
+/// * `input` - Where the bytes to be processed come from.
+/// * `output` - Where the result goes.
+/// * `encode` - Whether to encode (true) or decode (false).
+/// * `config` - How the program is being run.
+///
+/// Return the statistics of the encoding, if they were asked for.
+fn convert(
+    path: &str, mut input: impl Read, mut output: impl Write, encode: bool, config: &Config
+) -> Result<Option<Stats>, RleError> {
+    match (config.transport, encode) {
+        (Transport::Binary, _) => convert_format(path, input, output, encode, config),
+        (Transport::Hex, true) => {
+            let mut output = HexWriter::with_line_len(output, 76);
+            let stats = convert_format(path, input, &mut output, true, config)?;
+            output.finish()?.flush()?;
+
+            Ok(stats)
+        }
+        (Transport::Hex, false) => convert_format(path, HexReader::new(input), output, false, config),
+        (Transport::Base64, true) => {
+            let mut output = Base64Writer::with_line_len(output, 76);
+            let stats = convert_format(path, input, &mut output, true, config)?;
+            output.finish()?.flush()?;
+
+            Ok(stats)
+        }
+        (Transport::Base64, false) => {
+            convert_format(path, Base64Reader::new(input), output, false, config)
+        }
+        // The pairs are only written once they're all known, as the text
+        // between them depends on where each starts.
+        (Transport::Pairs, true) => {
+            let mut encoding = Vec::new();
+            let stats = convert_format(path, input, &mut encoding, true, config)?;
+            writeln!(output, "{}", text::to_text(&encoding))?;
+            output.flush()?;
+
+            Ok(stats)
+        }
+        (Transport::Pairs, false) => {
+            let mut pairs = String::new();
+            input.read_to_string(&mut pairs)?;
+            let encoding = text::from_text(&pairs)?;
+
+            convert_format(path, &encoding[..], output, false, config)
+        }
+    }
+}
+
+/// Encode or decode the input, from `mapped` if the input file was mapped
+/// into memory and from `input` otherwise, and write the result to `output`.
+///
+/// * `path` - The path to the input file, or `-` for stdin.
+/// * `mapped` - The contents of the input file, if it was mapped.
+/// * `input` - Where the bytes to be processed come from otherwise.
+/// * `output` - Where the result goes.
+/// * `encode` - Whether to encode (true) or decode (false).
+/// * `config` - How the program is being run.
+///
+/// Return the statistics of the encoding, if they were asked for.
+fn convert_input(
+    path: &str,
+    mapped: Option<&[u8]>,
+    input: impl Read,
+    output: impl Write,
+    encode: bool,
+    config: &Config,
+) -> Result<Option<Stats>, RleError> {
+    match mapped {
+        // Hex, base64 and pairs are read and written as streams anyway.
+        Some(bytes) if config.transport == Transport::Binary => {
+            convert_mapped(path, bytes, output, encode, config)
+        }
+        _ => convert(path, input, output, encode, config),
+    }
+}
+
+/// Encode or decode `bytes`, the whole of a mapped input file, and write the
+/// result to `output`, along with the header and trailer unless they're
+/// disabled.
+///
+/// Unlike [`convert_format`], the input is already in memory, so data that
+/// encoding wouldn't shrink is stored as it is whatever its size.
+///
+/// * `path` - The path to the input file.
+/// * `bytes` - The contents of the input file.
+/// * `output` - Where the result goes.
+/// * `encode` - Whether to encode (true) or decode (false).
+/// * `config` - How the program is being run.
+///
+/// Return the statistics of the encoding, if they were asked for.
+fn convert_mapped(
+    path: &str, bytes: &[u8], mut output: impl Write, encode: bool, config: &Config
+) -> Result<Option<Stats>, RleError> {
+    if let Some(chunk_size) = config.parallel {
+        return convert_parallel(bytes, output, encode, chunk_size).map(|_| None);
+    }
+
+    if !encode {
+        // Encoded files may hold several, which are found as they're read.
+        if config.header {
+            return convert_format(path, bytes, output, false, config);
+        }
+
+        output.write_all(&config.format.decode(bytes)?)?;
+        output.flush()?;
+
+        return Ok(None);
+    }
+
+    if let Some(threads) = config.threads {
+        Header { blocks: true, ..config.file_header(config.format) }.write_to(&mut output)?;
+        output.write_all(&blocks::encode(bytes, config.format, threads))?;
+
+        if config.checksum {
+            output.write_all(&Trailer::of(bytes).to_bytes())?;
+        }
+
+        output.flush()?;
+
+        return Ok(None);
+    }
+
+    let (encoding, stats) = match config.format {
+        Format::Byte if config.stats => {
+            let (encoding, stats) = encode_with_stats(bytes);
+            (encoding, Some(stats))
+        }
+        Format::Byte => (encode_with_opts(bytes, &config.encode_options), None),
+        format => (format.encode(bytes), None),
+    };
+
+    if !config.header {
+        output.write_all(&encoding)?;
+    } else {
+        let (format, data) = if encoding.len() > bytes.len() {
+            report_stored(path, config);
+            (Format::Stored, bytes)
+        } else {
+            (config.format, &encoding[..])
+        };
+
+        config.file_header(format).write_to(&mut output)?;
+        output.write_all(data)?;
+
+        if config.checksum {
+            output.write_all(&Trailer::of(bytes).to_bytes())?;
+        }
+    }
+
+    output.flush()?;
+
+    Ok(stats)
+}
+
+/// Encode or decode everything read from `input` and write the result to
+/// `output`, along with the header and trailer unless they're disabled.
+///
+/// * `path` - The path to the input file, or `-` for stdin.
+/// * `input` - Where the bytes to be processed come from.
+/// * `output` - Where the result goes.
+/// * `encode` - Whether to encode (true) or decode (false).
+/// * `config` - How the program is being run.
+///
+/// Return the statistics of the encoding, if they were asked for.
+fn convert_format(
+    path: &str, input: impl Read, mut output: impl Write, encode: bool, config: &Config
+) -> Result<Option<Stats>, RleError> {
+    if !config.header {
+        return convert_data(input, output, encode, config.format, config);
+    }
+
+    if let (true, Some(threads)) = (encode, config.threads) {
+        Header { blocks: true, ..config.file_header(config.format) }.write_to(&mut output)?;
+        let mut input = Checksummed::new(input);
+        // Enough blocks for every thread to encode one.
+        let batch_len = threads * blocks::BLOCK_SIZE;
+        let mut batch = Vec::with_capacity(batch_len);
+
+        loop {
+            batch.clear();
+            input.by_ref().take(batch_len as u64).read_to_end(&mut batch)?;
+
+            if batch.is_empty() {
+                break;
+            }
+
+            output.write_all(&blocks::encode(&batch, config.format, threads))?;
+        }
+
+        if config.checksum {
+            output.write_all(&input.trailer().to_bytes())?;
+        }
+
+        output.flush()?;
+
+        return Ok(None);
+    }
+
+    if encode {
+        // Data that encoding wouldn't shrink is stored as it is instead,
+        // which can only be known once all of it is encoded, so both the
+        // data and its encoding are set aside until then.
+        let mut input = Checksummed::new(Tee::new(input));
+        let mut encoding = Spill::new();
+        let stats = convert_data(&mut input, &mut encoding, true, config.format, config)?;
+        let trailer = input.trailer();
+        let mut copy = input.inner.copy;
+
+        let (format, data) = if encoding.len() > trailer.len {
+            report_stored(path, config);
+            (Format::Stored, &mut copy)
+        } else {
+            (config.format, &mut encoding)
+        };
+
+        config.file_header(format).write_to(&mut output)?;
+        data.write_to(&mut output)?;
+
+        if config.checksum {
+            output.write_all(&trailer.to_bytes())?;
+        }
+
+        output.flush()?;
+
+        return Ok(stats);
+    }
+
+    // Decoding uses the scheme named by the header of each file the input
+    // holds, whatever was asked for.
+    let mut input = Members::new(input);
+    let mut header = input.next_header()?;
+
+    loop {
+        report_header(path, &header, config);
+
+        if !header.checksum {
+            if config.checksum {
+                report::warn(config, &format!("{}: the file has no checksum, so it can't be verified", path));
+            }
+
+            return decode_data(input, output, header, config).map(|_| None);
+        }
+
+        // The schemes decoded as they're read are read up to the end of each
+        // file; the others are decoded in memory, a file at a time.
+        let streamed = match header.format {
+            _ if header.blocks => stream_member(&mut input, &mut output, header, config),
+            Format::Byte | Format::Stored | Format::Varint => stream_member(&mut input, &mut output, header, config),
+            // Without the checksums to tell where each file ends, the rest of
+            // the input is taken as a single file.
+            _ if !config.checksum => {
+                let mut bytes = Vec::new();
+                input.read_to_end(&mut bytes)?;
+                let (data, trailer) = Trailer::split_from(&bytes)?;
+                let decoding = header.decode(data)?;
+                trailer.check_len(decoding.len() as u64)?;
+                output.write_all(&decoding)?;
+                output.flush()?;
+
+                return Ok(None);
+            }
+            _ => {
+                output.write_all(&input.decode_member(header)?)?;
+                output.flush().map_err(RleError::from)
+            }
+        };
+
+        match streamed {
+            Err(RleError::Decode(DecodeError::TrailingData { offset })) if config.lenient => {
+                let len = input.offset() + input.skip_rest()? - offset;
+                report_ignored(path, len, offset, config);
+
+                return Ok(None);
+            }
+            streamed => streamed?,
+        }
+
+        if input.at_end()? {
+            return Ok(None);
+        }
+
+        header = match input.next_header() {
+            Ok(header) => header,
+            Err(RleError::Decode(DecodeError::TrailingData { offset })) if config.lenient => {
+                report_ignored(path, input.skip_rest()?, offset, config);
+
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+    }
+}
+
+/// Report, as a warning, the data after the last file in an input that
+/// isn't an encoded file, ignored with `--lenient`.
+///
+/// * `path` - The path to the input file, or `-` for stdin.
+/// * `len` - The length of the data.
+/// * `offset` - The offset of the data in the input.
+/// * `config` - How the program is being run.
+fn report_ignored(path: &str, len: u64, offset: u64, config: &Config) {
+    report::warn(config, &format!("{}: ignored {} bytes at offset {} that aren't an encoded file", path, len, offset));
+}
+
+/// Decode the data of the file `input` has reached, which follows `header`,
+/// as it's read, up to the trailer that checks out, and write the result to
+/// `output`.
+///
+/// A trailer that checks out but is followed by neither another file nor
+/// the end of the input may be part of the data. The decoding of what
+/// follows it is held back until a later trailer checks out, and if none
+/// does, what follows it is taken to be other data.
+///
+/// * `input` - Where the encoded files come from.
+/// * `output` - Where the result goes.
+/// * `header` - The header of the file, which has a trailer.
+/// * `config` - How the program is being run.
+fn stream_member(
+    input: &mut Members<impl Read>, output: impl Write, header: Header, config: &Config
+) -> Result<(), RleError> {
+    input.split(header);
+    let mut output = Checksummed::new(Held::new(output));
+    // The offset of what follows the last trailer that checked out, if
+    // reading went on after it.
+    let mut after = None;
+
+    let e = loop {
+        if let Err(e) = decode_data(&mut *input, &mut output, header, config) {
+            break e;
+        }
+
+        let actual = output.trailer();
+        let checked = input.trailer().and_then(|trailer| {
+            if config.checksum { trailer.check(actual) } else { trailer.check_len(actual.len) }
+        });
+
+        match checked {
+            Ok(()) if input.ends_member()? => {
+                output.inner.release()?;
+                output.flush()?;
+
+                return Ok(());
+            }
+            Ok(()) => {
+                output.inner.release()?;
+                output.inner.hold();
+                after = Some(input.offset() + Trailer::LEN as u64);
+                input.resume()?;
+            }
+            // What looked like the end of the file is part of its data,
+            // unless the input ends there.
+            Err(e) => {
+                if !input.resume()? {
+                    break e.into();
+                }
+            }
+        }
+    };
+
+    // What followed the last trailer that checked out is other data after
+    // all.
+    match (after, e) {
+        (Some(offset), RleError::Decode(_)) => Err(DecodeError::TrailingData { offset }.into()),
+        (_, e) => Err(e),
+    }
+}
+
+/// Report the scheme named by the header of a file being decoded, with
+/// `-vv`.
+///
+/// * `path` - The path to the file, or `-` for stdin.
+/// * `header` - The header read from the start of the file.
+/// * `config` - How the program is being run.
+fn report_header(path: &str, header: &Header, config: &Config) {
+    report::debug(
+        config,
+        &format!(
+            "{}: decoding with the {} scheme named by the header{}",
+            path,
+            scheme_name(header.format),
+            if header.blocks { ", in blocks" } else { "" }
+        ),
+    );
+}
+
+/// Report that a file is stored as it is, because encoding it wouldn't
+/// shrink it, with `-vv`.
+///
+/// * `path` - The path to the file, or `-` for stdin.
+/// * `config` - How the program is being run.
+fn report_stored(path: &str, config: &Config) {
+    report::debug(config, &format!("{}: the encoding is larger than the file, so it's stored as it is", path));
+}
+
+/// Return the name of a scheme as printed with `-vv`, e.g. `packbits`.
+///
+/// * `format` - The scheme.
+fn scheme_name(format: Format) -> String {
+    format!("{:?}", format).to_lowercase()
+}
+
+/// Decode the data read from `input`, which follows `header`, and write the
+/// result to `output`.
+///
+/// * `input` - Where the encoded data comes from, without the trailer.
+/// * `output` - Where the result goes.
+/// * `header` - The header read from the start of the file.
+/// * `config` - How the program is being run.
+fn decode_data(
+    input: impl Read, output: impl Write, header: Header, config: &Config
+) -> Result<(), RleError> {
+    if header.blocks {
+        let threads = config.threads.unwrap_or(1);
+        blocks::decode_stream_with_threads(input, output, header.format, threads)?;
+    } else {
+        convert_data(input, output, false, header.format, config)?;
+    }
+
+    Ok(())
+}
+
+/// Encode or decode the data read from `input` with `format` and write the
+/// result to `output`.
+///
+/// * `input` - Where the bytes to be processed come from.
+/// * `output` - Where the result goes.
+/// * `encode` - Whether to encode (true) or decode (false).
+/// * `format` - The scheme to encode or decode with.
+/// * `config` - How the program is being run.
+///
+/// Return the statistics of the encoding, if they were asked for.
+fn convert_data(
+    mut input: impl Read, mut output: impl Write, encode: bool, format: Format, config: &Config
+) -> Result<Option<Stats>, RleError> {
+    if let Some(chunk_size) = config.parallel {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+
+        return convert_parallel(&bytes, output, encode, chunk_size).map(|_| None);
+    }
+
+    if format == Format::Byte && !(encode && config.stats) {
+        if encode {
+            encode_stream_with_opts(input, output, &config.encode_options)?;
+        } else {
+            decode_stream(input, output)?;
+        }
+
+        return Ok(None);
+    }
+
+    if format == Format::Stored {
+        io::copy(&mut input, &mut output)?;
+        output.flush()?;
+
+        return Ok(None);
+    }
+
+    // The other formats, and gathering statistics, have no streaming
+    // implementation, so the whole input is read first.
+    let mut bytes = Vec::new();
+    input.read_to_end(&mut bytes)?;
+
+    if format == Format::Varint && !encode {
+        // A single run can be longer than memory, so it's written out as it's
+        // decoded.
+        varint::decode_to_writer(&bytes, &mut output)?;
+        output.flush()?;
+
+        return Ok(None);
+    }
+
+    if encode && config.stats {
+        let (result, stats) = encode_with_stats(&bytes);
+        output.write_all(&result)?;
+        output.flush()?;
+
+        return Ok(Some(stats));
+    }
+
+    let result = if encode {
+        format.encode(&bytes)
+    } else {
+        format.decode(&bytes)?
+    };
+
+    output.write_all(&result)?;
+    output.flush()?;
+
+    Ok(None)
+}
+
+/// Encode `bytes` in chunks of `chunk_size` bytes on every CPU, or decode
+/// such chunks, and write the result to `output`.
+///
+/// * `bytes` - The bytes to be processed.
+/// * `output` - Where the result goes.
+/// * `encode` - Whether to encode (true) or decode (false).
+/// * `chunk_size` - The length of the chunks to encode.
+fn convert_parallel(
+    bytes: &[u8], mut output: impl Write, encode: bool, chunk_size: usize
+) -> Result<(), RleError> {
+    let result = if encode {
+        encode_parallel(bytes, chunk_size)
+    } else {
+        decode_parallel(bytes)?
+    };
+
+    output.write_all(&result)?;
+    output.flush()?;
+
+    Ok(())
+}
+
+/// A reader or writer that checksums the data going through it.
+///
+/// * `inner` - The reader or writer the data goes through.
+/// * `crc` - The checksum of the data so far.
+/// * `len` - The length of the data so far.
+struct Checksummed<T> {
+    inner: T,
+    crc: Crc32,
+    len: u64,
+}
+
+impl<T> Checksummed<T> {
+    fn new(inner: T) -> Self {
+        Self { inner, crc: Crc32::new(), len: 0 }
+    }
+
+    /// Return the trailer describing the data so far.
+    fn trailer(&self) -> Trailer {
+        Trailer { crc: self.crc.value(), len: self.len }
+    }
+}
+
+/// A writer that can hold back what is written to it until it's released.
+///
+/// * `inner` - The writer the output goes to.
+/// * `held` - The bytes held back, or `None` if they aren't.
+struct Held<W> {
+    inner: W,
+    held: Option<Spill>,
+}
+
+impl<W: Write> Held<W> {
+    fn new(inner: W) -> Self {
+        Self { inner, held: None }
+    }
+
+    /// Hold back what is written from now on, unless it already is.
+    fn hold(&mut self) {
+        self.held.get_or_insert_with(Spill::new);
+    }
+
+    /// Write what was held back, and stop holding it back.
+    fn release(&mut self) -> io::Result<()> {
+        match self.held.take() {
+            Some(mut held) => held.write_to(&mut self.inner),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write> Write for Held<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.held {
+            Some(held) => held.write(buf),
+            None => self.inner.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Bytes set aside to be written later, in memory up to [`CHUNK_SIZE`] of
+/// them, and past that in a temporary file. On Unix the file is removed as
+/// soon as it's created, and elsewhere when this is dropped.
+///
+/// * `held` - The bytes kept in memory, until there's a file.
+/// * `file` - The temporary file and its path, once there's one.
+/// * `len` - How many bytes were set aside.
+struct Spill {
+    held: Vec<u8>,
+    file: Option<(File, PathBuf)>,
+    len: u64,
+}
+
+impl Spill {
+    fn new() -> Self {
+        Self { held: Vec::new(), file: None, len: 0 }
+    }
+
+    /// Return how many bytes were set aside.
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Write the bytes set aside to `output`.
+    ///
+    /// * `output` - Where they go.
+    fn write_to(&mut self, mut output: impl Write) -> io::Result<()> {
+        match &mut self.file {
+            Some((file, _)) => {
+                file.seek(SeekFrom::Start(0))?;
+                io::copy(file, &mut output)?;
+            }
+            None => output.write_all(&self.held)?,
+        }
+
+        Ok(())
+    }
+
+    /// Create the temporary file, in the directory for temporary files, and
+    /// move the bytes kept in memory to it.
+    fn spill(&mut self) -> io::Result<()> {
+        for n in 0.. {
+            let path = env::temp_dir().join(format!(".rle-rs-spill.tmp{}-{}", process::id(), n));
+
+            match OpenOptions::new().read(true).write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    #[cfg(unix)]
+                    fs::remove_file(&path)?;
+
+                    file.write_all(&self.held)?;
+                    self.held = Vec::new();
+                    self.file = Some((file, path));
+
+                    return Ok(());
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        unreachable!("every temporary file name is taken")
+    }
+}
+
+impl Write for Spill {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.file.is_none() && self.held.len() + buf.len() > CHUNK_SIZE {
+            self.spill()?;
+        }
+
+        let n = match &mut self.file {
+            Some((file, _)) => file.write(buf)?,
+            None => {
+                self.held.extend_from_slice(buf);
+                buf.len()
+            }
+        };
+        self.len += n as u64;
+
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        // On Unix the file was removed already.
+        #[cfg(not(unix))]
+        if let Some((_, path)) = self.file.take() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// A reader that sets aside a copy of everything read through it.
+///
+/// * `inner` - The reader the bytes come from.
+/// * `copy` - The bytes read so far.
+struct Tee<R> {
+    inner: R,
+    copy: Spill,
+}
+
+impl<R> Tee<R> {
+    fn new(inner: R) -> Self {
+        Self { inner, copy: Spill::new() }
+    }
+}
+
+impl<R: Read> Read for Tee<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.copy.write_all(&buf[..n])?;
+
+        Ok(n)
+    }
+}
+
+impl<R: Read> Read for Checksummed<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.crc.update(&buf[..n]);
+        self.len += n as u64;
+
+        Ok(n)
+    }
+}
+
+impl<W: Write> Write for Checksummed<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.crc.update(&buf[..n]);
+        self.len += n as u64;
+
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A reader or writer that counts the bytes going through it.
+///
+/// * `inner` - The reader or writer the bytes go through.
+/// * `len` - How many bytes went through so far.
+struct Counted<T> {
+    inner: T,
+    len: u64,
+}
+
+impl<T> Counted<T> {
+    fn new(inner: T) -> Self {
+        Self { inner, len: 0 }
+    }
+}
+
+impl<R: Read> Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.len += n as u64;
+
+        Ok(n)
+    }
+}
+
+impl<W: Write> Write for Counted<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.len += n as u64;
+
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A reader that shows how much of its input was read on a line of stderr,
+/// which is cleared once it's dropped.
+///
+/// * `inner` - The reader the input comes from.
+/// * `path` - The input file, as given.
+/// * `total` - The size of the input, if known.
+/// * `read` - How many bytes were read so far.
+/// * `start` - When reading started.
+/// * `shown` - When the line was last updated.
+struct Progress<R> {
+    inner: R,
+    path: String,
+    total: Option<u64>,
+    read: u64,
+    start: Instant,
+    shown: Instant,
+}
+
+impl<R> Progress<R> {
+    fn new(inner: R, path: &str, total: Option<u64>) -> Self {
+        let now = Instant::now();
+
+        Self { inner, path: path.to_owned(), total, read: 0, start: now, shown: now }
+    }
+}
+
+impl<R: Read> Read for Progress<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n as u64;
+
+        let now = Instant::now();
+
+        if now.duration_since(self.shown) >= PROGRESS_INTERVAL {
+            self.shown = now;
+            let line = progress_line(self.read, self.total, now.duration_since(self.start));
+            report::progress(&format!("{}: {}", self.path, line));
+        }
+
+        Ok(n)
+    }
+}
+
+impl<R> Drop for Progress<R> {
+    fn drop(&mut self) {
+        report::clear_progress();
+    }
+}
+
+/// Return the progress of reading a file, as shown by [`Progress`].
+///
+/// * `read` - How many bytes were read so far.
+/// * `total` - The size of the file, if known.
+/// * `elapsed` - How long reading took so far.
+fn progress_line(read: u64, total: Option<u64>, elapsed: Duration) -> String {
+    let rate = read as f64 / elapsed.as_secs_f64().max(1e-3);
+    let mut line = format!("{:.1} MB", read as f64 / 1e6);
+
+    if let Some(total) = total.filter(|&total| total > 0) {
+        line += &format!(" of {:.1} MB ({:.0}%)", total as f64 / 1e6, read as f64 / total as f64 * 100.0);
+    }
+
+    line += &format!(", {:.1} MB/s", rate / 1e6);
+
+    if let Some(total) = total.filter(|_| rate > 0.0) {
+        let left = (total.saturating_sub(read) as f64 / rate).round() as u64;
+        line += &format!(", {}:{:02}:{:02} left", left / 3600, left / 60 % 60, left % 60);
+    }
+
+    line
+}
+
+/// A reader of the data of the encoded files in an input that may hold
+/// several one after another, as written by `cat a.rle b.rle`, holding back
+/// the trailer at the end of each.
+///
+/// Nothing records where the data of a file ends, so its runs are walked
+/// through with [`Header::run_at`], and reading stops after the first a
+/// trailer recording the length of their decoding may follow, as if the
+/// input ended there. If the trailer doesn't check out, it's part of the
+/// data, and reading goes on with [`Members::resume`].
+///
+/// * `inner` - The reader the input comes from.
+/// * `ahead` - The bytes read from `inner`, of which those from `start` on
+///   weren't returned yet.
+/// * `start` - The offset in `ahead` of the next byte to return.
+/// * `drained` - How many bytes read from `inner` were dropped from the
+///   start of `ahead`.
+/// * `ended` - Whether `inner` has ended.
+/// * `header` - The header of the current file, once its data is split from
+///   its trailer, or `None` if it runs to the end of the input.
+/// * `walked` - The offset from `start` of the end of the runs walked
+///   through.
+/// * `len` - The length of the decoding of the data of the current file, up
+///   to the end of the runs walked through.
+/// * `looked` - Whether the bytes after the runs walked through were looked
+///   at for a trailer.
+/// * `stopped` - Whether reading stopped at a trailer recording the length
+///   of the data before it.
+struct Members<R> {
+    inner: R,
+    ahead: Vec<u8>,
+    start: usize,
+    drained: u64,
+    ended: bool,
+    header: Option<Header>,
+    walked: usize,
+    len: u64,
+    looked: bool,
+    stopped: bool,
+}
+
+impl<R: Read> Members<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            ahead: Vec::new(),
+            start: 0,
+            drained: 0,
+            ended: false,
+            header: None,
+            walked: 0,
+            len: 0,
+            looked: false,
+            stopped: false,
+        }
+    }
+
+    /// Return the bytes read from the input but not returned yet.
+    fn pending(&self) -> &[u8] {
+        &self.ahead[self.start..]
+    }
+
+    /// Return the offset in the input of the first pending byte.
+    fn offset(&self) -> u64 {
+        self.drained + self.start as u64
+    }
+
+    /// Read more of the input, unless it has ended.
+    fn fill(&mut self) -> io::Result<()> {
+        self.ahead.drain(..self.start);
+        self.drained += self.start as u64;
+        self.start = 0;
+
+        let len = self.ahead.len();
+        self.ahead.resize(len + CHUNK_SIZE, 0);
+
+        let n = loop {
+            match self.inner.read(&mut self.ahead[len..]) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.ahead.truncate(len);
+                    return Err(e);
+                }
+            }
+        };
+
+        self.ahead.truncate(len + n);
+        self.ended = n == 0;
+
+        Ok(())
+    }
+
+    /// Return whether the input has been read to the end, but for the
+    /// trailer of the current file.
+    fn at_end(&mut self) -> io::Result<bool> {
+        let held = if self.header.is_some() { Trailer::LEN } else { 0 };
+
+        while self.pending().len() <= held && !self.ended {
+            self.fill()?;
+        }
+
+        Ok(self.pending().len() <= held)
+    }
+
+    /// Read the header of the next file, after the trailer of the last one,
+    /// if any. Its data runs to the end of the input until [`Members::split`]
+    /// is called.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DecodeError::TrailingData`] if the input goes on after the
+    /// last file with data that isn't an encoded file.
+    fn next_header(&mut self) -> Result<Header, RleError> {
+        if self.header.is_some() {
+            self.start += self.pending().len().min(Trailer::LEN);
+        }
+
+        while self.pending().len() < Header::MAX_LEN && !self.ended {
+            self.fill()?;
+        }
+
+        let header = match Header::parse(self.pending()) {
+            Ok((header, _)) => header,
+            Err(DecodeError::InvalidMagic) if self.offset() > 0 => {
+                return Err(DecodeError::TrailingData { offset: self.offset() }.into())
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        self.start += header.size();
+        self.header = None;
+        self.walked = 0;
+        self.len = 0;
+        self.looked = false;
+        self.stopped = false;
+
+        Ok(header)
+    }
+
+    /// Look for the end of the data of the current file, of one of the
+    /// schemes whose runs are told apart from fewer bytes than a trailer.
+    ///
+    /// * `header` - The header of the file, which has a trailer.
+    fn split(&mut self, header: Header) {
+        self.header = Some(header);
+    }
+
+    /// Decode the data of the current file, as [`rle_rs::decode_member`]
+    /// does, reading only as much of the input as it takes, and return its
+    /// decoding. Only its trailer is left to read.
+    ///
+    /// * `header` - The header of the file, which has a trailer.
+    fn decode_member(&mut self, header: Header) -> Result<Vec<u8>, RleError> {
+        let mut wanted = CHUNK_SIZE;
+
+        loop {
+            while self.pending().len() < wanted && !self.ended {
+                self.fill()?;
+            }
+
+            match rle_rs::decode_member(header, self.pending()) {
+                // A trailer followed by other data may not be the last that
+                // checks out.
+                Ok((decoding, len)) if self.ended || self.ends_after(len) => {
+                    self.start += len;
+                    self.header = Some(header);
+
+                    return Ok(decoding);
+                }
+                Err(e) if self.ended => return Err(e.into()),
+                // The end of the file may not have been read yet.
+                _ => wanted = 2 * self.pending().len(),
+            }
+        }
+    }
+
+    /// Return whether another file follows `len` pending bytes of data of the
+    /// current file and its trailer.
+    ///
+    /// * `len` - The length of the data.
+    fn ends_after(&self, len: usize) -> bool {
+        match self.pending().get(len + Trailer::LEN..) {
+            Some(next) if !next.is_empty() => starts_member(next),
+            _ => false,
+        }
+    }
+
+    /// Skip the rest of the input and return how long it was.
+    fn skip_rest(&mut self) -> io::Result<u64> {
+        let pending = self.pending().len() as u64;
+        self.start = self.ahead.len();
+
+        Ok(pending + io::copy(&mut self.inner, &mut io::sink())?)
+    }
+
+    /// Return the trailer after the data read.
+    fn trailer(&self) -> Result<Trailer, DecodeError> {
+        let pending = self.pending();
+        let len = if self.stopped { Trailer::LEN } else { pending.len() };
+
+        Trailer::split_from(&pending[..len]).map(|(_, trailer)| trailer)
+    }
+
+    /// Return whether the trailer after the data read is followed by another
+    /// file or the end of the input, so that it ends the current file.
+    fn ends_member(&mut self) -> io::Result<bool> {
+        while self.stopped && self.pending().len() < Trailer::LEN + Header::MAX_LEN && !self.ended {
+            self.fill()?;
+        }
+
+        Ok(starts_member(self.pending().get(Trailer::LEN..).unwrap_or_default()))
+    }
+
+    /// Carry on reading the data of the current file after stopping at what
+    /// turned out not to be its end, and return whether it goes on: not if
+    /// reading stopped at the end of the input, or didn't stop.
+    fn resume(&mut self) -> io::Result<bool> {
+        while self.stopped && self.pending().len() <= Trailer::LEN && !self.ended {
+            self.fill()?;
+        }
+
+        if !self.stopped || self.pending().len() <= Trailer::LEN {
+            return Ok(false);
+        }
+
+        self.stopped = false;
+        self.looked = true;
+
+        Ok(true)
+    }
+
+    /// Return how many of the pending bytes can be returned as data, up to
+    /// the first trailer that may end it.
+    ///
+    /// * `header` - The header of the current file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::ErrorKind::InvalidData`] error wrapping the
+    /// [`DecodeError`] if a run is malformed, the input ends before a
+    /// trailer, or the last trailer records a different length, which is
+    /// found without decoding the runs before it.
+    fn ready(&mut self, header: Header) -> io::Result<usize> {
+        let offset = self.offset();
+        let pending = &self.ahead[self.start..];
+        let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+        let truncated = || match pending.len() {
+            0 => DecodeError::MissingTrailer,
+            _ => DecodeError::TruncatedInput { at_byte: offset as usize },
+        };
+
+        loop {
+            // The rest of the run being walked through is data, whatever
+            // follows it.
+            let rest = match pending.get(self.walked..) {
+                Some(rest) => rest,
+                None if self.ended => return Err(invalid(truncated())),
+                None => return Ok(pending.len()),
+            };
+
+            if !self.looked {
+                match rest.get(..Trailer::LEN) {
+                    Some(trailer) => {
+                        let trailer = Trailer::from_bytes(trailer.try_into().unwrap());
+
+                        if trailer.len == self.len {
+                            self.stopped = self.walked == 0;
+
+                            return Ok(self.walked);
+                        }
+
+                        // A trailer at the end of the input is the file's,
+                        // however long the runs it doesn't match.
+                        if rest.len() == Trailer::LEN && self.ended {
+                            let e = DecodeError::LengthMismatch { expected: trailer.len, actual: self.len };
+
+                            return Err(invalid(e));
+                        }
+
+                        if rest.len() == Trailer::LEN {
+                            return Ok(self.walked);
+                        }
+
+                        self.looked = true;
+                    }
+                    None if self.ended && self.walked == 0 => return Err(invalid(truncated())),
+                    None => return Ok(self.walked),
+                }
+            }
+
+            match header.run_at(rest) {
+                Some((run, len)) => {
+                    self.walked += run;
+                    self.len = self.len.saturating_add(len);
+                    self.looked = false;
+                }
+                None if rest.len() < Trailer::LEN && !self.ended => return Ok(self.walked),
+                None if self.walked > 0 => return Ok(self.walked),
+                // The runs of the schemes read this way are told apart from
+                // fewer bytes than a trailer, so decoding that many finds
+                // what is wrong with the run.
+                None => {
+                    let at = |at_byte: usize| at_byte + offset as usize;
+                    let e = match header.decode(&rest[..rest.len().min(Trailer::LEN)]) {
+                        Err(DecodeError::ZeroCount { at_byte }) => DecodeError::ZeroCount { at_byte: at(at_byte) },
+                        Err(DecodeError::CountOverflow { at_byte }) => DecodeError::CountOverflow { at_byte: at(at_byte) },
+                        Err(e) => e,
+                        Ok(_) => truncated(),
+                    };
+
+                    return Err(invalid(e));
+                }
+            }
+        }
+    }
+}
+
+/// Return whether `bytes`, what follows a trailer, is the end of the input or
+/// another file.
+///
+/// * `bytes` - The bytes after the trailer.
+fn starts_member(bytes: &[u8]) -> bool {
+    bytes.is_empty() || !matches!(Header::parse(bytes), Err(DecodeError::InvalidMagic))
+}
+
+impl<R: Read> Read for Members<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let ready = match self.header {
+                Some(header) => self.ready(header)?,
+                None => self.pending().len(),
+            };
+
+            if ready > 0 || buf.is_empty() || self.stopped {
+                let n = ready.min(buf.len());
+                buf[..n].copy_from_slice(&self.pending()[..n]);
+                self.start += n;
+                self.walked = self.walked.saturating_sub(n);
+
+                return Ok(n);
+            }
+
+            if self.ended {
+                return Ok(0);
+            }
+
+            // Data that runs to the end of the input isn't held back.
+            if self.header.is_none() {
+                return self.inner.read(buf);
+            }
+
+            self.fill()?;
+        }
+    }
+}
+
+/// Return the path the result of encoding or decoding a file is written to.
+///
+/// * `path` - The path to the input file.
+/// * `encode` - Whether the file is being encoded (true) or decoded (false).
+/// * `config` - How the program is being run.
+fn output_path(path: &str, encode: bool, config: &Config) -> String {
+    let suffix = config.suffix.as_deref().unwrap_or("rle");
+
+    if encode {
+        return format!("{}.{}", path, suffix);
+    }
+
+    // Decoding strips the suffix, unless the file it would restore already
+    // exists (and isn't meant to be overwritten) or there is no suffix.
+    // e.g.: encoding a .txt produces a .txt.rle, which then becomes a .txt
+    // again on decoding, or a .txt.rle.dat if the .txt is still there.
+    if let Some(stem) = strip_suffix(path, suffix) {
+        if config.force || !Path::new(stem).exists() {
+            return stem.to_owned();
+        }
+
+        report::warn(config, &format!("{}: '{}' already exists, writing '{}.dat' instead", path, stem, path));
+    }
+
+    format!("{}.dat", path)
+}
+
+/// Return `path` without its `.<suffix>` extension, or `None` if it doesn't
+/// have it or nothing but the extension would be left of its file name.
+///
+/// * `path` - The path to strip the extension from.
+/// * `suffix` - The extension, without the leading dot.
+fn strip_suffix<'a>(path: &'a str, suffix: &str) -> Option<&'a str> {
+    let stem = path.strip_suffix(suffix)?.strip_suffix('.')?;
+
+    if stem.is_empty() || stem.ends_with(std::path::is_separator) {
+        return None;
+    }
+
+    Some(stem)
+}
+
+/// The file the result is written to, by way of a temporary file next to it
+/// that only takes its name once the whole result is on disk, so that a
+/// failure or an interruption never leaves a truncated output behind. The
+/// temporary file is removed when this is dropped.
+///
+/// * `file` - The file being written.
+/// * `path` - The path the result goes to.
+/// * `temp` - The path of the temporary file, or `None` if the result is
+///   written to `path` directly, as for devices such as `/dev/null`.
+struct Output {
+    file: File,
+    path: PathBuf,
+    temp: Option<PathBuf>,
+}
+
+impl Output {
+    /// Create the temporary file for a result that goes to `path`, in the
+    /// same directory, named after it, e.g. `.a.txt.rle.tmp1234-0`.
+    ///
+    /// * `path` - The path the result goes to.
+    /// * `force` - Whether the result may replace an existing file.
+    fn create(path: &Path, force: bool) -> Result<Self, RleError> {
+        match fs::metadata(path) {
+            // Checking now, as well as when the result is moved into place,
+            // saves doing all the work for nothing.
+            Ok(_) if !force => return Err(already_exists(path)),
+            // Devices and pipes can't be replaced, only written to.
+            Ok(metadata) if !metadata.is_file() => {
+                let file = OpenOptions::new().write(true).open(path).map_err(RleError::at(path))?;
+
+                return Ok(Self { file, path: path.to_owned(), temp: None });
+            }
+            _ => {}
+        }
+
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+
+        for n in 0.. {
+            let temp = dir.join(format!(".{}.tmp{}-{}", name, process::id(), n));
+
+            match OpenOptions::new().write(true).create_new(true).open(&temp) {
+                Ok(file) => {
+                    interrupt::remove_on_interrupt(Some(&temp));
+
+                    return Ok(Self { file, path: path.to_owned(), temp: Some(temp) });
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(RleError::at(temp)(e)),
+            }
+        }
+
+        unreachable!("every temporary file name is taken")
+    }
+
+    /// Create the temporary file for a result that is appended to the file
+    /// at `path`, starting it with a copy of that file, if any, so that the
+    /// file is only replaced once the result is complete.
+    ///
+    /// * `path` - The path the result is appended to.
+    fn append(path: &Path) -> Result<Self, RleError> {
+        let mut output = Self::create(path, true)?;
+
+        // Devices are written to as they are.
+        if output.temp.is_none() {
+            return Ok(output);
+        }
+
+        match File::open(path) {
+            Ok(mut file) => {
+                io::copy(&mut file, &mut output.file).map_err(RleError::at(path))?;
+
+                #[cfg(unix)]
+                {
+                    let permissions = file.metadata().map_err(RleError::at(path))?.permissions();
+                    output.file.set_permissions(permissions).map_err(RleError::at(path))?;
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(RleError::at(path)(e)),
+        }
+
+        Ok(output)
+    }
+
+    /// Return the path of the file being written.
+    fn written_path(&self) -> &Path {
+        self.temp.as_deref().unwrap_or(&self.path)
+    }
+
+    /// Give the file the permissions and modification time of the input,
+    /// unless it's written in place, as a device that isn't the program's to
+    /// change.
+    ///
+    /// * `source` - The metadata of the input.
+    fn copy_attributes(&self, source: &fs::Metadata) -> io::Result<()> {
+        if self.temp.is_none() {
+            return Ok(());
+        }
+
+        self.file.set_modified(source.modified()?)?;
+
+        // On Windows the permissions are only the read-only attribute, which
+        // would keep the file from being removed if the program fails after.
+        #[cfg(unix)]
+        self.file.set_permissions(source.permissions())?;
+
+        Ok(())
+    }
+
+    /// Make sure the result is on disk, and give it its name.
+    ///
+    /// * `force` - Whether the result may replace an existing file.
+    fn persist(&mut self, force: bool) -> Result<(), RleError> {
+        // Devices and pipes have nothing to sync, or move.
+        let temp = match &self.temp {
+            Some(temp) => temp,
+            None => return Ok(()),
+        };
+
+        self.file.sync_all().map_err(RleError::at(temp))?;
+
+        if force {
+            fs::rename(temp, &self.path).map_err(RleError::at(&self.path))?;
+            interrupt::remove_on_interrupt(None);
+            self.temp = None;
+
+            return Ok(());
+        }
+
+        // A link only takes the name if nothing has it, in a single step, so
+        // no other process can create the output in between; the temporary
+        // name is then removed on drop.
+        match fs::hard_link(temp, &self.path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(already_exists(&self.path)),
+            // Some file systems have no links, and only the check is left.
+            Err(_) if self.path.exists() => Err(already_exists(&self.path)),
+            Err(_) => {
+                fs::rename(temp, &self.path).map_err(RleError::at(&self.path))?;
+                interrupt::remove_on_interrupt(None);
+                self.temp = None;
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        if let Some(temp) = self.temp.take() {
+            interrupt::remove_on_interrupt(None);
+            let _ = fs::remove_file(temp);
+        }
+    }
+}
+
+/// Return the error reporting that the output already exists.
+///
+/// * `path` - The path to the output.
+fn already_exists(path: &Path) -> RleError {
+    RleError::at(path)(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        "the output already exists (use --force to overwrite)",
+    ))
+}
+
+/// Return whether two paths refer to the same existing file.
+///
+/// * `a` - The first path.
+/// * `b` - The second path.
+fn is_same_file(a: &str, b: &str) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Hold configuration information needed for the program to run.
+///
+/// * `command` - What is done with the filepaths.
+/// * `mode` - Whether files are encoded, decoded, or either depending on
+///   their contents.
+/// * `stdout` - Whether the result is written to stdout instead of a file.
+/// * `output` - Where to write the result, instead of a path derived from the
+///   input's.
+/// * `output_dir` - The directory to write the result into, instead of the
+///   input's.
+/// * `force` - Whether existing output files may be overwritten.
+/// * `append` - Whether encodings are appended to their output files instead
+///   of replacing them.
+/// * `remove` - Whether input files are removed once processed.
+/// * `recursive` - Whether directories are walked for files to process.
+/// * `suffix` - The extension of encoded files, without the leading dot, if
+///   not the default `rle`.
+/// * `format` - The encoding scheme to use, unless decoding a file with a
+///   header.
+/// * `checksum` - Whether encodings end with a trailer, and the checksums in
+///   the trailers of files decoded are checked.
+/// * `header` - Whether encoded files start with a header.
+/// * `encode_options` - How to tune the default scheme's encoder.
+/// * `stats` - Whether statistics are printed after encoding.
+/// * `analyze` - Whether files are only analyzed, with their statistics
+///   printed instead of encoding them.
+/// * `json` - Whether the outcome of each file is printed as JSON.
+/// * `progress` - Whether the progress of reading each file is shown,
+///   whatever its size.
+/// * `verbosity` - How much is printed to stderr besides errors.
+/// * `lenient` - Whether data after the encoded files in a file is ignored.
+/// * `test` - Whether files are only checked, by decoding them without
+///   writing the result.
+/// * `dry_run` - Whether results are only measured instead of written.
+/// * `verify` - Whether encodings are decoded again and checked against the
+///   files they came from.
+/// * `threads` - How many threads to encode on, if files are split into
+///   blocks.
+/// * `parallel` - The length of the chunks files are split into, if they're
+///   encoded in parallel with [`encode_parallel`].
+/// * `mmap` - Whether input files are mapped into memory instead of read.
+/// * `preserve` - Whether outputs get the permissions and modification time
+///   of their inputs.
+/// * `legacy_decode` - Whether decoding was asked for with the deprecated
+///   `d`.
+/// * `paths` - The filepaths, where `-` stands for stdin and stdout.
+struct Config {
+    command: Command,
+    mode: Mode,
+    stdout: bool,
+    output: Option<String>,
+    output_dir: Option<String>,
+    force: bool,
+    append: bool,
+    remove: bool,
+    recursive: bool,
+    suffix: Option<String>,
+    format: Format,
+    header: bool,
+    checksum: bool,
+    transport: Transport,
+    encode_options: EncodeOptions,
+    stats: bool,
+    analyze: bool,
+    json: bool,
+    progress: bool,
+    verbosity: Verbosity,
+    lenient: bool,
+    test: bool,
+    verify: bool,
+    dry_run: bool,
+    threads: Option<usize>,
+    parallel: Option<usize>,
+    mmap: bool,
+    preserve: bool,
+    legacy_decode: bool,
+    paths: Vec<String>,
+}
+
+/// How encoded data is written, on top of the encoding scheme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transport {
+    /// As it is.
+    Binary,
+    /// As lowercase hex, in lines of 76 digits.
+    Hex,
+    /// As base64, in lines of 76 symbols.
+    Base64,
+    /// As the `[byte, count]` pairs of the encoding, written out by
+    /// [`text::to_text`].
+    Pairs,
+}
+
+impl Transport {
+    /// Return the bytes an encoded file starts with when written this way.
+    fn magic(self) -> Vec<u8> {
+        match self {
+            Self::Binary => MAGIC.to_vec(),
+            Self::Hex => hex::encode(&MAGIC).into_bytes(),
+            // Only the first 3 bytes make whole symbols on their own.
+            Self::Base64 => base64::encode(&MAGIC[..3]).into_bytes(),
+            Self::Pairs => text::to_text(&MAGIC).into_bytes(),
+        }
+    }
+}
+
+/// How much the program prints to stderr besides errors, from least to most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Verbosity {
+    /// Nothing.
+    Quiet,
+    /// Warnings, progress and summaries.
+    Normal,
+    /// The sizes of each file and of its result too.
+    Verbose,
+    /// The schemes chosen and how long each file took too.
+    Debug,
+}
+
+/// What the program does with its filepaths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    /// Encode or decode each file.
+    Convert,
+    /// Bundle the files into an archive, the first filepath.
+    Archive,
+    /// Extract files from an archive, the first filepath.
+    Extract,
+}
+
+impl Command {
+    /// Return the name the command is given by, or `None` for converting
+    /// files, which needs none.
+    fn name(self) -> Option<&'static str> {
+        match self {
+            Self::Convert => None,
+            Self::Archive => Some("archive"),
+            Self::Extract => Some("extract"),
+        }
+    }
+}
+
+/// Whether the program encodes or decodes its files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Encode,
+    Decode,
+    /// Decode files starting with the magic number and encode the rest.
+    Auto,
+}
+
+impl Config {
+    /// Create a new Config struct based on user input.
+    ///
+    /// * `args` - The command-line arguments used to create the struct, as
+    ///   described by [`USAGE`].
+    fn new(args: &[String]) -> Result<Self, ConfigError> {
+        if args.len() < 2 {
+            return Err(ConfigError::NoArguments)
+        }
+
+        let mut command = Command::Convert;
+        let mut mode = Mode::Auto;
+        let mut stdout = false;
+        let mut output = None;
+        let mut output_dir = None;
+        let mut force = false;
+        let mut append = false;
+        let mut remove = false;
+        let mut recursive = false;
+        let mut suffix = None;
+        let mut format = Format::Byte;
+        let mut header = true;
+        let mut checksum = true;
+        let mut transport = Transport::Binary;
+        let mut encode_options = EncodeOptions::default();
+        let mut stats = false;
+        let mut json = false;
+        let mut progress = false;
+        let mut quiet = false;
+        let mut verbose = 0;
+        let mut lenient = false;
+        let mut test = false;
+        let mut verify = false;
+        let mut dry_run = false;
+        let mut threads = None;
+        let mut parallel = None;
+        let mut mmap = false;
+        let mut preserve = true;
+        let mut legacy_decode = false;
+        let mut paths = Vec::new();
+        let mut args = args.iter().skip(1);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-e" | "--encode" => mode = Mode::Encode,
+                "-t" | "--test" => test = true,
+                "--lenient" => lenient = true,
+                "--verify" => verify = true,
+                "-n" | "--dry-run" => dry_run = true,
+                "--mmap" => mmap = true,
+                "--preserve" => preserve = true,
+                "--no-preserve" => preserve = false,
+                "--threads" => {
+                    let n = value(&mut args, arg)?;
+
+                    threads = match n.parse() {
+                        Ok(0) => Some(thread::available_parallelism().map_or(1, |n| n.get())),
+                        Ok(n) => Some(n),
+                        _ => return Err(ConfigError::InvalidValue(arg.to_owned(), n)),
+                    };
+                }
+                "--parallel" => {
+                    parallel = parallel.or(Some(blocks::BLOCK_SIZE));
+                    header = false;
+                }
+                "--chunk-size" => {
+                    let n = value(&mut args, arg)?;
+
+                    parallel = match n.parse() {
+                        Ok(chunk_size) if chunk_size > 0 => Some(chunk_size),
+                        _ => return Err(ConfigError::InvalidValue(arg.to_owned(), n)),
+                    };
+                    header = false;
+                }
+                "d" if paths.is_empty() => {
+                    mode = Mode::Decode;
+                    legacy_decode = true;
+                }
+                "-d" | "--decode" => mode = Mode::Decode,
+                "archive" if paths.is_empty() && command == Command::Convert => command = Command::Archive,
+                "extract" if paths.is_empty() && command == Command::Convert => command = Command::Extract,
+                "-c" | "--stdout" => stdout = true,
+                "-o" | "--output" => {
+                    output = Some(value(&mut args, arg)?);
+                }
+                arg if arg.starts_with("--output=") => {
+                    output = Some(arg["--output=".len()..].to_owned());
+                }
+                "--output-dir" => {
+                    output_dir = Some(value(&mut args, arg)?);
+                }
+                "-f" | "--force" => force = true,
+                "--append" => append = true,
+                "--rm" => remove = true,
+                "-r" | "--recursive" => recursive = true,
+                "--suffix" => {
+                    let ext = value(&mut args, arg)?;
+                    let ext = ext.strip_prefix('.').unwrap_or(&ext);
+
+                    // An empty suffix would make the output path that of the
+                    // input.
+                    if ext.is_empty() {
+                        return Err(ConfigError::EmptySuffix)
+                    }
+
+                    suffix = Some(ext.to_owned());
+                }
+                "--u16" => format = Format::U16,
+                "--leb128" => format = Format::Varint,
+                "--unit" => {
+                    let size = value(&mut args, arg)?;
+
+                    format = match size.as_str() {
+                        "1" => Format::Byte,
+                        "2" => Format::Unit2,
+                        "4" => Format::Unit4,
+                        _ => return Err(ConfigError::InvalidValue(arg.to_owned(), size)),
+                    };
+                }
+                "--counts" => {
+                    let width = value(&mut args, arg)?;
+
+                    format = match width.as_str() {
+                        "8" => Format::Byte,
+                        "16" => Format::U16,
+                        _ => return Err(ConfigError::InvalidValue(arg.to_owned(), width)),
+                    };
+                }
+                "--packbits" => format = Format::PackBits,
+                "--pcx" => format = Format::Pcx,
+                "--delta" => format = Format::Delta,
+                "--bits" => format = Format::Bits,
+                "--text" => {
+                    format = Format::Text;
+                    header = false;
+                }
+                "--format" => {
+                    let name = value(&mut args, arg)?;
+
+                    format = match name.as_str() {
+                        "byte" => Format::Byte,
+                        "u16" => Format::U16,
+                        "packbits" => Format::PackBits,
+                        "escape" => Format::Escape,
+                        "varint" => Format::Varint,
+                        "rle0" => Format::Rle0,
+                        _ => return Err(ConfigError::InvalidValue(arg.to_owned(), name)),
+                    };
+                }
+                "--no-header" | "--raw" => header = false,
+                "--no-checksum" => checksum = false,
+                "--hex" => transport = Transport::Hex,
+                "--base64" => transport = Transport::Base64,
+                "--pairs" => {
+                    transport = Transport::Pairs;
+                    header = false;
+                }
+                "--stats" => stats = true,
+                "--json" => json = true,
+                "--progress" => progress = true,
+                "-q" | "--quiet" => quiet = true,
+                "-v" | "--verbose" => verbose += 1,
+                "-vv" => verbose += 2,
+                "--max-run" => {
+                    let n = value(&mut args, arg)?;
+
+                    encode_options.max_run = match n.parse() {
+                        Ok(max_run) if max_run > 0 => max_run,
+                        _ => return Err(ConfigError::InvalidValue(arg.to_owned(), n)),
+                    };
+                }
+                "--" => paths.extend(args.by_ref().cloned()),
+                arg if arg.starts_with('-') && arg != "-" => {
+                    return Err(ConfigError::UnknownOption(arg.to_owned()))
+                }
+                arg => paths.push(arg.to_owned()),
+            }
+        }
+
+        if paths.is_empty() {
+            return Err(ConfigError::NoFilepath)
+        }
+
+        // The archive is the first filepath, and what's extracted from it
+        // goes into a directory or to stdout.
+        if let Some(name) = command.name() {
+            if output.is_some() {
+                return Err(ConfigError::ConflictingOptions(name, "-o"))
+            }
+
+            if recursive {
+                return Err(ConfigError::ConflictingOptions(name, "-r"))
+            }
+        }
+
+        if command == Command::Archive {
+            if stdout {
+                return Err(ConfigError::ConflictingOptions("archive", "-c"))
+            }
+
+            if output_dir.is_some() {
+                return Err(ConfigError::ConflictingOptions("archive", "--output-dir"))
+            }
+        }
+
+        if output.is_some() && (paths.len() > 1 || recursive) {
+            return Err(ConfigError::OutputWithManyFilepaths)
+        }
+
+        if [stdout, output.is_some(), output_dir.is_some()].iter().filter(|&&o| o).count() > 1 {
+            return Err(ConfigError::ConflictingOutputs)
+        }
+
+        if quiet && verbose > 0 {
+            return Err(ConfigError::ConflictingOptions("-q", "-v"))
+        }
+
+        let verbosity = match verbose {
+            _ if quiet => Verbosity::Quiet,
+            0 => Verbosity::Normal,
+            1 => Verbosity::Verbose,
+            _ => Verbosity::Debug,
+        };
+
+        if verify && stdout {
+            return Err(ConfigError::ConflictingOptions("--verify", "-c"))
+        }
+
+        // The JSON goes to stdout, which the output can't then go to.
+        if json && stdout {
+            return Err(ConfigError::ConflictingOptions("--json", "-c"))
+        }
+
+        // Only the header tells where an encoded file starts, and only the
+        // trailer where it ends.
+        if lenient && !header {
+            return Err(ConfigError::ConflictingOptions("--lenient", "--no-header"))
+        }
+
+        if lenient && !checksum {
+            return Err(ConfigError::ConflictingOptions("--lenient", "--no-checksum"))
+        }
+
+        if test {
+            if mode == Mode::Encode {
+                return Err(ConfigError::ConflictingOptions("-t", "-e"))
+            }
+
+            // Nothing is written, so nothing may be removed.
+            if remove {
+                return Err(ConfigError::ConflictingOptions("-t", "--rm"))
+            }
+
+            mode = Mode::Decode;
+        }
+
+        // Only encoded files can be appended to, and only the header tells
+        // where each starts.
+        if append {
+            if test {
+                return Err(ConfigError::ConflictingOptions("--append", "-t"))
+            }
+
+            if mode == Mode::Decode {
+                return Err(ConfigError::ConflictingOptions("--append", "-d"))
+            }
+
+            if !header {
+                return Err(ConfigError::ConflictingOptions("--append", "--no-header"))
+            }
+
+            // The output is checked by decoding it all, which stops at the
+            // first data that isn't an encoded file whatever --lenient says.
+            if lenient {
+                return Err(ConfigError::ConflictingOptions("--append", "--lenient"))
+            }
+
+            // The padding at the end of each file's base64 would be in the
+            // middle of the output.
+            if transport == Transport::Base64 {
+                return Err(ConfigError::ConflictingOptions("--append", "--base64"))
+            }
+
+            // The output holds the other files too.
+            if verify {
+                return Err(ConfigError::ConflictingOptions("--append", "--verify"))
+            }
+
+            // A file without a trailer runs to the end of the output, so any
+            // file appended after it would be taken as part of its data.
+            if !checksum {
+                return Err(ConfigError::ConflictingOptions("--append", "--no-checksum"))
+            }
+
+            mode = Mode::Encode;
+        }
+
+        // Without -e, the files are only analyzed, which makes no sense of
+        // encoded ones.
+        if stats && mode == Mode::Decode {
+            return Err(ConfigError::ConflictingOptions("--stats", "-d"))
+        }
+
+        let analyze = stats && mode == Mode::Auto;
+
+        // Statistics are only gathered by the default scheme's encoder, as is.
+        if stats && format != Format::Byte {
+            return Err(ConfigError::ConflictingOptions("--stats", "--format"))
+        }
+
+        if stats && encode_options != EncodeOptions::default() {
+            return Err(ConfigError::ConflictingOptions("--stats", "--max-run"))
+        }
+
+        // Only the default scheme's encoding is made of pairs, and chunks
+        // start with their lengths.
+        if transport == Transport::Pairs {
+            if format != Format::Byte {
+                return Err(ConfigError::ConflictingOptions("--pairs", "--format"))
+            }
+
+            if parallel.is_some() {
+                return Err(ConfigError::ConflictingOptions("--pairs", "--parallel"))
+            }
+        }
+
+        // Chunks are encoded with the default scheme, as they are.
+        if parallel.is_some() {
+            if threads.is_some() {
+                return Err(ConfigError::ConflictingOptions("--parallel", "--threads"))
+            }
+
+            if format != Format::Byte {
+                return Err(ConfigError::ConflictingOptions("--parallel", "--format"))
+            }
+
+            if stats {
+                return Err(ConfigError::ConflictingOptions("--parallel", "--stats"))
+            }
+
+            if encode_options != EncodeOptions::default() {
+                return Err(ConfigError::ConflictingOptions("--parallel", "--max-run"))
+            }
+        }
+
+        // Blocks are encoded with the plain schemes, and are named by the
+        // header.
+        if threads.is_some() {
+            if !header {
+                return Err(ConfigError::ConflictingOptions("--threads", "--no-header"))
+            }
+
+            if stats {
+                return Err(ConfigError::ConflictingOptions("--threads", "--stats"))
+            }
+
+            if encode_options != EncodeOptions::default() {
+                return Err(ConfigError::ConflictingOptions("--threads", "--max-run"))
+            }
+        }
+
+        Ok(Self {
+            command,
+            mode,
+            stdout,
+            output,
+            output_dir,
+            force,
+            append,
+            remove,
+            recursive,
+            suffix,
+            format,
+            header,
+            checksum,
+            transport,
+            encode_options,
+            stats,
+            analyze,
+            json,
+            progress,
+            verbosity,
+            lenient,
+            test,
+            verify,
+            dry_run,
+            threads,
+            parallel,
+            mmap,
+            preserve,
+            legacy_decode,
+            paths,
+        })
+    }
+
+    /// Return the header of a file encoded with `format`, in the current
+    /// version of the format, with a trailer unless `--no-checksum` was
+    /// given.
+    ///
+    /// * `format` - The scheme the data is encoded with.
+    fn file_header(&self, format: Format) -> Header {
+        Header { checksum: self.checksum, ..Header::new(format) }
+    }
+}
+
+/// Return the value following an option that takes one.
+///
+/// * `args` - The remaining command-line arguments.
+/// * `option` - The option the value belongs to.
+fn value<'a>(
+    args: &mut impl Iterator<Item = &'a String>, option: &str
+) -> Result<String, ConfigError> {
+    args.next().cloned().ok_or_else(|| ConfigError::MissingValue(option.to_owned()))
+}
+
+/// Remove the temporary output being written when the program is interrupted,
+/// as by Ctrl-C, before exiting.
+mod interrupt {
+    use std::path::Path;
+
+    #[cfg(unix)]
+    use std::{ffi::CString, os::unix::ffi::OsStrExt, ptr, sync::atomic::{AtomicBool, AtomicPtr, Ordering}};
+
+    /// The path of the temporary output, or null if there's none.
+    #[cfg(unix)]
+    static TEMP: AtomicPtr<libc::c_char> = AtomicPtr::new(ptr::null_mut());
+
+    /// Whether a signal is being handled, after which no path is freed.
+    #[cfg(unix)]
+    static HANDLING: AtomicBool = AtomicBool::new(false);
+
+    /// Handle the signals that interrupt the program.
+    #[cfg(unix)]
+    pub fn install() {
+        for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+            // SAFETY: the handler only makes calls that are safe in a signal
+            // handler.
+            unsafe { libc::signal(signal, on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t) };
+        }
+    }
+
+    /// Remove the temporary output, if any, and exit as the signal would
+    /// have.
+    #[cfg(unix)]
+    extern "C" fn on_signal(signal: libc::c_int) {
+        HANDLING.store(true, Ordering::SeqCst);
+        let temp = TEMP.load(Ordering::SeqCst);
+
+        // SAFETY: `temp` is null or a path that isn't freed once a signal is
+        // being handled, and unlink and _exit are async-signal-safe.
+        unsafe {
+            if !temp.is_null() {
+                libc::unlink(temp);
+            }
+
+            libc::_exit(128 + signal);
+        }
+    }
+
+    /// Set the temporary output to remove, or `None` once there's none, and
+    /// free the path of the one before.
+    ///
+    /// * `path` - The path of the temporary output.
+    #[cfg(unix)]
+    pub fn remove_on_interrupt(path: Option<&Path>) {
+        let temp = path
+            .and_then(|path| CString::new(path.as_os_str().as_bytes()).ok())
+            .map_or(ptr::null_mut(), CString::into_raw);
+        let old = TEMP.swap(temp, Ordering::SeqCst);
+
+        // A handler that started before the swap may be reading the old path
+        // on another thread, so it's left to the exit that follows.
+        if !old.is_null() && !HANDLING.load(Ordering::SeqCst) {
+            // SAFETY: `old` came from CString::into_raw, and no handler reads
+            // it: one that starts from now on reads `temp`.
+            drop(unsafe { CString::from_raw(old) });
+        }
+    }
+
+    /// Handle the signals that interrupt the program, which only Unix has.
+    #[cfg(not(unix))]
+    pub fn install() {}
+
+    /// Set the temporary output to remove, which only Unix can.
+    #[cfg(not(unix))]
+    pub fn remove_on_interrupt(_: Option<&Path>) {}
+}
+
+/// Print messages to stderr, as much of them as the verbosity allows.
+mod report {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::{Config, Verbosity};
+
+    /// Whether the progress line is on stderr, where it must be cleared
+    /// before anything else is printed.
+    static PROGRESS_SHOWN: AtomicBool = AtomicBool::new(false);
+
+    /// Print an error message, whatever the verbosity.
+    ///
+    /// * `msg` - The message that will be printed.
+    pub fn bail(msg: &str) {
+        clear_progress();
+        eprintln!("rle-rs: error: {}", msg);
+    }
+
+    /// Print a warning message, unless `-q` was given.
+    ///
+    /// * `config` - How the program is being run.
+    /// * `msg` - The message that will be printed.
+    pub fn warn(config: &Config, msg: &str) {
+        print(config, Verbosity::Normal, &format!("warning: {}", msg));
+    }
+
+    /// Print a message, unless `-q` was given.
+    ///
+    /// * `config` - How the program is being run.
+    /// * `msg` - The message that will be printed.
+    pub fn note(config: &Config, msg: &str) {
+        print(config, Verbosity::Normal, msg);
+    }
+
+    /// Print a message with `-v`.
+    ///
+    /// * `config` - How the program is being run.
+    /// * `msg` - The message that will be printed.
+    pub fn info(config: &Config, msg: &str) {
+        print(config, Verbosity::Verbose, msg);
+    }
+
+    /// Print a message with `-vv`.
+    ///
+    /// * `config` - How the program is being run.
+    /// * `msg` - The message that will be printed.
+    pub fn debug(config: &Config, msg: &str) {
+        print(config, Verbosity::Debug, msg);
+    }
+
+    /// Print a message if the verbosity is at least `level`.
+    ///
+    /// * `config` - How the program is being run.
+    /// * `level` - The least verbosity the message is printed at.
+    /// * `msg` - The message that will be printed.
+    fn print(config: &Config, level: Verbosity, msg: &str) {
+        if config.verbosity >= level {
+            clear_progress();
+            eprintln!("rle-rs: {}", msg);
+        }
+    }
+
+    /// Show `line` as the progress line, in place of the last one.
+    ///
+    /// * `line` - The progress to show.
+    pub fn progress(line: &str) {
+        // Go back to the start of the line and clear it before writing over
+        // it.
+        eprint!("\r\x1b[Krle-rs: {}", line);
+        PROGRESS_SHOWN.store(true, Ordering::Relaxed);
+    }
+
+    /// Clear the progress line, if it's there.
+    pub fn clear_progress() {
+        if PROGRESS_SHOWN.swap(false, Ordering::Relaxed) {
+            eprint!("\r\x1b[K");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    use rle_rs::packbits;
+
+    /// Write `bytes` to a fresh file in the temporary directory and return
+    /// its path.
+    ///
+    /// * `name` - A name unique to the calling test.
+    /// * `bytes` - The contents of the file.
+    fn temp_file(name: &str, bytes: &[u8]) -> String {
+        let path = env::temp_dir().join(format!("rle-rs-{}-{}", process::id(), name));
+        fs::write(&path, bytes).unwrap();
+
+        path.to_str().unwrap().to_owned()
+    }
+
+    /// Return the contents of a file encoded with `format`.
+    ///
+    /// * `format` - The scheme the file is encoded with.
+    /// * `body` - The encoded data.
+    /// * `original` - The data before encoding, for the trailer.
+    fn encoded_file(format: Format, body: &[u8], original: &[u8]) -> Vec<u8> {
+        [&Header::new(format).to_bytes()[..], body, &Trailer::of(original).to_bytes()].concat()
+    }
+
+    /// Turn string literals into owned command-line arguments.
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    /// Run the program on every file named by the command-line arguments,
+    /// stopping at the first failure.
+    fn run_args(args: &[String]) -> Result<(), RleError> {
+        let config = Config::new(args).unwrap();
+
+        config.paths.iter().try_for_each(|path| run(path, Path::new(""), &config).map(|_| ()))
+    }
+
+    #[test]
+    fn config_decode_without_filepath_is_an_error() {
+        assert_eq!(Config::new(&args(&["rle-rs", "d"])).err(), Some(ConfigError::NoFilepath));
+    }
+
+    #[test]
+    fn config_decode_aliases() {
+        let config = Config::new(&args(&["rle-rs", "d", "file"])).unwrap();
+        assert_eq!((config.mode, config.legacy_decode), (Mode::Decode, true));
+
+        let config = Config::new(&args(&["rle-rs", "--decode", "file"])).unwrap();
+        assert_eq!((config.mode, config.legacy_decode), (Mode::Decode, false));
+
+        // Only before the filepaths.
+        let config = Config::new(&args(&["rle-rs", "file", "d"])).unwrap();
+        assert_eq!((config.mode, config.paths), (Mode::Auto, vec!["file".to_owned(), "d".to_owned()]));
+    }
+
+    #[test]
+    fn config_options_after_filepaths() {
+        let config = Config::new(&args(&["rle-rs", "file", "-d", "-f"])).unwrap();
+
+        assert_eq!((config.mode, config.force), (Mode::Decode, true));
+        assert_eq!(config.paths, ["file"]);
+    }
+
+    #[test]
+    fn config_double_dash_ends_options() {
+        let config = Config::new(&args(&["rle-rs", "-f", "--", "-d", "--", "file"])).unwrap();
+
+        assert_eq!(config.mode, Mode::Auto);
+        assert_eq!(config.paths, ["-d", "--", "file"]);
+        assert_eq!(Config::new(&args(&["rle-rs", "--"])).err(), Some(ConfigError::NoFilepath));
+    }
+
+    #[test]
+    fn config_unknown_option_is_named() {
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "file", "--frobnicate"])).err(),
+            Some(ConfigError::UnknownOption("--frobnicate".to_owned()))
+        );
+    }
+
+    #[test]
+    fn info_is_requested_before_double_dash() {
+        assert_eq!(info_request(&args(&["rle-rs", "-d", "--help"])), Some(Info::Help));
+        assert_eq!(info_request(&args(&["rle-rs", "-h", "-V"])), Some(Info::Help));
+        assert_eq!(info_request(&args(&["rle-rs", "file", "--version"])), Some(Info::Version));
+        assert_eq!(info_request(&args(&["rle-rs", "-V"])), Some(Info::Version));
+        assert_eq!(info_request(&args(&["rle-rs", "--", "--help"])), None);
+        assert_eq!(info_request(&args(&["rle-rs", "file"])), None);
+    }
+
+    #[test]
+    fn config_dash_filepath() {
+        let config = Config::new(&args(&["rle-rs", "d", "-"])).unwrap();
+
+        assert_eq!(config.paths, ["-"]);
+        assert_eq!(config.mode, Mode::Decode);
+    }
+
+    #[test]
+    fn config_u16_option() {
+        let config = Config::new(&args(&["rle-rs", "--u16", "d", "file"])).unwrap();
+
+        assert_eq!(config.format, Format::U16);
+        assert_eq!(config.mode, Mode::Decode);
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--u8", "file"])).err(),
+            Some(ConfigError::UnknownOption("--u8".to_owned()))
+        );
+    }
+
+    #[test]
+    fn config_leb128_option() {
+        let config = Config::new(&args(&["rle-rs", "--leb128", "file"])).unwrap();
+
+        assert_eq!(config.format, Format::Varint);
+    }
+
+    #[test]
+    fn config_multiple_filepaths() {
+        let config = Config::new(&args(&["rle-rs", "d", "a", "d", "-c"])).unwrap();
+
+        assert_eq!(config.mode, Mode::Decode);
+        assert!(config.stdout);
+        assert_eq!(config.paths, ["a", "d"]);
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "-o", "out", "a", "b"])).err(),
+            Some(ConfigError::OutputWithManyFilepaths)
+        );
+    }
+
+    #[test]
+    fn config_stdout_option() {
+        assert!(Config::new(&args(&["rle-rs", "-c", "file"])).unwrap().stdout);
+        assert!(Config::new(&args(&["rle-rs", "d", "--stdout", "file"])).unwrap().stdout);
+        assert!(!Config::new(&args(&["rle-rs", "file"])).unwrap().stdout);
+        assert!(Config::new(&args(&["rle-rs", "-f", "file"])).unwrap().force);
+        assert!(Config::new(&args(&["rle-rs", "-x", "file"])).is_err());
+    }
+
+    #[test]
+    fn config_output_option() {
+        let config = Config::new(&args(&["rle-rs", "d", "-o", "out", "file"])).unwrap();
+        assert_eq!(config.output.as_deref(), Some("out"));
+
+        let config = Config::new(&args(&["rle-rs", "--output=out", "file"])).unwrap();
+        assert_eq!(config.output.as_deref(), Some("out"));
+
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "file", "-o"])).err(),
+            Some(ConfigError::MissingValue("-o".to_owned()))
+        );
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "-c", "-o", "out", "file"])).err(),
+            Some(ConfigError::ConflictingOutputs)
+        );
+    }
+
+    #[test]
+    fn run_output_option() {
+        let path = temp_file("output", b"xxxy");
+        let output = format!("{}.custom", path);
+        run_args(&args(&["rle-rs", "-o", &output, &path])).unwrap();
+
+        assert_eq!(fs::read(output).unwrap(), encoded_file(Format::Byte, &[b'x', 3, b'y', 1], b"xxxy"));
+    }
+
+    #[test]
+    fn run_output_dir_option() {
+        let path = temp_file("output-dir-input", b"xxxy");
+        let dir = env::temp_dir().join(format!("rle-rs-{}-output-dir", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let temp = env::temp_dir();
+        let input = temp.join("..").join(temp.file_name().unwrap()).join(Path::new(&path).file_name().unwrap());
+        let args = args(&["rle-rs", "--output-dir", dir.to_str().unwrap(), input.to_str().unwrap()]);
+        run_args(&args).unwrap();
+
+        assert_eq!(
+            fs::read(dir.join(format!("rle-rs-{}-output-dir-input.rle", process::id()))).unwrap(),
+            encoded_file(Format::Byte, &[b'x', 3, b'y', 1], b"xxxy")
+        );
+    }
+
+    #[test]
+    fn config_archive_commands() {
+        let config = Config::new(&args(&["rle-rs", "archive", "all.rlea", "a", "b"])).unwrap();
+        assert_eq!((config.command, config.paths), (Command::Archive, args(&["all.rlea", "a", "b"])));
+
+        let config = Config::new(&args(&["rle-rs", "-f", "extract", "all.rlea"])).unwrap();
+        assert_eq!((config.command, config.paths), (Command::Extract, args(&["all.rlea"])));
+
+        // Only the first filepath can be a command.
+        let config = Config::new(&args(&["rle-rs", "file", "archive"])).unwrap();
+        assert_eq!((config.command, config.paths), (Command::Convert, args(&["file", "archive"])));
+
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "extract", "-o", "out", "all.rlea"])).err(),
+            Some(ConfigError::ConflictingOptions("extract", "-o"))
+        );
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "archive", "-c", "all.rlea", "a"])).err(),
+            Some(ConfigError::ConflictingOptions("archive", "-c"))
+        );
+    }
+
+    #[test]
+    fn archive_and_extract_round_trip() {
+        let a = temp_file("archived-a", b"aaaaaaab");
+        let b = temp_file("archived-b", &[]);
+        let archived = env::temp_dir().join(format!("rle-rs-{}-archive.rlea", process::id()));
+        let _ = fs::remove_file(&archived);
+        let dir = env::temp_dir().join(format!("rle-rs-{}-extracted", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let name = |path: &str| Path::new(path).file_name().unwrap().to_str().unwrap().to_owned();
+
+        archive(&Config::new(&args(&["rle-rs", "archive", archived.to_str().unwrap(), &a, &b])).unwrap()).unwrap();
+
+        let bytes = fs::read(&archived).unwrap();
+        assert_eq!(rle_rs::list_archive(&bytes).unwrap(), [name(&a), name(&b)]);
+
+        let extract_args = |names: &[&str]| {
+            let mut extract_args = args(&["rle-rs", "extract", "--output-dir", dir.to_str().unwrap()]);
+            extract_args.push(archived.to_str().unwrap().to_owned());
+            extract_args.extend(args(names));
+            extract(&Config::new(&extract_args).unwrap())
+        };
+
+        extract_args(&[&name(&b)]).unwrap();
+        assert_eq!(fs::read(dir.join(name(&b))).unwrap(), []);
+        assert!(!dir.join(name(&a)).exists());
+
+        // The file extracted before is only overwritten with -f, but the
+        // files before it are still extracted.
+        assert!(extract_args(&[]).is_err());
+        fs::remove_file(dir.join(name(&a))).unwrap();
+        fs::remove_file(dir.join(name(&b))).unwrap();
+        extract_args(&[]).unwrap();
+
+        assert_eq!(fs::read(dir.join(name(&a))).unwrap(), b"aaaaaaab");
+        let e = extract_args(&["missing"]).unwrap_err();
+        let missing = DecodeError::FileNotInArchive("missing".to_owned());
+        assert!(matches!(&e, RleError::File { error, .. } if error.get_ref().unwrap().downcast_ref() == Some(&missing)));
+    }
+
+    #[test]
+    fn archive_refuses_files_with_the_same_name() {
+        let a = temp_file("same-name", b"a");
+        let dir = env::temp_dir().join(format!("rle-rs-{}-same-name-dir", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let b = dir.join(Path::new(&a).file_name().unwrap());
+        fs::write(&b, b"b").unwrap();
+        let archived = env::temp_dir().join(format!("rle-rs-{}-same-name.rlea", process::id()));
+        let args = args(&["rle-rs", "archive", archived.to_str().unwrap(), &a, b.to_str().unwrap()]);
+        let e = archive(&Config::new(&args).unwrap()).unwrap_err();
+
+        assert!(matches!(&e, RleError::File { path, error } if *path == b && error.kind() == io::ErrorKind::InvalidInput));
+        assert!(!archived.exists());
+    }
+
+    #[test]
+    fn extract_refuses_names_that_are_paths() {
+        let dir = env::temp_dir().join(format!("rle-rs-{}-unsafe-dir", process::id()));
+        let path = temp_file("unsafe.rlea", &rle_rs::create_archive(&[("../escaped", b"x")]));
+        let args = args(&["rle-rs", "extract", "--output-dir", dir.to_str().unwrap(), &path]);
+        let e = extract(&Config::new(&args).unwrap()).unwrap_err();
+
+        assert!(matches!(&e, RleError::File { path: p, .. } if *p == Path::new(&path)));
+        assert!(e.to_string().contains("'../escaped'"));
+        assert!(!env::temp_dir().join("escaped").exists());
+    }
+
+    #[test]
+    fn extract_errors_name_the_archive() {
+        let mut archived = rle_rs::create_archive(&[("a", b"xxxy")]);
+        let len = archived.len();
+        archived[len - 1] ^= 0xff;
+        let path = temp_file("corrupt.rlea", &archived);
+
+        for names in &[&[][..], &["a"]] {
+            let args = args(&[&["rle-rs", "extract", "-c", &path][..], names].concat());
+            let e = extract(&Config::new(&args).unwrap()).unwrap_err();
+
+            assert!(e.to_string().starts_with(&format!("'{}': ", path)), "{}", e);
+        }
+    }
+
+    #[test]
+    fn config_append_option() {
+        let config = Config::new(&args(&["rle-rs", "--append", "-o", "all.rle", "file"])).unwrap();
+        assert!(config.append);
+        assert_eq!(config.mode, Mode::Encode);
+
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--append", "-d", "file"])).err(),
+            Some(ConfigError::ConflictingOptions("--append", "-d"))
+        );
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--append", "--parallel", "file"])).err(),
+            Some(ConfigError::ConflictingOptions("--append", "--no-header"))
+        );
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--append", "--verify", "file"])).err(),
+            Some(ConfigError::ConflictingOptions("--append", "--verify"))
+        );
+    }
+
+    #[test]
+    fn run_append_builds_a_file_of_several() {
+        let output = env::temp_dir().join(format!("rle-rs-{}-appended.rle", process::id()));
+        let _ = fs::remove_file(&output);
+        let output = output.to_str().unwrap();
+        let inputs = [temp_file("append-a", b"aaaab"), temp_file("append-b", &[]), temp_file("append-c", b"c")];
+
+        for (i, input) in inputs.iter().enumerate() {
+            // Mapped inputs are appended the same way.
+            let options: &[&str] = if i == 1 { &["--mmap"] } else { &[] };
+            run_args(&args(&[&["rle-rs", "--append", "-o", output][..], options, &[input]].concat())).unwrap();
+        }
+
+        let appended = fs::read(output).unwrap();
+        assert_eq!(rle_rs::decode_members(&appended).unwrap(), b"aaaabc");
+
+        run_args(&args(&["rle-rs", "-d", "-f", "-o", &format!("{}.dat", output), output])).unwrap();
+        assert_eq!(fs::read(format!("{}.dat", output)).unwrap(), b"aaaabc");
+    }
+
+    #[test]
+    fn run_append_refuses_a_truncated_file() {
+        let encoded = rle_rs::encode_with_header(b"xxxy", Format::Byte);
+        let output = temp_file("append-truncated.rle", &encoded[..encoded.len() - 1]);
+        let input = temp_file("append-truncated-input", b"zz");
+        let e = run_args(&args(&["rle-rs", "--append", "-o", &output, &input])).unwrap_err();
+
+        assert!(matches!(
+            &e,
+            RleError::File { path, error } if *path == Path::new(&output) && error.kind() == io::ErrorKind::InvalidData
+        ));
+        assert_eq!(fs::read(&output).unwrap(), &encoded[..encoded.len() - 1]);
+    }
+
+    #[test]
+    fn run_refuses_to_overwrite_output() {
+        let path = temp_file("existing-output", b"xxy");
+        let output = temp_file("existing-output.rle", b"precious");
+        let e = run_args(&args(&["rle-rs", &path])).unwrap_err();
+
+        assert!(matches!(
+            &e,
+            RleError::File { path, error } if *path == Path::new(&output) && error.kind() == io::ErrorKind::AlreadyExists
+        ));
+        assert_eq!(
+            e.to_string(),
+            format!("'{}': the output already exists (use --force to overwrite)", output)
+        );
+        assert_eq!(fs::read(&output).unwrap(), b"precious");
+    }
+
+    #[test]
+    fn run_force_overwrites_output() {
+        let path = temp_file("forced-output", b"xxxy");
+        let output = temp_file("forced-output.rle", b"stale contents");
+        run_args(&args(&["rle-rs", "--force", &path])).unwrap();
+
+        assert_eq!(fs::read(&output).unwrap(), encoded_file(Format::Byte, &[b'x', 3, b'y', 1], b"xxxy"));
+    }
+
+    #[test]
+    fn output_is_not_moved_over_a_file_created_meanwhile() {
+        let path = env::temp_dir().join(format!("rle-rs-{}-raced-output.rle", process::id()));
+        let mut output = Output::create(&path, false).unwrap();
+        let temp = output.written_path().to_owned();
+        (&output.file).write_all(b"result").unwrap();
+        fs::write(&path, b"precious").unwrap();
+
+        assert!(matches!(
+            output.persist(false),
+            Err(RleError::File { error, .. }) if error.kind() == io::ErrorKind::AlreadyExists
+        ));
+        drop(output);
+        assert_eq!(fs::read(&path).unwrap(), b"precious");
+        assert!(!temp.exists());
+    }
+
+    #[test]
+    fn output_is_removed_unless_persisted() {
+        let path = env::temp_dir().join(format!("rle-rs-{}-dropped-output.rle", process::id()));
+        let output = Output::create(&path, false).unwrap();
+        let temp = output.written_path().to_owned();
+
+        assert_eq!(
+            temp.file_name().unwrap().to_str().unwrap(),
+            format!(".rle-rs-{}-dropped-output.rle.tmp{}-0", process::id(), process::id())
+        );
+        assert!(temp.exists());
+        drop(output);
+        assert!(!temp.exists());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn run_failure_leaves_no_output() {
+        let path = temp_file("failed-decode.rle", &encoded_file(Format::Byte, &[b'x', 3, b'y'], b"xxxy"));
+        let prefix = format!(".rle-rs-{}-failed-decode", process::id());
+        run_args(&args(&["rle-rs", "-d", &path])).unwrap_err();
+
+        assert!(!Path::new(&path[..path.len() - 4]).exists());
+        assert!(!fs::read_dir(env::temp_dir())
+            .unwrap()
+            .any(|entry| entry.unwrap().file_name().to_string_lossy().starts_with(&prefix)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_force_writes_to_devices_in_place() {
+        use std::os::unix::fs::FileTypeExt;
+
+        let path = temp_file("to-device", b"xxxy");
+        run_args(&args(&["rle-rs", "-f", "-o", "/dev/null", &path])).unwrap();
+
+        assert!(fs::metadata("/dev/null").unwrap().file_type().is_char_device());
+    }
+
+    #[test]
+    fn config_preserve_options() {
+        let preserve = |options: &[&str]| {
+            let args = args(&[&["rle-rs"], options, &["file"]].concat());
+            Config::new(&args).unwrap().preserve
+        };
+
+        assert!(preserve(&[]));
+        assert!(preserve(&["--preserve"]));
+        assert!(!preserve(&["--no-preserve"]));
+        assert!(preserve(&["--no-preserve", "--preserve"]));
+    }
+
+    #[test]
+    fn run_preserves_the_modification_time() {
+        let path = temp_file("preserved", b"xxxy");
+        let time = std::time::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        File::options().write(true).open(&path).unwrap().set_modified(time).unwrap();
+        run_args(&args(&["rle-rs", "-f", &path])).unwrap();
+
+        assert_eq!(fs::metadata(format!("{}.rle", path)).unwrap().modified().unwrap(), time);
+
+        run_args(&args(&["rle-rs", "-f", "--no-preserve", &path])).unwrap();
+
+        assert_ne!(fs::metadata(format!("{}.rle", path)).unwrap().modified().unwrap(), time);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_preserves_the_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_file("preserved-mode", b"xxxy");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        run_args(&args(&["rle-rs", "-f", &path])).unwrap();
+
+        assert_eq!(fs::metadata(format!("{}.rle", path)).unwrap().permissions().mode() & 0o777, 0o640);
+    }
+
+    #[test]
+    fn run_rm_removes_input() {
+        let path = temp_file("rm", b"xxxy");
+        run_args(&args(&["rle-rs", "--rm", &path])).unwrap();
+
+        assert!(!Path::new(&path).exists());
+        assert_eq!(fs::read(format!("{}.rle", path)).unwrap(), encoded_file(Format::Byte, &[b'x', 3, b'y', 1], b"xxxy"));
+    }
+
+    #[test]
+    fn run_rm_keeps_input_on_failure() {
+        let path = temp_file("rm-failure", &[7]);
+
+        assert!(run_args(&args(&["rle-rs", "--rm", "d", &path])).is_err());
+        assert!(Path::new(&path).exists());
+    }
+
+    #[test]
+    fn run_refuses_to_write_over_input() {
+        let path = temp_file("output-over-input", b"xxy");
+
+        assert!(run_args(&args(&["rle-rs", "-o", &path, &path])).is_err());
+        assert_eq!(fs::read(path).unwrap(), b"xxy");
+    }
+
+    #[test]
+    fn config_threads_option() {
+        let config = Config::new(&args(&["rle-rs", "--threads", "3", "file"])).unwrap();
+        assert_eq!(config.threads, Some(3));
+
+        let config = Config::new(&args(&["rle-rs", "--threads", "0", "file"])).unwrap();
+        assert!(config.threads.unwrap() >= 1);
+
+        assert_eq!(Config::new(&args(&["rle-rs", "file"])).unwrap().threads, None);
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--threads", "many", "file"])).err(),
+            Some(ConfigError::InvalidValue("--threads".into(), "many".into()))
+        );
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--threads", "2", "--raw", "file"])).err(),
+            Some(ConfigError::ConflictingOptions("--threads", "--no-header"))
+        );
+    }
+
+    #[test]
+    fn config_parallel_options() {
+        let config = Config::new(&args(&["rle-rs", "--parallel", "file"])).unwrap();
+        assert_eq!((config.parallel, config.header), (Some(blocks::BLOCK_SIZE), false));
+
+        let config = Config::new(&args(&["rle-rs", "--chunk-size", "4096", "--parallel", "file"])).unwrap();
+        assert_eq!(config.parallel, Some(4096));
+
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--chunk-size", "0", "file"])).err(),
+            Some(ConfigError::InvalidValue("--chunk-size".into(), "0".into()))
+        );
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--parallel", "--threads", "2", "file"])).err(),
+            Some(ConfigError::ConflictingOptions("--parallel", "--threads"))
+        );
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--parallel", "--u16", "file"])).err(),
+            Some(ConfigError::ConflictingOptions("--parallel", "--format"))
+        );
+    }
+
+    #[test]
+    fn config_json_option() {
+        assert!(Config::new(&args(&["rle-rs", "--json", "file"])).unwrap().json);
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--json", "-c", "file"])).err(),
+            Some(ConfigError::ConflictingOptions("--json", "-c"))
+        );
+    }
+
+    #[test]
+    fn config_progress_options() {
+        let config = Config::new(&args(&["rle-rs", "--progress", "file"])).unwrap();
+        assert!(config.progress);
+        assert_eq!(config.verbosity, Verbosity::Normal);
+    }
+
+    #[test]
+    fn config_verbosity_options() {
+        let verbosity = |options: &[&str]| {
+            let args = args(&[&["rle-rs"], options, &["file"]].concat());
+            Config::new(&args).map(|config| config.verbosity)
+        };
+
+        assert_eq!(verbosity(&[]), Ok(Verbosity::Normal));
+        assert_eq!(verbosity(&["-q"]), Ok(Verbosity::Quiet));
+        assert_eq!(verbosity(&["--quiet"]), Ok(Verbosity::Quiet));
+        assert_eq!(verbosity(&["-v"]), Ok(Verbosity::Verbose));
+        assert_eq!(verbosity(&["-vv"]), Ok(Verbosity::Debug));
+        assert_eq!(verbosity(&["-v", "--verbose", "-v"]), Ok(Verbosity::Debug));
+        assert_eq!(verbosity(&["-q", "-v"]), Err(ConfigError::ConflictingOptions("-q", "-v")));
+    }
+
+    #[test]
+    fn summary_separates_thousands() {
+        let outcome = Outcome {
+            operation: "encode",
+            input_size: 83_412_992,
+            output_size: 1_204_551,
+            analysis: None,
+        };
+        assert_eq!(summary(&outcome), "83,412,992 -> 1,204,551 bytes (1.4%)");
+
+        let outcome = Outcome { operation: "encode", input_size: 0, output_size: 0, analysis: None };
+        assert_eq!(summary(&outcome), "0 -> 0 bytes (0.0%)");
+
+        assert_eq!(separated(999), "999");
+        assert_eq!(separated(1000), "1,000");
+        assert_eq!(separated(123_456_789), "123,456,789");
+    }
+
+    #[test]
+    fn progress_line_shows_the_share_rate_and_time_left() {
+        assert_eq!(
+            progress_line(25_000_000, Some(100_000_000), Duration::from_secs(2)),
+            "25.0 MB of 100.0 MB (25%), 12.5 MB/s, 0:00:06 left"
+        );
+        assert_eq!(
+            progress_line(7_200_000_000, Some(7_210_000_000), Duration::from_secs(3600)),
+            "7200.0 MB of 7210.0 MB (100%), 2.0 MB/s, 0:00:05 left"
+        );
+        assert_eq!(progress_line(3_000_000, None, Duration::from_secs(1)), "3.0 MB, 3.0 MB/s");
+    }
+
+    #[test]
+    fn progress_reads_its_input_as_it_is() {
+        let mut progress = Progress::new(&b"xxxy"[..], "file", Some(4));
+        let mut read = Vec::new();
+        progress.read_to_end(&mut read).unwrap();
+
+        assert_eq!(read, b"xxxy");
+        assert_eq!(progress.read, 4);
+    }
+
+    #[test]
+    fn json_string_escapes() {
+        assert_eq!(json_string("a\"b\\c\nd\u{1}é"), r#""a\"b\\c\nd\u0001é""#);
+    }
+
+    #[test]
+    fn json_report_of_an_encoding() {
+        let path = temp_file("json", b"xxxy");
+        let config = Config::new(&args(&["rle-rs", "--json", &path])).unwrap();
+        let outcome = run(&path, Path::new(""), &config);
+        let report = json_report(&path, &outcome, Duration::from_micros(1500), &config);
+
+        assert_eq!(
+            report,
+            format!(
+                r#"{{"path":{},"operation":"encode","input_size":4,"output_size":{},"ratio":{:.6},"duration_ms":1.500,"status":"ok","error":null}}"#,
+                json_string(&path),
+                encoded_file(Format::Stored, b"xxxy", b"xxxy").len(),
+                encoded_file(Format::Stored, b"xxxy", b"xxxy").len() as f64 / 4.0,
+            )
+        );
+    }
+
+    #[test]
+    fn json_report_of_a_failure() {
+        let config = Config::new(&args(&["rle-rs", "--json", "d", "missing"])).unwrap();
+        let outcome = run("/nonexistent/missing", Path::new(""), &config);
+        let report = json_report("missing", &outcome, Duration::ZERO, &config);
+
+        assert!(report.starts_with(
+            r#"{"path":"missing","operation":"decode","input_size":null,"output_size":null,"ratio":null,"#
+        ));
+        assert!(report.contains(r#""status":"error","error":""#));
+    }
+
+    #[test]
+    fn json_report_of_an_analysis() {
+        let path = temp_file("json-stats", b"abbbbc");
+        let config = Config::new(&args(&["rle-rs", "--json", "--stats", &path])).unwrap();
+        let report = json_report(&path, &run(&path, Path::new(""), &config), Duration::ZERO, &config);
+
+        assert!(report.contains(r#""operation":"stats","input_size":6,"output_size":6,"ratio":1.000000,"#));
+        assert!(report.ends_with(
+            r#""runs":3,"mean_run":2.000000,"median_run":1,"longest_run":{"byte":98,"len":4,"offset":1},"long_run_fraction":0.666667,"entropy":1.251629}"#
+        ));
+    }
+
+    #[test]
+    fn config_max_run_option() {
+        let config = Config::new(&args(&["rle-rs", "--max-run", "16", "file"])).unwrap();
+        assert_eq!(config.encode_options.max_run, 16);
+
+        for n in &["0", "256", "x"] {
+            assert_eq!(
+                Config::new(&args(&["rle-rs", "--max-run", n, "file"])).err(),
+                Some(ConfigError::InvalidValue("--max-run".to_owned(), n.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn config_suffix_option() {
+        let config = Config::new(&args(&["rle-rs", "--suffix", ".rlz", "file"])).unwrap();
+        assert_eq!(config.suffix.as_deref(), Some("rlz"));
+
+        let config = Config::new(&args(&["rle-rs", "--suffix", "rlz", "file"])).unwrap();
+        assert_eq!(config.suffix.as_deref(), Some("rlz"));
+
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--suffix", ".", "file"])).err(),
+            Some(ConfigError::EmptySuffix)
+        );
+    }
+
+    #[test]
+    fn output_path_suffix() {
+        let config = Config::new(&args(&["rle-rs", "--suffix", "rlz", "file"])).unwrap();
+        assert_eq!(output_path("dir/a.txt", true, &config), "dir/a.txt.rlz");
+
+        let config = Config::new(&args(&["rle-rs", "d", "--suffix", "rlz", "file"])).unwrap();
+        assert_eq!(output_path("dir/a.txt.rlz", false, &config), "dir/a.txt");
+        assert_eq!(output_path("dir/.rlz", false, &config), "dir/.rlz.dat");
+        assert_eq!(output_path("dir/a.txt", false, &config), "dir/a.txt.dat");
+
+    }
+
+    #[test]
+    fn output_path_strips_suffix_on_decode() {
+        let config = Config::new(&args(&["rle-rs", "d", "file"])).unwrap();
+        let missing = env::temp_dir().join(format!("rle-rs-{}-missing", process::id()));
+        let missing = missing.to_str().unwrap();
+
+        assert_eq!(output_path("data.rle", false, &config), "data");
+        assert_eq!(output_path(&format!("{}.rle.rle", missing), false, &config), format!("{}.rle", missing));
+        assert_eq!(output_path("data.txt", false, &config), "data.txt.dat");
+    }
+
+    #[test]
+    fn output_path_falls_back_to_dat_when_original_exists() {
+        let original = temp_file("existing-original", b"xxy");
+        let encoded = format!("{}.rle", original);
+
+        let config = Config::new(&args(&["rle-rs", "d", "file"])).unwrap();
+        assert_eq!(output_path(&encoded, false, &config), format!("{}.dat", encoded));
+
+        let config = Config::new(&args(&["rle-rs", "d", "-f", "file"])).unwrap();
+        assert_eq!(output_path(&encoded, false, &config), original);
+    }
+
+    #[test]
+    fn run_u16_round_trip() {
+        let path = temp_file("u16", &[1; 1000]);
+        run_args(&args(&["rle-rs", "--u16", &path])).unwrap();
+        let encoded = format!("{}.rle", path);
+
+        assert_eq!(fs::read(&encoded).unwrap(), encoded_file(Format::U16, &[1, 0xe8, 0x03], &[1; 1000]));
+
+        // The header names the scheme, so decoding doesn't need --u16.
+        run_args(&args(&["rle-rs", "d", &encoded])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", encoded)).unwrap(), [1; 1000]);
+    }
+
+    #[test]
+    fn run_packbits_round_trip() {
+        let bytes = [&[1; 10][..], b"abc"].concat();
+        let path = temp_file("packbits", &bytes);
+        run_args(&args(&["rle-rs", "--packbits", &path])).unwrap();
+        let encoded = format!("{}.rle", path);
+
+        assert_eq!(fs::read(&encoded).unwrap(), encoded_file(Format::PackBits, &packbits::encode(&bytes), &bytes));
+
+        run_args(&args(&["rle-rs", "--packbits", "d", &encoded])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", encoded)).unwrap(), bytes);
+    }
+
+    #[test]
+    fn run_decode_one_byte_file_is_an_error() {
+        let path = temp_file("one-byte", &[7]);
+        let e = run_args(&args(&["rle-rs", "--no-header", "d", &path])).unwrap_err();
+
+        assert!(matches!(e, RleError::Decode(DecodeError::TruncatedInput { at_byte: 0 })));
+    }
+
+    #[test]
+    fn run_decode_dangling_final_byte_is_an_error() {
+        let path = temp_file("dangling", &encoded_file(Format::Byte, &[7, 1, 8], &[7]));
+
+        let e = run_args(&args(&["rle-rs", "d", &path])).unwrap_err();
+
+        assert!(matches!(e, RleError::Decode(DecodeError::TruncatedInput { .. })));
+    }
+
+    #[test]
+    fn run_decode_empty_file() {
+        let path = temp_file("empty", &[]);
+        run_args(&args(&["rle-rs", "--no-header", "d", &path])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", path)).unwrap(), []);
+    }
+
+    #[test]
+    fn run_decode_without_header_is_an_error() {
+        let path = temp_file("headerless", &[b'x', 2, b'y', 1]);
+        let e = run_args(&args(&["rle-rs", "d", &path])).unwrap_err();
+
+        assert!(matches!(e, RleError::Decode(DecodeError::InvalidMagic)));
+    }
+
+    #[test]
+    fn run_no_header_round_trip() {
+        let path = temp_file("no-header", b"xxy");
+        run_args(&args(&["rle-rs", "--no-header", &path])).unwrap();
+        let encoded = format!("{}.rle", path);
+
+        assert_eq!(fs::read(&encoded).unwrap(), [b'x', 2, b'y', 1]);
+
+        run_args(&args(&["rle-rs", "--raw", "d", &encoded])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", encoded)).unwrap(), b"xxy");
+    }
+
+    #[test]
+    fn config_recursive_option() {
+        assert!(Config::new(&args(&["rle-rs", "-r", "dir"])).unwrap().recursive);
+        assert!(Config::new(&args(&["rle-rs", "d", "--recursive", "dir"])).unwrap().recursive);
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "-r", "-o", "out", "dir"])).err(),
+            Some(ConfigError::OutputWithManyFilepaths)
+        );
+    }
+
+    #[test]
+    fn run_dir_walks_subdirectories() {
+        let dir = env::temp_dir().join(format!("rle-rs-{}-recursive", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a"), b"aa").unwrap();
+        fs::write(dir.join("sub").join("b"), b"bb").unwrap();
+        fs::write(dir.join("done.rle"), b"not encoded again").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&dir, dir.join("sub").join("loop")).unwrap();
+
+        let config = Config::new(&args(&["rle-rs", "-r", dir.to_str().unwrap()])).unwrap();
+
+        let mut tally = Tally::default();
+        run_dir(&dir, &dir, &config, &mut tally);
+
+        assert_eq!((tally.processed, tally.failed), (2, 0));
+        assert_eq!(fs::read(dir.join("a.rle")).unwrap(), encoded_file(Format::Byte, &[b'a', 2], b"aa"));
+        assert_eq!(fs::read(dir.join("sub").join("b.rle")).unwrap(), encoded_file(Format::Byte, &[b'b', 2], b"bb"));
+        assert!(!dir.join("done.rle.rle").exists());
+        assert!(!dir.join("a.rle.rle").exists());
+
+        // Decoding only takes the encoded files, and a failure doesn't stop
+        // the others.
+        let config = Config::new(&args(&["rle-rs", "d", "-r", "-f", dir.to_str().unwrap()])).unwrap();
+
+        let mut tally = Tally::default();
+        run_dir(&dir, &dir, &config, &mut tally);
+
+        assert_eq!(tally.to_string(), "2 files processed, 1 error");
+        assert_eq!(fs::read(dir.join("a")).unwrap(), b"aa");
+        assert_eq!(fs::read(dir.join("sub").join("b")).unwrap(), b"bb");
+        assert!(!dir.join("a.dat").exists());
+    }
+
+    #[test]
+    fn run_dir_mirrors_subdirectories_under_output_dir() {
+        let dir = env::temp_dir().join(format!("rle-rs-{}-recursive-output-dir", process::id()));
+        let out = dir.with_extension("out");
+        let _ = fs::remove_dir_all(&dir);
+        let _ = fs::remove_dir_all(&out);
+        fs::create_dir_all(dir.join("one")).unwrap();
+        fs::create_dir_all(dir.join("two")).unwrap();
+        fs::write(dir.join("one").join("log"), b"11").unwrap();
+        fs::write(dir.join("two").join("log"), b"22").unwrap();
+
+        let config = Config::new(&args(&["rle-rs", "-r", "--output-dir", out.to_str().unwrap(), dir.to_str().unwrap()]));
+        let mut tally = Tally::default();
+        run_dir(&dir, &dir, &config.unwrap(), &mut tally);
+
+        assert_eq!((tally.processed, tally.failed), (2, 0));
+        assert_eq!(fs::read(out.join("one").join("log.rle")).unwrap(), encoded_file(Format::Byte, &[b'1', 2], b"11"));
+        assert_eq!(fs::read(out.join("two").join("log.rle")).unwrap(), encoded_file(Format::Byte, &[b'2', 2], b"22"));
+    }
+
+    #[test]
+    fn config_mode_options() {
+        assert_eq!(Config::new(&args(&["rle-rs", "file"])).unwrap().mode, Mode::Auto);
+        assert_eq!(Config::new(&args(&["rle-rs", "-e", "file"])).unwrap().mode, Mode::Encode);
+        assert_eq!(Config::new(&args(&["rle-rs", "--decode", "file"])).unwrap().mode, Mode::Decode);
+    }
+
+    #[test]
+    fn run_auto_mode_detects_encoded_files() {
+        let path = temp_file("auto", b"xxy");
+        run_args(&args(&["rle-rs", &path])).unwrap();
+        let encoded = format!("{}.rle", path);
+        fs::remove_file(&path).unwrap();
+
+        run_args(&args(&["rle-rs", &encoded])).unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"xxy");
+    }
+
+    #[test]
+    fn run_encode_option_encodes_encoded_files() {
+        let inner = encoded_file(Format::Stored, &[0; 100], &[0; 100]);
+        let encoded = temp_file("forced-encode.rle", &inner);
+        run_args(&args(&["rle-rs", "-e", &encoded])).unwrap();
+
+        assert_eq!(
+            fs::read(format!("{}.rle", encoded)).unwrap(),
+            encoded_file(Format::Byte, &rle_rs::encode(&inner), &inner)
+        );
+    }
+
+    #[test]
+    fn expand_glob_patterns() {
+        let dir = env::temp_dir().join(format!("rle-rs-{}-glob", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for name in &["b.log", "a.log", "c.txt", "*.txt"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        let dir = dir.to_str().unwrap();
+        let config = Config::new(&args(&["rle-rs", "file"])).unwrap();
+
+        assert_eq!(
+            expand(&format!("{}/*.log", dir), &config).unwrap(),
+            [format!("{}/a.log", dir), format!("{}/b.log", dir)]
+        );
+        assert_eq!(expand(&format!("{}/*.txt", dir), &config).unwrap(), [format!("{}/*.txt", dir)]);
+        assert_eq!(expand("-", &config).unwrap(), ["-"]);
+        assert_eq!(expand("missing", &config).unwrap(), ["missing"]);
+
+        let e = expand(&format!("{}/*.bin", dir), &config).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+
+        let config = Config::new(&args(&["rle-rs", "-o", "out", "file"])).unwrap();
+        assert!(expand(&format!("{}/*.log", dir), &config).is_err());
+    }
+
+    #[test]
+    fn config_stats_option() {
+        let config = Config::new(&args(&["rle-rs", "--stats", "file"])).unwrap();
+        assert!(config.stats && config.analyze);
+
+        let config = Config::new(&args(&["rle-rs", "-e", "--stats", "file"])).unwrap();
+        assert!(config.stats && !config.analyze);
+
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "d", "--stats", "file"])).err(),
+            Some(ConfigError::ConflictingOptions("--stats", "-d"))
+        );
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--stats", "--u16", "file"])).err(),
+            Some(ConfigError::ConflictingOptions("--stats", "--format"))
+        );
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--max-run", "9", "--stats", "file"])).err(),
+            Some(ConfigError::ConflictingOptions("--stats", "--max-run"))
+        );
+    }
+
+    #[test]
+    fn run_stats_writes_the_same_encoding() {
+        let path = temp_file("stats", b"xxxy");
+        run_args(&args(&["rle-rs", "-e", "--stats", &path])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.rle", path)).unwrap(), encoded_file(Format::Byte, &[b'x', 3, b'y', 1], b"xxxy"));
+    }
+
+    #[test]
+    fn run_stats_alone_writes_nothing() {
+        let path = temp_file("stats-only", b"xxxy");
+        run_args(&args(&["rle-rs", "--stats", &path])).unwrap();
+
+        assert!(!Path::new(&format!("{}.rle", path)).exists());
+    }
+
+    #[test]
+    fn analyze_reads_every_chunk() {
+        let bytes = [vec![5; CHUNK_SIZE + 10], vec![6; 3]].concat();
+
+        assert_eq!(analyze(&bytes[..]).unwrap(), Analysis::of(&bytes));
+    }
+
+    #[test]
+    fn run_decode_corrupt_file_is_an_error() {
+        let path = temp_file("corrupt", &encoded_file(Format::Byte, &[b'y', 2], b"xx"));
+        let e = run_args(&args(&["rle-rs", "d", &path])).unwrap_err();
+
+        assert!(matches!(e, RleError::Decode(DecodeError::ChecksumMismatch { .. })));
+    }
+
+    #[test]
+    fn config_no_checksum_option() {
+        assert!(!Config::new(&args(&["rle-rs", "--no-checksum", "file"])).unwrap().checksum);
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--no-checksum", "--lenient", "-d", "file"])).err(),
+            Some(ConfigError::ConflictingOptions("--lenient", "--no-checksum"))
+        );
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--append", "--no-checksum", "file"])).err(),
+            Some(ConfigError::ConflictingOptions("--append", "--no-checksum"))
+        );
+    }
+
+    #[test]
+    fn run_no_checksum_round_trip() {
+        let path = temp_file("no-checksum", b"xxxy");
+        run_args(&args(&["rle-rs", "--no-checksum", &path])).unwrap();
+        let encoded = format!("{}.rle", path);
+        let header = Header { checksum: false, ..Header::new(Format::Byte) };
+
+        assert_eq!(fs::read(&encoded).unwrap(), [&header.to_bytes()[..], &[b'x', 3, b'y', 1]].concat());
+
+        run_args(&args(&["rle-rs", "-d", &encoded])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", encoded)).unwrap(), b"xxxy");
+    }
+
+    #[test]
+    fn run_decode_no_checksum_checks_only_the_length() {
+        for (format, body) in [(Format::Byte, vec![b'y', 2]), (Format::PackBits, packbits::encode(b"yy"))] {
+            let wrong_crc = temp_file("wrong-crc", &encoded_file(format, &body, b"xx"));
+            let wrong_len = temp_file("wrong-len", &encoded_file(format, &body, b"yyy"));
+
+            assert!(matches!(
+                run_args(&args(&["rle-rs", "-t", &wrong_crc])),
+                Err(RleError::Decode(DecodeError::ChecksumMismatch { .. }))
+            ));
+
+            run_args(&args(&["rle-rs", "-d", "-f", "--no-checksum", &wrong_crc])).unwrap();
+
+            assert_eq!(fs::read(format!("{}.dat", wrong_crc)).unwrap(), b"yy");
+            assert!(matches!(
+                run_args(&args(&["rle-rs", "-t", "--no-checksum", &wrong_len])),
+                Err(RleError::Decode(DecodeError::LengthMismatch { expected: 3, actual: 2 }))
+            ));
+        }
+    }
+
+    #[test]
+    fn run_decode_legacy_file() {
+        let header = Header { version: 1, format: Format::Byte, checksum: false, blocks: false };
+        let path = temp_file("legacy", &[&header.to_bytes()[..], &[b'x', 2]].concat());
+        run_args(&args(&["rle-rs", "d", &path])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", path)).unwrap(), b"xx");
+    }
+
+    #[test]
+    fn members_hold_back_the_trailer() {
+        let header = Header::new(Format::Byte);
+        let file = encoded_file(Format::Byte, b"x\x02", b"xx");
+        let input = [&file[..], &file[..]].concat();
+        let mut reader = Members::new(&input[..]);
+
+        for _ in 0..2 {
+            assert_eq!(reader.next_header().unwrap(), header);
+            reader.split(header);
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data).unwrap();
+
+            assert_eq!(data, b"x\x02");
+            assert_eq!(reader.trailer().unwrap(), Trailer::of(b"xx"));
+        }
+
+        assert!(reader.at_end().unwrap());
+    }
+
+    #[test]
+    fn members_resume_after_a_trailer_within_the_data() {
+        // The data of the file holds what looks like a trailer recording the
+        // length of the data before it, which is only told apart once it
+        // doesn't check out.
+        let data = [&b"xy"[..], &Trailer { crc: 0, len: 2 }.to_bytes(), b"z"].concat();
+        let decoded = Format::Stored.decode(&data).unwrap();
+        let input = encoded_file(Format::Stored, &data, &decoded);
+        let mut reader = Members::new(&input[..]);
+        let header = reader.next_header().unwrap();
+        reader.split(header);
+        let mut read = Vec::new();
+        let mut stops = 0;
+
+        loop {
+            reader.read_to_end(&mut read).unwrap();
+            stops += 1;
+
+            if reader.trailer().and_then(|t| t.check(Trailer::of(&read))).is_ok() {
+                break;
+            }
+
+            assert!(reader.resume().unwrap());
+        }
+
+        assert_eq!(read, data);
+        assert_eq!(stops, 2);
+        assert!(reader.at_end().unwrap());
+    }
+
+    #[test]
+    fn run_decode_concatenated_files() {
+        let large: Vec<u8> = (0..CHUNK_SIZE + 5000).map(|i| (i / 1000) as u8).collect();
+        let bytes = [&b"xxxy"[..], &[1; 10], &large, b"yx"].concat();
+        let encoded = [
+            rle_rs::encode_with_header(b"xxxy", Format::Byte),
+            encoded_file(Format::PackBits, &packbits::encode(&[1; 10]), &[1; 10]),
+            rle_rs::encode_with_header(&large, Format::Byte),
+            rle_rs::encode_with_header(b"yx", Format::Byte),
+        ]
+        .concat();
+        let path = temp_file("concatenated", &encoded);
+
+        for options in &[&["d"][..], &["--mmap", "-f", "d"]] {
+            run_args(&args(&[&["rle-rs"][..], options, &[&path]].concat())).unwrap();
+
+            assert_eq!(fs::read(format!("{}.dat", path)).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn run_decode_trailing_data_needs_lenient() {
+        for &format in &[Format::Byte, Format::PackBits] {
+            let file = rle_rs::encode_with_header(b"xxxy", format);
+            let encoded = [&file[..], &file, b"\0\0padding"].concat();
+            let path = temp_file("trailing", &encoded);
+
+            assert!(matches!(
+                run_args(&args(&["rle-rs", "-f", "d", &path])),
+                Err(RleError::Decode(DecodeError::TrailingData { offset })) if offset == 2 * file.len() as u64
+            ));
+            assert!(!Path::new(&format!("{}.dat", path)).exists());
+
+            run_args(&args(&["rle-rs", "--lenient", "-f", "d", &path])).unwrap();
+
+            assert_eq!(fs::read(format!("{}.dat", path)).unwrap(), b"xxxyxxxy");
+            fs::remove_file(format!("{}.dat", path)).unwrap();
+        }
+    }
+
+    #[test]
+    fn run_decode_trailers_within_data() {
+        // 200 random bytes, their trailer and 50 more bytes, stored as they
+        // are, hold a trailer that checks out but doesn't end the file.
+        let random: Vec<u8> = (0..200u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+        let bytes = [&random[..], &Trailer::of(&random).to_bytes(), &random[..50]].concat();
+        let path = temp_file("trailer_within", &bytes);
+
+        run_args(&args(&["rle-rs", "-f", "-e", &path])).unwrap();
+        let encoded = fs::read(format!("{}.rle", path)).unwrap();
+        assert_eq!(Header::parse(&encoded).unwrap().0.format, Format::Stored);
+
+        for options in &[&["d"][..], &["--mmap", "d"], &["--lenient", "d"]] {
+            run_args(&args(&[&["rle-rs", "-f"][..], options, &[&format!("{}.rle", path)]].concat())).unwrap();
+
+            assert_eq!(fs::read(&path).unwrap(), bytes);
+        }
+
+        // Nor does it in a file decoded in memory, or followed by another,
+        // or with more after it than is held back in memory.
+        let long: Vec<u8> = (0..CHUNK_SIZE as u32 + 100).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+        let long = [&bytes[..], &long].concat();
+        let mut encoded = rle_rs::encode_with_header(&bytes, Format::PackBits);
+        encoded.extend(rle_rs::encode_with_header(&bytes, Format::Stored));
+        encoded.extend(rle_rs::encode_with_header(&long, Format::Stored));
+        let path = temp_file("trailer_within", &encoded);
+
+        run_args(&args(&["rle-rs", "-f", "d", &path])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", path)).unwrap(), [&bytes[..], &bytes, &long].concat());
+    }
+
+    #[test]
+    fn config_lenient_option() {
+        assert!(Config::new(&args(&["rle-rs", "--lenient", "d", "file"])).unwrap().lenient);
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--lenient", "--no-header", "d", "file"])).err(),
+            Some(ConfigError::ConflictingOptions("--lenient", "--no-header"))
+        );
+    }
+
+    #[test]
+    fn config_test_option() {
+        let config = Config::new(&args(&["rle-rs", "-t", "file"])).unwrap();
+
+        assert!(config.test);
+        assert_eq!(config.mode, Mode::Decode);
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--test", "--rm", "file"])).err(),
+            Some(ConfigError::ConflictingOptions("-t", "--rm"))
+        );
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "-e", "-t", "file"])).err(),
+            Some(ConfigError::ConflictingOptions("-t", "-e"))
+        );
+    }
+
+    #[test]
+    fn run_test_writes_nothing() {
+        let good = temp_file("test-good.rle", &encoded_file(Format::Byte, &[b'x', 2], b"xx"));
+        let bad = temp_file("test-bad.rle", &encoded_file(Format::Byte, &[b'x', 3], b"xx"));
+
+        run_args(&args(&["rle-rs", "-t", &good])).unwrap();
+        assert!(run_args(&args(&["rle-rs", "-t", &bad])).is_err());
+
+        for path in &[good, bad] {
+            assert!(!Path::new(path.strip_suffix(".rle").unwrap()).exists());
+            assert!(!Path::new(&format!("{}.dat", path)).exists());
+        }
+    }
+
+    #[test]
+    fn destination_of_dash_and_directory_outputs() {
+        let config = Config::new(&args(&["rle-rs", "-o", "-", "file"])).unwrap();
+        assert_eq!(destination("file", Path::new(""), true, &config).unwrap(), None);
+
+        let temp = env::temp_dir();
+        let config = Config::new(&args(&["rle-rs", "-o", temp.to_str().unwrap(), "file"])).unwrap();
+        assert_eq!(destination("dir/file", Path::new(""), true, &config).unwrap(), Some(temp.join("file.rle")));
+    }
+
+    #[test]
+    fn run_verify_and_rm() {
+        let path = temp_file("verify", &[b"abc", &[0; 1000][..]].concat());
+        run_args(&args(&["rle-rs", "--verify", "--rm", &path])).unwrap();
+
+        assert!(!Path::new(&path).exists());
+        assert!(Path::new(&format!("{}.rle", path)).exists());
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--verify", "-c", "file"])).err(),
+            Some(ConfigError::ConflictingOptions("--verify", "-c"))
+        );
+    }
+
+    #[test]
+    fn compare_reports_the_first_difference() {
+        let mut compare = Compare::new(&b"abcdef"[..]);
+        compare.write_all(b"abc").unwrap();
+        let e = compare.write_all(b"dxf").unwrap_err();
+
+        assert!(e.to_string().ends_with("offset 4: the input has 0x65, the decoded output 0x78"));
+
+        let mut compare = Compare::new(&b"abc"[..]);
+        compare.write_all(b"ab").unwrap();
+        let e = compare.finish().unwrap_err();
+
+        assert!(e.to_string().ends_with("offset 2: the input has 0x63, the decoded output the end"));
+
+        let mut compare = Compare::new(&b"ab"[..]);
+        let e = compare.write_all(b"abc").unwrap_err();
+
+        assert!(e.to_string().ends_with("offset 2: the input has the end, the decoded output 0x63"));
+    }
+
+    #[test]
+    fn exit_status_of_each_error() {
+        let mut compare = Compare::new(&b"ab"[..]);
+        let mismatch = RleError::from(compare.write_all(b"ax").unwrap_err());
+        let checksum = DecodeError::ChecksumMismatch { expected: 1, actual: 2 };
+
+        assert_eq!(exit_status(&mismatch), EXIT_INTEGRITY);
+        assert_eq!(exit_status(&checksum.into()), EXIT_INTEGRITY);
+        assert_eq!(exit_status(&DecodeError::InvalidMagic.into()), EXIT_DATA);
+        assert_eq!(exit_status(&ConfigError::NoFilepath.into()), EXIT_USAGE);
+        assert_eq!(exit_status(&io::Error::other("disk on fire").into()), EXIT_IO);
+        assert_eq!(exit_status(&RleError::at("out")(io::Error::other("disk on fire"))), EXIT_IO);
+
+        let invalid = |e: DecodeError| RleError::at("in")(io::Error::new(io::ErrorKind::InvalidData, e));
+        assert_eq!(exit_status(&invalid(DecodeError::LengthMismatch { expected: 1, actual: 2 })), EXIT_INTEGRITY);
+        assert_eq!(exit_status(&invalid(DecodeError::InvalidMagic)), EXIT_DATA);
+    }
+
+    #[test]
+    fn run_dry_run_writes_nothing() {
+        let path = temp_file("dry-run", b"xxy");
+        let dir = env::temp_dir().join(format!("rle-rs-{}-dry-run-dir", process::id()));
+        let args = args(&["rle-rs", "-n", "--rm", "--output-dir", dir.to_str().unwrap(), &path]);
+        run_args(&args).unwrap();
+
+        assert!(Path::new(&path).exists());
+        assert!(!Path::new(&format!("{}.rle", path)).exists());
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn run_stores_data_that_does_not_shrink() {
+        let path = temp_file("stored", b"abc");
+        run_args(&args(&["rle-rs", &path])).unwrap();
+        let encoded = format!("{}.rle", path);
+
+        assert_eq!(fs::read(&encoded).unwrap(), encoded_file(Format::Stored, b"abc", b"abc"));
+
+        run_args(&args(&["rle-rs", "d", &encoded])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", encoded)).unwrap(), b"abc");
+
+        // Files larger than the encoding set aside in memory are stored too.
+        let random: Vec<u8> = (0..3 * CHUNK_SIZE as u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+        let path = temp_file("stored_large", &random);
+
+        for options in &[&[][..], &["--bits"]] {
+            run_args(&args(&[&["rle-rs", "-f"][..], options, &[&path]].concat())).unwrap();
+
+            assert!(fs::read(format!("{}.rle", path)).unwrap() == rle_rs::encode_with_header(&random, Format::Stored));
+        }
+    }
+
+    #[test]
+    fn config_format_option() {
+        let config = Config::new(&args(&["rle-rs", "--format", "packbits", "file"])).unwrap();
+        assert_eq!(config.format, Format::PackBits);
+
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--format", "lzw", "file"])).err(),
+            Some(ConfigError::InvalidValue("--format".to_owned(), "lzw".to_owned()))
+        );
+    }
+
+    #[test]
+    fn config_counts_option() {
+        let config = Config::new(&args(&["rle-rs", "--counts", "16", "file"])).unwrap();
+        assert_eq!(config.format, Format::U16);
+
+        let config = Config::new(&args(&["rle-rs", "--u16", "--counts", "8", "file"])).unwrap();
+        assert_eq!(config.format, Format::Byte);
+
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--counts", "32", "file"])).err(),
+            Some(ConfigError::InvalidValue("--counts".to_owned(), "32".to_owned()))
+        );
+    }
+
+    #[test]
+    fn run_unit_round_trip() {
+        let bytes = [[0x10, 0x27].repeat(500), vec![1, 2, 3]].concat();
+        let path = temp_file("unit", &bytes);
+        run_args(&args(&["rle-rs", "--unit", "2", &path])).unwrap();
+        let encoded = format!("{}.rle", path);
+
+        assert_eq!(fs::read(&encoded).unwrap(), encoded_file(Format::Unit2, &rle_rs::units::encode(&bytes, 2), &bytes));
+
+        // The unit size comes from the header.
+        run_args(&args(&["rle-rs", "d", &encoded])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", encoded)).unwrap(), bytes);
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--unit", "3", "file"])).err(),
+            Some(ConfigError::InvalidValue("--unit".to_owned(), "3".to_owned()))
+        );
+    }
+
+    #[test]
+    fn run_bits_round_trip() {
+        let bytes = [vec![0xff; 300], vec![0; 300]].concat();
+        let path = temp_file("bits", &bytes);
+        run_args(&args(&["rle-rs", "--bits", &path])).unwrap();
+        let encoded = format!("{}.rle", path);
+
+        assert_eq!(fs::read(&encoded).unwrap(), encoded_file(Format::Bits, &rle_rs::bits::encode(&bytes), &bytes));
+
+        run_args(&args(&["rle-rs", "d", &encoded])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", encoded)).unwrap(), bytes);
+    }
+
+    #[test]
+    fn run_pcx_round_trip() {
+        let bytes = [vec![0xff; 100], vec![1, 2, 3]].concat();
+        let path = temp_file("pcx", &bytes);
+        run_args(&args(&["rle-rs", "--pcx", &path])).unwrap();
+        let encoded = format!("{}.rle", path);
+
+        assert_eq!(fs::read(&encoded).unwrap(), encoded_file(Format::Pcx, &rle_rs::pcx::encode(&bytes), &bytes));
+
+        run_args(&args(&["rle-rs", "d", &encoded])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", encoded)).unwrap(), bytes);
+    }
+
+    #[test]
+    fn run_delta_round_trip() {
+        let bytes: Vec<u8> = (0..1000).map(|i| (i * 3) as u8).collect();
+        let path = temp_file("delta", &bytes);
+        run_args(&args(&["rle-rs", "--delta", &path])).unwrap();
+        let encoded = format!("{}.rle", path);
+
+        assert_eq!(fs::read(&encoded).unwrap(), encoded_file(Format::Delta, &rle_rs::encode_delta_rle(&bytes), &bytes));
+
+        run_args(&args(&["rle-rs", "d", &encoded])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", encoded)).unwrap(), bytes);
+    }
+
+    #[test]
+    fn run_parallel_round_trip() {
+        let bytes: Vec<u8> = (0..10_000).map(|i| (i / 300) as u8).collect();
+        let path = temp_file("parallel", &bytes);
+        run_args(&args(&["rle-rs", "--chunk-size", "1000", &path])).unwrap();
+        let encoded = format!("{}.rle", path);
+
+        assert_eq!(fs::read(&encoded).unwrap(), rle_rs::encode_parallel(&bytes, 1000));
+
+        run_args(&args(&["rle-rs", "d", "--parallel", "--mmap", &encoded])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", encoded)).unwrap(), bytes);
+    }
+
+    #[test]
+    fn run_text_round_trip() {
+        let path = temp_file("text", b"AAAAAAAAAAABCCCC");
+        run_args(&args(&["rle-rs", "--text", &path])).unwrap();
+        let encoded = format!("{}.rle", path);
+
+        assert_eq!(fs::read(&encoded).unwrap(), b"A11B1C4\n");
+
+        run_args(&args(&["rle-rs", "d", "--text", &encoded])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", encoded)).unwrap(), b"AAAAAAAAAAABCCCC");
+    }
+
+    #[test]
+    fn run_pairs_round_trip() {
+        let path = temp_file("pairs", b"AAA BB");
+        run_args(&args(&["rle-rs", "--pairs", &path])).unwrap();
+        let encoded = format!("{}.rle", path);
+
+        assert_eq!(fs::read_to_string(&encoded).unwrap(), "0x41×3 0x20×1 0x42×2\n");
+
+        run_args(&args(&["rle-rs", "-d", "--pairs", &encoded])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", encoded)).unwrap(), b"AAA BB");
+
+        let pairs = temp_file("pairs-invalid.rle", "0x41×3 0x20×300\n".as_bytes());
+        let e = run_args(&args(&["rle-rs", "-d", "--pairs", &pairs])).unwrap_err();
+
+        assert!(matches!(e, RleError::Parse(rle_rs::ParseError::InvalidCount(ref count)) if count == "300"), "{}", e);
+        assert_eq!(
+            Config::new(&args(&["rle-rs", "--pairs", "--u16", "file"])).err(),
+            Some(ConfigError::ConflictingOptions("--pairs", "--format"))
+        );
+    }
+
+    #[test]
+    fn run_hex_round_trip() {
+        let bytes = [vec![b'x'; 100], b"y".to_vec()].concat();
+        let path = temp_file("hex", &bytes);
+        run_args(&args(&["rle-rs", "--hex", "--format", "packbits", &path])).unwrap();
+        let encoded = format!("{}.rle", path);
+        let text = fs::read(&encoded).unwrap();
+
+        assert!(text.split(|&c| c == b'\n').all(|line| line.len() <= 76));
+        assert_eq!(
+            hex::decode(&text).unwrap(),
+            encoded_file(Format::PackBits, &rle_rs::packbits::encode(&bytes), &bytes)
+        );
+
+        // The header is recognized in hex too, so no d is needed.
+        run_args(&args(&["rle-rs", "--hex", &encoded])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", encoded)).unwrap(), bytes);
+    }
+
+    #[test]
+    fn run_hex_invalid_input() {
+        let path = temp_file("hex-invalid.rle", b"524c45 01 xy");
+        let e = run_args(&args(&["rle-rs", "--hex", "d", &path])).unwrap_err();
+
+        assert!(matches!(e, RleError::Decode(DecodeError::InvalidHex { at_byte: 10, .. })), "{}", e);
+    }
+
+    #[test]
+    fn run_base64_round_trip() {
+        let bytes = [vec![b'x'; 1000], b"yz".to_vec()].concat();
+        let path = temp_file("base64", &bytes);
+        run_args(&args(&["rle-rs", "--base64", &path])).unwrap();
+        let encoded = format!("{}.rle", path);
+        let text = fs::read(&encoded).unwrap();
+
+        assert!(text.split(|&c| c == b'\n').all(|line| line.len() <= 76));
+        assert_eq!(base64::decode(&text).unwrap(), encoded_file(Format::Byte, &rle_rs::encode(&bytes), &bytes));
+
+        run_args(&args(&["rle-rs", "--base64", &encoded])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", encoded)).unwrap(), bytes);
+    }
+
+    #[test]
+    fn run_base64_truncated_input() {
+        let text = base64::encode(&encoded_file(Format::Byte, &[b'a', 3], b"aaa"));
+        let path = temp_file("base64-truncated.rle", &text.as_bytes()[..text.len() - 3]);
+        let e = run_args(&args(&["rle-rs", "--base64", "d", &path])).unwrap_err();
+
+        assert!(matches!(e, RleError::Decode(DecodeError::InvalidBase64 { .. })), "{}", e);
+    }
+
+    #[test]
+    fn run_escape_round_trip() {
+        let bytes = b"plain text with a looooooong run".to_vec();
+        let path = temp_file("escape", &bytes);
+        run_args(&args(&["rle-rs", "--format", "escape", &path])).unwrap();
+        let encoded = format!("{}.rle", path);
+
+        assert_eq!(fs::read(&encoded).unwrap(), encoded_file(Format::Escape, &rle_rs::escape::encode(&bytes), &bytes));
+
+        run_args(&args(&["rle-rs", "d", &encoded])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", encoded)).unwrap(), bytes);
+    }
+
+    #[test]
+    fn run_file_larger_than_a_chunk() {
+        // Runs of 1000 bytes, so that one crosses the end of the first chunk.
+        let bytes: Vec<u8> = (0..CHUNK_SIZE + 5000).map(|i| (i / 1000) as u8).collect();
+        let path = temp_file("large", &bytes);
+        run_args(&args(&["rle-rs", &path])).unwrap();
+        let encoded = format!("{}.rle", path);
+
+        assert_eq!(fs::read(&encoded).unwrap(), rle_rs::encode_with_header(&bytes, Format::Byte));
+
+        run_args(&args(&["rle-rs", "d", &encoded])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", encoded)).unwrap(), bytes);
+    }
+
+    #[test]
+    fn run_threads_round_trip() {
+        let bytes: Vec<u8> = (0..3 * blocks::BLOCK_SIZE + 5000).map(|i| (i / 1000) as u8).collect();
+        let path = temp_file("threads", &bytes);
+        let header = Header { blocks: true, ..Header::new(Format::Varint) };
+        let expected = [
+            &header.to_bytes()[..],
+            &blocks::encode(&bytes, Format::Varint, 1),
+            &Trailer::of(&bytes).to_bytes(),
+        ]
+        .concat();
+
+        for threads in &["1", "2", "4"] {
+            let output = format!("{}.{}.rle", path, threads);
+            run_args(&args(&["rle-rs", "--threads", threads, "--format", "varint", "-o", &output, &path]))
+                .unwrap();
+
+            assert_eq!(fs::read(&output).unwrap(), expected);
+        }
+
+        let encoded = format!("{}.4.rle", path);
+        run_args(&args(&["rle-rs", "d", &encoded])).unwrap();
+        assert_eq!(fs::read(format!("{}.4", path)).unwrap(), bytes);
+
+        for threads in &["1", "3"] {
+            run_args(&args(&["rle-rs", "--threads", threads, "d", "-f", &encoded])).unwrap();
+            assert_eq!(fs::read(format!("{}.4", path)).unwrap(), bytes);
+
+            run_args(&args(&["rle-rs", "--threads", threads, "--mmap", "d", "-f", &encoded])).unwrap();
+            assert_eq!(fs::read(format!("{}.4", path)).unwrap(), bytes);
+        }
+
+        // Block 1 loses a run.
+        let mut corrupt = expected.clone();
+        let second_frame = 7 + 8 + u32::from_le_bytes([corrupt[11], corrupt[12], corrupt[13], corrupt[14]]) as usize;
+        corrupt[second_frame + 8 + 1] = 1;
+        let corrupt_path = temp_file("threads_corrupt.rle", &corrupt);
+        let e = run_args(&args(&["rle-rs", "--threads", "3", "d", &corrupt_path])).unwrap_err();
+        assert!(e.to_string().starts_with("block 1: "), "{}", e);
+
+        // Mapped files are split the same way.
+        let mapped = format!("{}.mmap.rle", path);
+        run_args(&args(&["rle-rs", "--threads", "2", "--format", "varint", "--mmap", "-o", &mapped, &path]))
+            .unwrap();
+        assert_eq!(fs::read(&mapped).unwrap(), expected);
+    }
+
+    #[test]
+    fn run_mmap_round_trip() {
+        let bytes: Vec<u8> = (0..CHUNK_SIZE + 5000).map(|i| (i / 1000) as u8).collect();
+        let path = temp_file("mmap", &bytes);
+        run_args(&args(&["rle-rs", "--mmap", &path])).unwrap();
+        let encoded = format!("{}.rle", path);
+
+        assert_eq!(fs::read(&encoded).unwrap(), rle_rs::encode_with_header(&bytes, Format::Byte));
+
+        run_args(&args(&["rle-rs", "--mmap", "d", &encoded])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", encoded)).unwrap(), bytes);
+    }
+
+    #[test]
+    fn run_mmap_stores_large_files_that_dont_shrink() {
+        let bytes: Vec<u8> = (0..CHUNK_SIZE + 5000).map(|i| i as u8).collect();
+        let path = temp_file("mmap_stored", &bytes);
+        run_args(&args(&["rle-rs", "--mmap", &path])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.rle", path)).unwrap(), encoded_file(Format::Stored, &bytes, &bytes));
+    }
+
+    #[test]
+    fn run_mmap_empty_file_falls_back_to_reading() {
+        let path = temp_file("mmap_empty", b"");
+        run_args(&args(&["rle-rs", "--mmap", &path])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.rle", path)).unwrap(), encoded_file(Format::Byte, b"", b""));
+    }
+
+    #[test]
+    fn run_varint_round_trip() {
+        let bytes = [vec![0; 100_000], b"data".to_vec(), vec![0; 100_000]].concat();
+        let path = temp_file("varint", &bytes);
+        run_args(&args(&["rle-rs", "--format", "varint", &path])).unwrap();
+        let encoded = format!("{}.rle", path);
+
+        assert_eq!(fs::read(&encoded).unwrap(), encoded_file(Format::Varint, &varint::encode(&bytes), &bytes));
+
+        run_args(&args(&["rle-rs", "d", &encoded])).unwrap();
+
+        assert_eq!(fs::read(format!("{}.dat", encoded)).unwrap(), bytes);
     }
 }
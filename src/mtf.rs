@@ -0,0 +1,183 @@
+//! The move-to-front transform, to turn data that keeps coming back to the
+//! same few bytes into runs before it is run-length encoded.
+//!
+//! Each byte is replaced by its position in a list of all 256 byte values,
+//! which is then moved to the front of the list. Bytes that were seen
+//! recently thus get small positions, and a byte repeated right away gets 0.
+//! Alternating patterns such as `ababab` become runs of 1, but on its own the
+//! transform only shortens the encoding of text a little; it pays off on data
+//! where bytes are already grouped, such as the output of a Burrows-Wheeler
+//! transform.
+
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use crate::RleError;
+
+/// Return the list of byte values both directions of the transform start
+/// with, in ascending order.
+fn initial_list() -> [u8; 256] {
+    let mut list = [0; 256];
+
+    for (position, byte) in list.iter_mut().enumerate() {
+        *byte = position as u8;
+    }
+
+    list
+}
+
+/// Move the byte at `position` of `list` to its front, shifting the ones
+/// before it back by one.
+///
+/// * `list` - The list of byte values.
+/// * `position` - The position of the byte to move.
+fn move_to_front(list: &mut [u8; 256], position: usize) {
+    let byte = list[position];
+    list.copy_within(..position, 1);
+    list[0] = byte;
+}
+
+/// Read a byte slice and return the position of each byte in the list of
+/// byte values, moving it to the front of the list after each.
+///
+/// * `bytes` - The bytes to be transformed.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(rle_rs::mtf_encode(b"abab"), [97, 98, 1, 1]);
+/// ```
+pub fn mtf_encode(bytes: &[u8]) -> Vec<u8> {
+    let mut list = initial_list();
+
+    bytes
+        .iter()
+        .map(|&byte| {
+            // Every value is in the list, so the search always succeeds.
+            let position = list.iter().position(|&b| b == byte).unwrap_or_default();
+            move_to_front(&mut list, position);
+
+            position as u8
+        })
+        .collect()
+}
+
+/// Read the positions returned by [`mtf_encode`] and return the bytes they
+/// were taken from, keeping the list of byte values the same way.
+///
+/// * `bytes` - The positions to be transformed back.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(rle_rs::mtf_decode(&[97, 98, 1, 1]), b"abab");
+/// ```
+pub fn mtf_decode(bytes: &[u8]) -> Vec<u8> {
+    let mut list = initial_list();
+
+    bytes
+        .iter()
+        .map(|&position| {
+            let byte = list[usize::from(position)];
+            move_to_front(&mut list, usize::from(position));
+
+            byte
+        })
+        .collect()
+}
+
+/// Read a byte slice and return the run-length encoding of its move-to-front
+/// transform.
+///
+/// * `bytes` - The bytes to be encoded.
+///
+/// # Examples
+///
+/// ```
+/// let alternating = b"ab".repeat(50);
+///
+/// assert_eq!(rle_rs::encode_mtf_rle(&alternating), [97, 1, 98, 1, 1, 98]);
+/// ```
+pub fn encode_mtf_rle(bytes: &[u8]) -> Vec<u8> {
+    crate::encode(&mtf_encode(bytes))
+}
+
+/// Read the run-length encoding of a move-to-front transform and return the
+/// bytes it was taken from.
+///
+/// * `bytes` - The bytes to be decoded, as produced by [`encode_mtf_rle`].
+///
+/// # Errors
+///
+/// Returns [`RleError::Decode`] for the same reasons as
+/// [`decode`](crate::decode).
+///
+/// # Examples
+///
+/// ```
+/// let alternating = b"ab".repeat(50);
+///
+/// assert_eq!(rle_rs::decode_rle_mtf(&[97, 1, 98, 1, 1, 98]).unwrap(), alternating);
+/// ```
+#[cfg(feature = "std")]
+pub fn decode_rle_mtf(bytes: &[u8]) -> Result<Vec<u8>, RleError> {
+    Ok(mtf_decode(&crate::decode(bytes)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "std")]
+    use crate::DecodeError;
+
+    /// English text, with the repeated words and letters of prose.
+    #[cfg(feature = "std")]
+    const TEXT: &[u8] = b"It was the best of times, it was the worst of times, it was the age of \
+        wisdom, it was the age of foolishness, it was the epoch of belief, it was the epoch of \
+        incredulity, it was the season of Light, it was the season of Darkness, it was the spring \
+        of hope, it was the winter of despair.";
+
+    #[test]
+    fn repeated_bytes_become_zeros() {
+        assert_eq!(mtf_encode(b"aaab"), [97, 0, 0, 98]);
+        assert_eq!(mtf_encode(&[0, 255, 255, 0]), [0, 255, 0, 1]);
+    }
+
+    #[test]
+    fn round_trip_every_byte() {
+        let bytes: Vec<u8> = (0..=255).chain((0..=255).rev()).chain([7, 7, 200, 7]).collect();
+
+        assert_eq!(mtf_decode(&mtf_encode(&bytes)), bytes);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn english_text_encodes_smaller() {
+        let encoded = encode_mtf_rle(TEXT);
+
+        // The transform turns some of the text into runs of small positions,
+        // but neither encoding shrinks it.
+        assert!(encoded.len() < crate::encode(TEXT).len());
+        assert!(encoded.len() > TEXT.len());
+        assert_eq!(decode_rle_mtf(&encoded).unwrap(), TEXT);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn alternating_bytes_encode_much_smaller() {
+        let bytes = b"xy".repeat(1000);
+        let encoded = encode_mtf_rle(&bytes);
+
+        assert!(encoded.len() * 100 < crate::encode(&bytes).len());
+        assert_eq!(decode_rle_mtf(&encoded).unwrap(), bytes);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn decode_malformed_input() {
+        assert!(matches!(
+            decode_rle_mtf(&[1]),
+            Err(RleError::Decode(DecodeError::TruncatedInput { at_byte: 0 }))
+        ));
+    }
+}
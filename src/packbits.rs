@@ -0,0 +1,184 @@
+//! The PackBits run-length scheme, as used by TIFF and many other image
+//! formats.
+//!
+//! Encoded data is a sequence of packets, each starting with a signed header
+//! byte `n`:
+//!
+//! * `0..=127` - The next `n + 1` bytes are copied verbatim (a literal
+//!   packet).
+//! * `-127..=-1` - The next byte is repeated `-n + 1` times (a run packet).
+//! * `-128` - No operation; the header is skipped.
+//!
+//! Unlike the byte-pair format, sequences of unique bytes cost a single extra
+//! byte per 128 bytes instead of doubling in size.
+
+use alloc::vec::Vec;
+
+use crate::DecodeError;
+
+/// The longest literal or run a single packet can hold.
+const MAX_PACKET: usize = 128;
+
+/// Read a byte slice and return its PackBits encoding.
+///
+/// * `bytes` - The bytes to be encoded.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::packbits;
+///
+/// assert_eq!(packbits::encode(b"aaaabc"), [0xfd, b'a', 0x01, b'b', b'c']);
+/// ```
+pub fn encode(bytes: &[u8]) -> Vec<u8> {
+    let mut encoding = Vec::new();
+    // Where the pending literal bytes start in `bytes`.
+    let mut literal_start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let run = bytes[i..]
+            .iter()
+            .take(MAX_PACKET)
+            .take_while(|&&b| b == bytes[i])
+            .count();
+
+        // A run of two only pays off when it doesn't interrupt a literal,
+        // since it would cost a header byte for the literal that follows.
+        if run >= 3 || (run == 2 && literal_start == i) {
+            push_literal(&mut encoding, &bytes[literal_start..i]);
+            encoding.extend(&[(1 - run as i16) as u8, bytes[i]]);
+            i += run;
+            literal_start = i;
+        } else {
+            i += run;
+        }
+    }
+
+    push_literal(&mut encoding, &bytes[literal_start..]);
+
+    encoding
+}
+
+/// Append `literal` to `encoding` as a series of literal packets.
+fn push_literal(encoding: &mut Vec<u8>, literal: &[u8]) {
+    for chunk in literal.chunks(MAX_PACKET) {
+        encoding.push(chunk.len() as u8 - 1);
+        encoding.extend(chunk);
+    }
+}
+
+/// Read a PackBits encoding and return its decoded contents.
+///
+/// * `bytes` - The bytes to be decoded.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::TruncatedInput`] if a packet is missing some of
+/// the bytes its header calls for.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::packbits;
+///
+/// assert_eq!(packbits::decode(&[0xfd, b'a', 0x01, b'b', b'c']).unwrap(), b"aaaabc");
+/// assert!(packbits::decode(&[0x01, b'b']).is_err());
+/// ```
+pub fn decode(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let mut decoding = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let header = bytes[i] as i8;
+        let truncated = DecodeError::TruncatedInput { at_byte: i };
+
+        match header {
+            0..=127 => {
+                let len = header as usize + 1;
+                let literal = bytes.get(i + 1..i + 1 + len).ok_or(truncated)?;
+                decoding.extend(literal);
+                i += 1 + len;
+            }
+            -127..=-1 => {
+                let byte = *bytes.get(i + 1).ok_or(truncated)?;
+                decoding.extend(core::iter::repeat_n(byte, (1 - header as isize) as usize));
+                i += 2;
+            }
+            -128 => i += 1,
+        }
+    }
+
+    Ok(decoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// The example from the PackBits section of the TIFF 6.0 specification.
+    const TIFF_UNPACKED: [u8; 24] = [
+        0xaa, 0xaa, 0xaa, 0x80, 0x00, 0x2a, 0xaa, 0xaa, 0xaa, 0xaa, 0x80, 0x00,
+        0x2a, 0x22, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa,
+    ];
+    const TIFF_PACKED: [u8; 15] = [
+        0xfe, 0xaa, 0x02, 0x80, 0x00, 0x2a, 0xfd, 0xaa, 0x03, 0x80, 0x00, 0x2a,
+        0x22, 0xf7, 0xaa,
+    ];
+
+    #[test]
+    fn encode_tiff_example() {
+        assert_eq!(encode(&TIFF_UNPACKED), TIFF_PACKED);
+    }
+
+    #[test]
+    fn decode_tiff_example() {
+        assert_eq!(decode(&TIFF_PACKED), Ok(TIFF_UNPACKED.to_vec()));
+    }
+
+    #[test]
+    fn decode_skips_no_op_headers() {
+        assert_eq!(decode(&[0x80, 0xff, b'x', 0x80]), Ok(b"xx".to_vec()));
+    }
+
+    #[test]
+    fn decode_truncated_literal() {
+        assert_eq!(
+            decode(&[0xff, b'x', 0x02, b'a', b'b']),
+            Err(DecodeError::TruncatedInput { at_byte: 2 })
+        );
+    }
+
+    #[test]
+    fn unique_bytes_barely_expand() {
+        let bytes: Vec<u8> = (0..=255).collect();
+
+        assert_eq!(encode(&bytes).len(), bytes.len() + 2);
+    }
+
+    #[test]
+    fn round_trip_long_runs_and_literals() {
+        let bytes: Vec<u8> = [vec![1; 300], (0..200).collect(), vec![2, 2, 3, 3, 3], vec![]].concat();
+
+        assert_eq!(decode(&encode(&bytes)), Ok(bytes));
+        assert_eq!(encode(&[]), []);
+    }
+
+    #[test]
+    fn packets_hold_at_most_128_bytes() {
+        let literal: Vec<u8> = (0..129).map(|n| n as u8).collect();
+        let encoded = encode(&literal);
+
+        assert_eq!(encoded[0], 127);
+        assert_eq!(encoded[129..], [0, 128]);
+        assert_eq!(encode(&[5; 129]), [0x81, 5, 0, 5]);
+        assert_eq!(decode(&[0x81, 5]), Ok(vec![5; 128]));
+    }
+
+    #[test]
+    fn two_byte_run_between_literals_stays_literal() {
+        assert_eq!(encode(b"abbc"), [3, b'a', b'b', b'b', b'c']);
+        assert_eq!(encode(b"bbc"), [0xff, b'b', 0, b'c']);
+    }
+}
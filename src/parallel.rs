@@ -0,0 +1,139 @@
+//! Encoding large inputs a chunk at a time on every CPU, with rayon.
+//!
+//! The input is cut into chunks of the same length, the last one possibly
+//! shorter, and each chunk is encoded on its own and written after the
+//! length of its encoding, in the layout of
+//! [`encode_blocks`](crate::encode_blocks), so that
+//! [`decode_block`](crate::decode_block) can decode any one of them.
+
+use rayon::prelude::*;
+
+use crate::chunks::{self, MAX_BLOCK_SIZE, PREFIX_LEN};
+use crate::DecodeError;
+
+/// Encode `bytes` a chunk of `chunk_size` bytes at a time, on as many
+/// threads as there are CPUs, and return the length-prefixed encodings of the
+/// chunks one after another.
+///
+/// * `bytes` - The bytes to be encoded.
+/// * `chunk_size` - The length of every chunk but the last. It is kept
+///   between 1 and `u32::MAX / 2`, so that encodings fit their prefix.
+///
+/// # Examples
+///
+/// ```
+/// let encoded = rle_rs::encode_parallel(b"aaab", 2);
+///
+/// assert_eq!(encoded, [2, 0, 0, 0, b'a', 2, 4, 0, 0, 0, b'a', 1, b'b', 1]);
+/// assert_eq!(rle_rs::decode_parallel(&encoded).unwrap(), b"aaab");
+/// ```
+pub fn encode_parallel(bytes: &[u8], chunk_size: usize) -> Vec<u8> {
+    let chunk_size = chunk_size.clamp(1, MAX_BLOCK_SIZE);
+    let encodings: Vec<Vec<u8>> = bytes.par_chunks(chunk_size).map(crate::encode).collect();
+    let mut encoding =
+        Vec::with_capacity(encodings.iter().map(|e| PREFIX_LEN + e.len()).sum());
+
+    for chunk in encodings {
+        encoding.extend(&(chunk.len() as u32).to_le_bytes());
+        encoding.extend(chunk);
+    }
+
+    encoding
+}
+
+/// Decode the length-prefixed chunks written by [`encode_parallel`], on as
+/// many threads as there are CPUs.
+///
+/// * `bytes` - The bytes to be decoded.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::TruncatedInput`] if `bytes` ends within a prefix or
+/// before the end of the chunk it announces, and
+/// [`DecodeError::InvalidBlock`] with the index of the first chunk that isn't
+/// a valid encoding.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::DecodeError;
+///
+/// assert_eq!(rle_rs::decode_parallel(&[2, 0, 0, 0, b'a', 3]).unwrap(), b"aaa");
+/// assert_eq!(
+///     rle_rs::decode_parallel(&[2, 0, 0, 0, b'a']),
+///     Err(DecodeError::TruncatedInput { at_byte: 5 })
+/// );
+/// ```
+pub fn decode_parallel(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let chunks = split_chunks(bytes)?;
+    let decodings: Vec<Vec<u8>> = chunks
+        .par_iter()
+        .enumerate()
+        .map(|(chunk, encoded)| {
+            crate::decode(encoded)
+                .map_err(|error| DecodeError::InvalidBlock { block: chunk, error: Box::new(error) })
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(decodings.concat())
+}
+
+/// Return the encoded chunks of `bytes`, without their prefixes.
+fn split_chunks(bytes: &[u8]) -> Result<Vec<&[u8]>, DecodeError> {
+    let mut chunks = Vec::new();
+    let mut at = 0;
+
+    while at < bytes.len() {
+        let (chunk, next) = chunks::next_block(bytes, at)?;
+        chunks.push(chunk);
+        at = next;
+    }
+
+    Ok(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_splits_runs_across_chunks() {
+        let bytes: Vec<u8> = (0..10_000).map(|i| (i / 300) as u8).collect();
+        let encoded = encode_parallel(&bytes, 1000);
+
+        assert_eq!(split_chunks(&encoded).unwrap().len(), 10);
+        assert_eq!(decode_parallel(&encoded).unwrap(), bytes);
+    }
+
+    #[test]
+    fn empty_input() {
+        assert!(encode_parallel(&[], 16).is_empty());
+        assert!(decode_parallel(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn zero_chunk_size_is_treated_as_one() {
+        assert_eq!(encode_parallel(b"aa", 0), [2, 0, 0, 0, b'a', 1, 2, 0, 0, 0, b'a', 1]);
+    }
+
+    #[test]
+    fn decode_reports_the_invalid_chunk() {
+        let encoded = [2, 0, 0, 0, b'a', 3, 1, 0, 0, 0, b'b'];
+
+        assert_eq!(
+            decode_parallel(&encoded),
+            Err(DecodeError::InvalidBlock {
+                block: 1,
+                error: Box::new(DecodeError::TruncatedInput { at_byte: 0 }),
+            })
+        );
+    }
+
+    #[test]
+    fn decode_truncated_prefix() {
+        assert_eq!(
+            decode_parallel(&[2, 0, 0, 0, b'a', 3, 1, 0]),
+            Err(DecodeError::TruncatedInput { at_byte: 8 })
+        );
+    }
+}
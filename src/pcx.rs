@@ -0,0 +1,211 @@
+//! The run-length compression of ZSoft PCX image files.
+//!
+//! A byte with its top two bits set, from `0xc0` up, is a count in its
+//! bottom 6 bits, and the byte after it the value to repeat; any other byte
+//! is a single pixel as it is. A pixel from `0xc0` up must therefore always
+//! be written as a run, even a run of 1.
+//!
+//! PCX files encode each scan line on its own, so to produce one, encode
+//! every line separately and join the results.
+
+use alloc::vec::Vec;
+
+use crate::DecodeError;
+
+/// The bits marking a byte as a count.
+pub(crate) const COUNT_FLAG: u8 = 0xc0;
+
+/// The longest run a single count can hold.
+const MAX_RUN: usize = 0x3f;
+
+/// Read a byte slice and return its PCX encoding.
+///
+/// * `bytes` - The bytes to be encoded.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::pcx;
+///
+/// assert_eq!(pcx::encode(&[1, 7, 7, 7, 0xc5]), [1, 0xc3, 7, 0xc1, 0xc5]);
+/// ```
+pub fn encode(bytes: &[u8]) -> Vec<u8> {
+    let mut encoding = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let byte = bytes[i];
+        let run = bytes[i..].iter().take(MAX_RUN).take_while(|&&b| b == byte).count();
+
+        if run == 1 && byte < COUNT_FLAG {
+            encoding.push(byte);
+        } else {
+            encoding.extend(&[COUNT_FLAG | run as u8, byte]);
+        }
+
+        i += run;
+    }
+
+    encoding
+}
+
+/// Read a PCX encoding and return its decoded contents.
+///
+/// * `bytes` - The bytes to be decoded.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::TruncatedInput`] if the input ends with a count,
+/// and [`DecodeError::ZeroCount`] if a count is 0, which encoders never
+/// write.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::pcx;
+///
+/// assert_eq!(pcx::decode(&[1, 0xc3, 7, 0xc1, 0xc5]).unwrap(), [1, 7, 7, 7, 0xc5]);
+/// assert!(pcx::decode(&[1, 0xc3]).is_err());
+/// ```
+pub fn decode(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let mut decoding = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let byte = bytes[i];
+
+        if byte < COUNT_FLAG {
+            decoding.push(byte);
+            i += 1;
+            continue;
+        }
+
+        let count = (byte & !COUNT_FLAG) as usize;
+        let value = *bytes.get(i + 1).ok_or(DecodeError::TruncatedInput { at_byte: i })?;
+
+        if count == 0 {
+            return Err(DecodeError::ZeroCount { at_byte: i });
+        }
+
+        decoding.extend(core::iter::repeat_n(value, count));
+        i += 2;
+    }
+
+    Ok(decoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// Return a 256-color PCX file of a `width` by `height` image, with a
+    /// grayscale palette and the scan lines encoded in `data`.
+    fn pcx_file(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+        let mut file = vec![0x0a, 5, 1, 8];
+
+        // The first and last column and row, and the resolution.
+        for &field in &[0, 0, width - 1, height - 1, 72, 72] {
+            file.extend(&(field as u16).to_le_bytes());
+        }
+
+        file.extend(&[0; 48]);
+        file.extend(&[0, 1]);
+        file.extend(&(width as u16).to_le_bytes());
+        file.extend(&1u16.to_le_bytes());
+        file.extend(&[0; 58]);
+        file.extend(data);
+        file.push(0x0c);
+
+        for i in 0..=255 {
+            file.extend(&[i, i, i]);
+        }
+
+        file
+    }
+
+    /// Decode a PCX file with an independent implementation and return its
+    /// pixels, top row first.
+    fn decode_with_pcx(file: &[u8], width: usize, height: usize) -> Vec<u8> {
+        let mut reader = ::pcx::Reader::from_mem(file).unwrap();
+        let mut pixels = vec![0; width * height];
+
+        for row in pixels.chunks_mut(width) {
+            reader.next_row_paletted(row).unwrap();
+        }
+
+        pixels
+    }
+
+    /// Return the pixels of a `width` by `height` image with short and long
+    /// runs, pixels from `0xc0` up, and noise.
+    fn test_image(width: usize, height: usize) -> Vec<u8> {
+        (0..width * height)
+            .map(|i| match i % width {
+                0..=69 => 0xff,
+                70..=79 => (i * 7 % 251) as u8,
+                80..=81 => 0xc0,
+                _ => (i / width * 30) as u8,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn scan_line() {
+        // 6 white pixels, a black one and 57 white ones, in a 256-color
+        // image whose white is 0xff.
+        let line = [vec![0xff; 6], vec![0], vec![0xff; 57]].concat();
+        let encoded = [0xc6, 0xff, 0x00, 0xf9, 0xff];
+
+        assert_eq!(encode(&line), encoded);
+        assert_eq!(decode(&encoded), Ok(line));
+    }
+
+    #[test]
+    fn encoding_matches_pcx_decoder() {
+        let (width, height) = (100, 6);
+        let pixels = test_image(width, height);
+        // Each scan line is encoded on its own.
+        let encoded: Vec<u8> = pixels.chunks(width).flat_map(encode).collect();
+
+        assert_eq!(decode_with_pcx(&pcx_file(&encoded, width, height), width, height), pixels);
+    }
+
+    #[test]
+    fn decode_pcx_encoder_output() {
+        let (width, height) = (100, 6);
+        let pixels = test_image(width, height);
+        let mut file = Vec::new();
+        let mut writer = ::pcx::WriterPaletted::new(&mut file, (width as u16, height as u16), (72, 72)).unwrap();
+
+        for row in pixels.chunks(width) {
+            writer.write_row(row).unwrap();
+        }
+
+        writer.write_palette(&[0; 768]).unwrap();
+
+        // The scan lines lie between the 128-byte header and the palette,
+        // which starts with 0x0c.
+        assert_eq!(decode(&file[128..file.len() - 769]), Ok(pixels));
+    }
+
+    #[test]
+    fn long_runs_are_split() {
+        assert_eq!(encode(&[4; 63]), [0xff, 4]);
+        assert_eq!(encode(&[4; 64]), [0xff, 4, 4]);
+        assert_eq!(encode(&[0xc0; 65]), [0xff, 0xc0, 0xc2, 0xc0]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes: Vec<u8> = (0..=255).flat_map(|n| vec![n; n as usize % 70 + 1]).collect();
+
+        assert_eq!(decode(&encode(&bytes)), Ok(bytes));
+    }
+
+    #[test]
+    fn decode_malformed_input() {
+        assert_eq!(decode(&[1, 0xc4]), Err(DecodeError::TruncatedInput { at_byte: 1 }));
+        assert_eq!(decode(&[1, 0xc0, 5]), Err(DecodeError::ZeroCount { at_byte: 1 }));
+    }
+}
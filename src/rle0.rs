@@ -0,0 +1,117 @@
+//! A run-length scheme that only encodes runs of zero bytes, suited to sparse
+//! data such as disk images and core dumps.
+//!
+//! Non-zero bytes are copied as they are. A zero byte is followed by the
+//! number of zeros after it in the same run, so a run of up to 256 zeros
+//! takes 2 bytes, and data without zeros doesn't grow at all.
+
+use alloc::vec::Vec;
+
+use crate::DecodeError;
+
+/// The longest run of zeros a single `[0, count]` pair can hold.
+const MAX_RUN: usize = 256;
+
+/// Read a byte slice and return its encoding with only the runs of zeros
+/// encoded.
+///
+/// * `bytes` - The bytes to be encoded.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::rle0;
+///
+/// assert_eq!(rle0::encode(&[7, 0, 0, 0, 8, 0]), [7, 0, 2, 8, 0, 0]);
+/// ```
+pub fn encode(bytes: &[u8]) -> Vec<u8> {
+    let mut encoding = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != 0 {
+            encoding.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+
+        let zeros = bytes[i..].iter().take(MAX_RUN).take_while(|&&byte| byte == 0).count();
+        encoding.extend(&[0, (zeros - 1) as u8]);
+        i += zeros;
+    }
+
+    encoding
+}
+
+/// Read an encoding with only the runs of zeros encoded and return its
+/// decoded contents.
+///
+/// * `bytes` - The bytes to be decoded.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::TruncatedInput`] if the input ends with a zero byte
+/// that has no count.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::rle0;
+///
+/// assert_eq!(rle0::decode(&[7, 0, 2, 8, 0, 0]).unwrap(), [7, 0, 0, 0, 8, 0]);
+/// assert!(rle0::decode(&[7, 0]).is_err());
+/// ```
+pub fn decode(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let mut decoding = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != 0 {
+            decoding.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+
+        let count = *bytes.get(i + 1).ok_or(DecodeError::TruncatedInput { at_byte: i })?;
+        decoding.extend(core::iter::repeat_n(0, count as usize + 1));
+        i += 2;
+    }
+
+    Ok(decoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn dense_data_passes_through() {
+        assert_eq!(encode(b"no zeros here"), b"no zeros here");
+        assert_eq!(encode(&[]), []);
+    }
+
+    #[test]
+    fn long_runs_of_zeros_are_split() {
+        assert_eq!(encode(&[0; 256]), [0, 255]);
+        assert_eq!(encode(&[0; 257]), [0, 255, 0, 0]);
+        assert_eq!(encode(&[0; 600]), [0, 255, 0, 255, 0, 87]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = [vec![1, 2], vec![0; 1000], vec![3; 5], vec![0]].concat();
+
+        assert_eq!(decode(&encode(&bytes)), Ok(bytes));
+    }
+
+    #[test]
+    fn decode_count_at_end_of_input() {
+        assert_eq!(decode(&[1, 0, 3]), Ok(vec![1, 0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn decode_zero_without_count_is_an_error() {
+        assert_eq!(decode(&[1, 0, 3, 0]), Err(DecodeError::TruncatedInput { at_byte: 3 }));
+    }
+}
@@ -0,0 +1,429 @@
+//! Runs, and iterators over the runs of raw and encoded data.
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+use core::iter::FromIterator;
+
+use crate::{DecodeError, RunsError};
+
+/// A byte repeated `len` times in a row: the unit of the encoding.
+///
+/// * `byte` - The repeated byte.
+/// * `len` - How many times it occurs in a row.
+///
+/// # Examples
+///
+/// ```
+/// use core::convert::TryFrom;
+/// use rle_rs::Run;
+///
+/// let run = Run { byte: b'A', len: 3 };
+///
+/// assert_eq!(run.to_string(), "0x41×3");
+/// assert_eq!(<[u8; 2]>::try_from(run), Ok([b'A', 3]));
+/// assert_eq!(Run::try_from([b'A', 3]), Ok(run));
+/// assert!(Run::try_from([b'A', 0]).is_err());
+///
+/// let encoded: Vec<u8> = vec![run, Run { byte: b'B', len: 300 }].into_iter().collect();
+/// assert_eq!(encoded, [b'A', 3, b'B', 255, b'B', 45]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Run {
+    pub byte: u8,
+    pub len: u64,
+}
+
+impl fmt::Display for Run {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#04x}×{}", self.byte, self.len)
+    }
+}
+
+impl TryFrom<Run> for [u8; 2] {
+    type Error = RunsError;
+
+    /// Write a run as a single `[byte, count]` pair, which only holds runs of
+    /// 1 to 255 bytes; [`encode_runs`] splits longer ones.
+    fn try_from(run: Run) -> Result<Self, RunsError> {
+        match u8::try_from(run.len) {
+            Ok(count) if count > 0 => Ok([run.byte, count]),
+            _ => Err(RunsError::NotAPair { len: run.len }),
+        }
+    }
+}
+
+impl TryFrom<[u8; 2]> for Run {
+    type Error = DecodeError;
+
+    /// Read a run from an encoded `[byte, count]` pair. A count of zero is
+    /// never produced by the encoder and is an error.
+    fn try_from(pair: [u8; 2]) -> Result<Self, DecodeError> {
+        match pair {
+            [_, 0] => Err(DecodeError::ZeroCount { at_byte: 0 }),
+            [byte, count] => Ok(Self { byte, len: u64::from(count) }),
+        }
+    }
+}
+
+impl FromIterator<Run> for Vec<u8> {
+    /// Encode the runs, as [`encode_runs`] does.
+    fn from_iter<I: IntoIterator<Item = Run>>(iter: I) -> Self {
+        iter.into_iter()
+            .flat_map(|run| split(run.byte, run.len, u8::MAX))
+            .flat_map(|(byte, count)| [byte, count])
+            .collect()
+    }
+}
+
+/// Return the runs of `bytes`, as [`runs`] finds them.
+///
+/// * `bytes` - The bytes to be scanned.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::Run;
+///
+/// assert_eq!(
+///     rle_rs::to_runs(b"aaab"),
+///     [Run { byte: b'a', len: 3 }, Run { byte: b'b', len: 1 }]
+/// );
+/// ```
+pub fn to_runs(bytes: &[u8]) -> Vec<Run> {
+    runs(bytes).map(|(byte, len)| Run { byte, len: len as u64 }).collect()
+}
+
+/// Return the bytes `runs` describe, each run's byte repeated as many times
+/// as its length.
+///
+/// * `runs` - The runs to be expanded.
+///
+/// # Errors
+///
+/// Returns [`RunsError::TooLong`] if the runs add up to more bytes than can be
+/// held in memory, before anything is allocated for them.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::{Run, RunsError};
+///
+/// let runs = [Run { byte: 0xff, len: 4 }, Run { byte: 0, len: 2 }];
+/// assert_eq!(rle_rs::from_runs(&runs).unwrap(), [0xff, 0xff, 0xff, 0xff, 0, 0]);
+///
+/// let runs = [Run { byte: 0xff, len: u64::MAX }, Run { byte: 0, len: 1 }];
+/// assert_eq!(rle_rs::from_runs(&runs), Err(RunsError::TooLong { len: None }));
+/// ```
+pub fn from_runs(runs: &[Run]) -> Result<Vec<u8>, RunsError> {
+    let len = runs
+        .iter()
+        .try_fold(0u64, |len, run| len.checked_add(run.len))
+        .ok_or(RunsError::TooLong { len: None })?;
+    let too_long = RunsError::TooLong { len: Some(len) };
+    let mut bytes = Vec::new();
+    bytes
+        .try_reserve_exact(usize::try_from(len).map_err(|_| too_long.clone())?)
+        .map_err(|_| too_long)?;
+
+    for run in runs {
+        bytes.resize(bytes.len() + run.len as usize, run.byte);
+    }
+
+    Ok(bytes)
+}
+
+/// Return the run-length encoding of the bytes `runs` describe, splitting
+/// runs longer than 255 bytes into several pairs.
+///
+/// Runs are encoded as they're given: consecutive runs of the same byte are
+/// not merged, and empty runs are left out.
+///
+/// * `runs` - The runs to be encoded.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::Run;
+///
+/// let row = [Run { byte: 0xff, len: 1920 }, Run { byte: 0, len: 80 }];
+/// let encoded = rle_rs::encode_runs(&row);
+///
+/// assert_eq!(encoded.len(), 2 * (8 + 1));
+/// assert_eq!(encoded, rle_rs::encode(&rle_rs::from_runs(&row).unwrap()));
+/// ```
+pub fn encode_runs(runs: &[Run]) -> Vec<u8> {
+    runs.iter().copied().collect()
+}
+
+/// Return an iterator over the runs of `bytes`, as `(byte, length)` pairs.
+///
+/// Runs are as long as they are in `bytes`, not capped at 255 bytes as in the
+/// encoding; splitting them gives the pairs [`encode`](crate::encode)
+/// produces. Nothing is allocated.
+///
+/// * `bytes` - The bytes to be scanned.
+///
+/// # Examples
+///
+/// ```
+/// let runs: Vec<(u8, usize)> = rle_rs::runs(b"aaab").collect();
+///
+/// assert_eq!(runs, [(b'a', 3), (b'b', 1)]);
+/// assert_eq!(rle_rs::runs(&[0; 1000]).collect::<Vec<_>>(), [(0, 1000)]);
+///
+/// // The encoding, run by run.
+/// let encoded: Vec<u8> = rle_rs::runs(&[0; 300])
+///     .flat_map(|(byte, len)| {
+///         (0..len).step_by(255).flat_map(move |i| [byte, (len - i).min(255) as u8])
+///     })
+///     .collect();
+///
+/// assert_eq!(encoded, rle_rs::encode(&[0; 300]));
+/// ```
+pub fn runs(bytes: &[u8]) -> impl Iterator<Item = (u8, usize)> + '_ {
+    let mut rest = bytes;
+
+    core::iter::from_fn(move || {
+        let &byte = rest.first()?;
+        let len = run_len(rest, byte);
+        rest = &rest[len..];
+
+        Some((byte, len))
+    })
+}
+
+/// Return how many bytes at the start of `bytes` are `byte`, comparing 16 of
+/// them at once.
+#[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse2"))]
+fn run_len(bytes: &[u8], byte: u8) -> usize {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::{__m128i, _mm_cmpeq_epi8, _mm_loadu_si128, _mm_movemask_epi8, _mm_set1_epi8};
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::{__m128i, _mm_cmpeq_epi8, _mm_loadu_si128, _mm_movemask_epi8, _mm_set1_epi8};
+
+    // Most runs in data that barely compresses are a byte long, and not worth
+    // setting up the comparison for.
+    if bytes.get(1) != Some(&byte) {
+        return usize::from(bytes.first() == Some(&byte));
+    }
+
+    // SAFETY: SSE2 is enabled in this build, as the `cfg` above checks.
+    let needle = unsafe { _mm_set1_epi8(byte as i8) };
+    let mut chunks = bytes.chunks_exact(16);
+    let mut len = 0;
+
+    for chunk in &mut chunks {
+        // A bit for each of the 16 bytes, set where it's `byte`.
+        // SAFETY: SSE2 is enabled, the chunk holds 16 bytes and the load
+        // needs no alignment.
+        let equal = unsafe {
+            let chunk = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+            _mm_movemask_epi8(_mm_cmpeq_epi8(chunk, needle)) as u32
+        };
+
+        if equal != 0xffff {
+            return len + (!equal).trailing_zeros() as usize;
+        }
+
+        len += 16;
+    }
+
+    len + scalar_run_len(chunks.remainder(), byte)
+}
+
+/// Return how many bytes at the start of `bytes` are `byte`.
+#[cfg(not(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse2")))]
+fn run_len(bytes: &[u8], byte: u8) -> usize {
+    scalar_run_len(bytes, byte)
+}
+
+/// Return how many bytes at the start of `bytes` are `byte`, one at a time.
+fn scalar_run_len(bytes: &[u8], byte: u8) -> usize {
+    bytes.iter().take_while(|&&b| b == byte).count()
+}
+
+/// Return an iterator over the `(byte, count)` pairs of `bytes`, with runs
+/// longer than `max_run` split, as [`encode`](crate::encode) does with 255.
+pub(crate) fn pairs(bytes: &[u8], max_run: u8) -> impl Iterator<Item = (u8, u8)> + '_ {
+    runs(bytes).flat_map(move |(byte, len)| split(byte, len as u64, max_run))
+}
+
+/// Return the `(byte, count)` pairs of a run of `len` bytes, with no count
+/// above `max_run`.
+fn split(byte: u8, len: u64, max_run: u8) -> impl Iterator<Item = (u8, u8)> {
+    let max_run = u64::from(max_run);
+    // Pairs holding `max_run` bytes each, then one holding the rest.
+    let full = (0..len / max_run).map(move |_| (byte, max_run as u8));
+    let rest = Some((byte, (len % max_run) as u8)).filter(|&(_, count)| count > 0);
+
+    full.chain(rest)
+}
+
+/// Return an iterator over the `(byte, count)` pairs of an encoding.
+///
+/// A dangling byte at the end of `encoded` is not part of any pair and is
+/// ignored; use [`decode_pairs`] or [`decode`](crate::decode) to validate an
+/// encoding.
+///
+/// * `encoded` - The run-length encoded bytes.
+///
+/// # Examples
+///
+/// ```
+/// let runs: Vec<(u8, u8)> = rle_rs::encoded_runs(&[b'a', 3, b'b', 1]).collect();
+///
+/// assert_eq!(runs, [(b'a', 3), (b'b', 1)]);
+/// ```
+pub fn encoded_runs(encoded: &[u8]) -> impl Iterator<Item = (u8, u8)> + '_ {
+    encoded.chunks_exact(2).map(|pair| (pair[0], pair[1]))
+}
+
+/// Return an iterator over the `(byte, count)` pairs of an encoding, checking
+/// each of them. Nothing is allocated.
+///
+/// The iterator ends after the first error.
+///
+/// * `encoded` - The run-length encoded bytes.
+///
+/// # Errors
+///
+/// Yields [`DecodeError::ZeroCount`] for a pair with a count of zero, which
+/// the encoder never produces, and [`DecodeError::TruncatedInput`] for a
+/// dangling byte at the end of `encoded`.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::DecodeError;
+///
+/// let pairs: Result<Vec<_>, _> = rle_rs::decode_pairs(&[b'a', 3, b'b', 1]).collect();
+/// assert_eq!(pairs, Ok(vec![(b'a', 3), (b'b', 1)]));
+///
+/// let pairs: Vec<_> = rle_rs::decode_pairs(&[b'a', 3, b'b']).collect();
+/// assert_eq!(pairs, [Ok((b'a', 3)), Err(DecodeError::TruncatedInput { at_byte: 2 })]);
+/// ```
+pub fn decode_pairs(encoded: &[u8]) -> impl Iterator<Item = Result<(u8, u8), DecodeError>> + '_ {
+    let mut at_byte = 0;
+
+    core::iter::from_fn(move || {
+        let result = match encoded.get(at_byte..)? {
+            [] => return None,
+            [_] => Err(DecodeError::TruncatedInput { at_byte }),
+            [_, 0, ..] => Err(DecodeError::ZeroCount { at_byte }),
+            &[byte, count, ..] => Ok((byte, count)),
+        };
+        // Past the end after an error, so that the iterator stops.
+        at_byte = if result.is_ok() { at_byte + 2 } else { encoded.len() + 1 };
+
+        Some(result)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use crate::encode;
+
+    #[test]
+    fn run_display() {
+        assert_eq!(Run { byte: 0, len: 1_000_000 }.to_string(), "0x00×1000000");
+    }
+
+    #[test]
+    fn runs_of_1_to_255_bytes_are_pairs() {
+        assert_eq!(<[u8; 2]>::try_from(Run { byte: 7, len: 1 }), Ok([7, 1]));
+        assert_eq!(<[u8; 2]>::try_from(Run { byte: 7, len: 255 }), Ok([7, 255]));
+        assert_eq!(<[u8; 2]>::try_from(Run { byte: 7, len: 0 }), Err(RunsError::NotAPair { len: 0 }));
+        assert_eq!(<[u8; 2]>::try_from(Run { byte: 7, len: 256 }), Err(RunsError::NotAPair { len: 256 }));
+    }
+
+    #[test]
+    fn runs_collect_into_their_encoding() {
+        let bytes = b"aaabccd";
+        let encoded: Vec<u8> = to_runs(bytes).into_iter().collect();
+
+        assert_eq!(encoded, encode(bytes));
+    }
+
+    #[test]
+    fn runs_empty() {
+        assert_eq!(runs(&[]).next(), None);
+    }
+
+    #[test]
+    fn runs_single_maximal_run() {
+        assert_eq!(runs(&[4; 100_000]).collect::<Vec<_>>(), [(4, 100_000)]);
+    }
+
+    #[test]
+    fn run_len_matches_scalar_run_len() {
+        // Runs ending at every offset within and across 16-byte chunks.
+        for len in 0..50 {
+            let bytes = [vec![9; len], vec![1; 20]].concat();
+
+            assert_eq!(run_len(&bytes, 9), len);
+            assert_eq!(run_len(&bytes, 9), scalar_run_len(&bytes, 9));
+            assert_eq!(run_len(&bytes[..len], 9), len);
+        }
+    }
+
+    #[test]
+    fn pairs_capped_at_max_run() {
+        assert_eq!(pairs(&[4; 300], 255).collect::<Vec<_>>(), [(4, 255), (4, 45)]);
+        assert_eq!(pairs(&[4; 6], 3).collect::<Vec<_>>(), [(4, 3), (4, 3)]);
+    }
+
+    #[test]
+    fn pairs_match_encoded_runs() {
+        let bytes: Vec<u8> = (0..300).flat_map(|n| vec![n as u8; n % 7 + 1]).collect();
+
+        assert!(pairs(&bytes, 255).eq(encoded_runs(&encode(&bytes))));
+    }
+
+    #[test]
+    fn encoded_runs_ignores_dangling_byte() {
+        assert_eq!(encoded_runs(&[1, 2, 3]).collect::<Vec<_>>(), [(1, 2)]);
+    }
+
+    #[test]
+    fn decode_pairs_empty() {
+        assert_eq!(decode_pairs(&[]).next(), None);
+    }
+
+    #[test]
+    fn decode_pairs_single_maximal_run() {
+        assert_eq!(decode_pairs(&[7, 255]).collect::<Vec<_>>(), [Ok((7, 255))]);
+    }
+
+    #[test]
+    fn from_runs_reverses_to_runs() {
+        let bytes: Vec<u8> = (0..300).flat_map(|n| vec![n as u8; n % 7 + 1]).collect();
+
+        assert_eq!(from_runs(&to_runs(&bytes)).unwrap(), bytes);
+    }
+
+    #[test]
+    fn from_runs_too_long_for_memory() {
+        let runs = [Run { byte: 1, len: u64::MAX / 2 }, Run { byte: 2, len: 1 }];
+
+        assert_eq!(from_runs(&runs), Err(RunsError::TooLong { len: Some(u64::MAX / 2 + 1) }));
+    }
+
+    #[test]
+    fn encode_runs_leaves_out_empty_runs() {
+        let runs = [Run { byte: 1, len: 0 }, Run { byte: 2, len: 510 }, Run { byte: 2, len: 1 }];
+
+        assert_eq!(encode_runs(&runs), [2, 255, 2, 255, 2, 1]);
+    }
+
+    #[test]
+    fn decode_pairs_stops_at_zero_count() {
+        assert_eq!(
+            decode_pairs(&[1, 2, 3, 0, 5, 6]).collect::<Vec<_>>(),
+            [Ok((1, 2)), Err(DecodeError::ZeroCount { at_byte: 2 })]
+        );
+    }
+}
@@ -0,0 +1,502 @@
+//! Statistics about how well data encodes.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::runs::{pairs, runs, Run};
+
+/// The shortest run counted in [`Analysis::bytes_in_long_runs`].
+const MIN_LONG_RUN: u64 = 3;
+
+/// The entropy below which [`is_likely_compressible`] considers data
+/// compressible, in bits per byte.
+#[cfg(feature = "std")]
+const COMPRESSIBLE_ENTROPY: f64 = 6.5;
+
+/// Statistics gathered while encoding.
+///
+/// * `input_size` - The length of the data, in bytes.
+/// * `encoded_size` - The length of its encoding, in bytes.
+/// * `run_count` - The number of `[byte, count]` pairs in the encoding. Runs
+///   longer than 255 bytes count once per pair they're split into.
+/// * `max_run` - The longest run in the encoding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub input_size: usize,
+    pub encoded_size: usize,
+    pub run_count: usize,
+    pub max_run: u8,
+}
+
+impl Stats {
+    /// Return the size of the encoding relative to that of the data, or 0 if
+    /// there's no data.
+    pub fn ratio(&self) -> f64 {
+        if self.input_size == 0 {
+            return 0.0;
+        }
+
+        self.encoded_size as f64 / self.input_size as f64
+    }
+
+    /// Return the average length of a run, or 0 if there are none.
+    pub fn average_run(&self) -> f64 {
+        if self.run_count == 0 {
+            return 0.0;
+        }
+
+        self.input_size as f64 / self.run_count as f64
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} -> {} bytes ({:.2}%), {} runs, {:.2} bytes per run on average, longest run {}",
+            self.input_size,
+            self.encoded_size,
+            self.ratio() * 100.0,
+            self.run_count,
+            self.average_run(),
+            self.max_run,
+        )
+    }
+}
+
+/// Read a byte slice and return its run-length encoding along with
+/// statistics about it, gathered as the encoding is produced.
+///
+/// * `bytes` - The bytes to be encoded.
+///
+/// # Examples
+///
+/// ```
+/// let (encoded, stats) = rle_rs::encode_with_stats(b"aaab");
+///
+/// assert_eq!(encoded, rle_rs::encode(b"aaab"));
+/// assert_eq!((stats.run_count, stats.max_run), (2, 3));
+/// ```
+pub fn encode_with_stats(bytes: &[u8]) -> (Vec<u8>, Stats) {
+    let mut encoding = Vec::new();
+    let mut stats = Stats { input_size: bytes.len(), ..Stats::default() };
+
+    for (byte, count) in pairs(bytes, u8::MAX) {
+        encoding.extend(&[byte, count]);
+        stats.run_count += 1;
+        stats.max_run = stats.max_run.max(count);
+    }
+
+    stats.encoded_size = encoding.len();
+
+    (encoding, stats)
+}
+
+/// Read a byte slice and return its Shannon entropy, in bits per byte: from
+/// 0 if it's a single byte value repeated, to 8 if every value occurs as
+/// often.
+///
+/// The entropy is the least number of bits per byte any encoding of the bytes
+/// taken one at a time can average, so data close to 8 won't shrink, whatever
+/// its scheme. It says nothing about the order of the bytes, though, which
+/// is what run-length encoding relies on: see [`is_likely_compressible`].
+///
+/// * `bytes` - The bytes to be measured.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(rle_rs::byte_entropy(b"aaaa"), 0.0);
+/// assert_eq!(rle_rs::byte_entropy(b"abcd"), 2.0);
+/// ```
+#[cfg(feature = "std")]
+pub fn byte_entropy(bytes: &[u8]) -> f64 {
+    let mut counts = [0u64; 256];
+
+    for &byte in bytes {
+        counts[usize::from(byte)] += 1;
+    }
+
+    entropy(&counts, bytes.len() as u64)
+}
+
+/// Read a byte slice and return whether run-length encoding is likely to
+/// shrink it: whether its entropy is below 6.5 bits per byte and it has runs
+/// of 3 bytes or more.
+///
+/// * `bytes` - The bytes to be checked.
+///
+/// # Examples
+///
+/// ```
+/// assert!(rle_rs::is_likely_compressible(b"aaaabbbbbbcc"));
+/// // No run is long enough.
+/// assert!(!rle_rs::is_likely_compressible(b"aabbaabbaabb"));
+/// ```
+#[cfg(feature = "std")]
+pub fn is_likely_compressible(bytes: &[u8]) -> bool {
+    byte_entropy(bytes) < COMPRESSIBLE_ENTROPY && runs(bytes).any(|(_, len)| len as u64 >= MIN_LONG_RUN)
+}
+
+/// Return the Shannon entropy of `total` bytes, of which there are
+/// `counts[b]` of each value `b`, or 0 if there are none.
+///
+/// * `counts` - How many times each byte value occurs.
+/// * `total` - How many bytes there are.
+#[cfg(feature = "std")]
+fn entropy(counts: &[u64; 256], total: u64) -> f64 {
+    let total = total as f64;
+
+    // The terms are subtracted, rather than their sum negated, so that data
+    // of a single value has an entropy of 0 and not -0.
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| count as f64 / total)
+        .fold(0.0, |h, p| h - p * p.log2())
+}
+
+/// A report on how well data would encode, to tell whether encoding it is
+/// worth it at all.
+///
+/// Runs are counted whole here, not split into pairs of 255 bytes as in the
+/// encoding.
+///
+/// * `input_size` - The length of the data, in bytes.
+/// * `encoded_size` - The length its encoding would have, in bytes.
+/// * `run_count` - The number of runs in the data.
+/// * `longest_run` - The longest run, the first one if several are as long,
+///   or `None` if there's no data.
+/// * `longest_run_offset` - The offset of the longest run in the data.
+/// * `bytes_in_long_runs` - How many bytes are part of runs of 3 bytes or
+///   more, which encoding shrinks.
+/// * `run_lengths` - How many runs there are of each length.
+/// * `byte_counts` - How many times each byte value occurs in the data.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::{Analysis, Run};
+///
+/// let analysis = Analysis::of(b"abbbbc");
+///
+/// assert_eq!((analysis.input_size, analysis.encoded_size, analysis.run_count), (6, 6, 3));
+/// assert_eq!(analysis.longest_run, Some(Run { byte: b'b', len: 4 }));
+/// assert_eq!(analysis.longest_run_offset, 1);
+/// assert_eq!(analysis.median_run(), 1);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    pub input_size: u64,
+    pub encoded_size: u64,
+    pub run_count: u64,
+    pub longest_run: Option<Run>,
+    pub longest_run_offset: u64,
+    pub bytes_in_long_runs: u64,
+    pub run_lengths: BTreeMap<u64, u64>,
+    pub byte_counts: [u64; 256],
+}
+
+impl Default for Analysis {
+    fn default() -> Self {
+        Self {
+            input_size: 0,
+            encoded_size: 0,
+            run_count: 0,
+            longest_run: None,
+            longest_run_offset: 0,
+            bytes_in_long_runs: 0,
+            run_lengths: BTreeMap::new(),
+            byte_counts: [0; 256],
+        }
+    }
+}
+
+impl Analysis {
+    /// Analyze `bytes`, all of which are already in memory.
+    ///
+    /// * `bytes` - The bytes to be analyzed.
+    pub fn of(bytes: &[u8]) -> Self {
+        let mut analyzer = Analyzer::new();
+        analyzer.push_slice(bytes);
+
+        analyzer.finish()
+    }
+
+    /// Return the size of the encoding relative to that of the data, or 0 if
+    /// there's no data.
+    pub fn ratio(&self) -> f64 {
+        if self.input_size == 0 {
+            return 0.0;
+        }
+
+        self.encoded_size as f64 / self.input_size as f64
+    }
+
+    /// Return the average length of a run, or 0 if there are none.
+    pub fn mean_run(&self) -> f64 {
+        if self.run_count == 0 {
+            return 0.0;
+        }
+
+        self.input_size as f64 / self.run_count as f64
+    }
+
+    /// Return the Shannon entropy of the bytes, as [`byte_entropy`] does.
+    #[cfg(feature = "std")]
+    pub fn entropy(&self) -> f64 {
+        entropy(&self.byte_counts, self.input_size)
+    }
+
+    /// Return the median length of a run, the lower of the two middle ones
+    /// if there's an even number of runs, or 0 if there are none.
+    pub fn median_run(&self) -> u64 {
+        let middle = self.run_count.div_ceil(2);
+        let mut seen = 0;
+
+        for (&len, &count) in &self.run_lengths {
+            seen += count;
+
+            if seen >= middle {
+                return len;
+            }
+        }
+
+        0
+    }
+
+    /// Return the share of the data in runs of 3 bytes or more, from 0 to 1,
+    /// or 0 if there's no data.
+    pub fn long_run_fraction(&self) -> f64 {
+        if self.input_size == 0 {
+            return 0.0;
+        }
+
+        self.bytes_in_long_runs as f64 / self.input_size as f64
+    }
+}
+
+impl fmt::Display for Analysis {
+    /// Write the report over several lines, ending with how many runs have a
+    /// length from each power of two to the next.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "input size: {} bytes", self.input_size)?;
+        writeln!(f, "encoded size: {} bytes ({:.2}%)", self.encoded_size, self.ratio() * 100.0)?;
+        #[cfg(feature = "std")]
+        writeln!(f, "entropy: {:.2} bits per byte", self.entropy())?;
+        writeln!(f, "runs: {}", self.run_count)?;
+        writeln!(
+            f, "run length: {:.2} bytes on average, {} median", self.mean_run(), self.median_run()
+        )?;
+
+        match self.longest_run {
+            Some(run) => writeln!(
+                f, "longest run: {} bytes of {:#04x} at offset {}", run.len, run.byte, self.longest_run_offset
+            )?,
+            None => writeln!(f, "longest run: none")?,
+        }
+
+        write!(f, "bytes in runs of 3 or more: {:.2}%", self.long_run_fraction() * 100.0)?;
+
+        // Runs from 2^k to 2^(k + 1) - 1 bytes long, for each k.
+        let mut buckets = [0u64; 64];
+
+        for (&len, &count) in &self.run_lengths {
+            buckets[len.ilog2() as usize] += count;
+        }
+
+        for (k, &count) in buckets.iter().enumerate().filter(|&(_, &count)| count > 0) {
+            let (low, high) = (1u64 << k, (1u64 << k) + ((1u64 << k) - 1));
+
+            if low == high {
+                write!(f, "\nruns of {} byte: {}", low, count)?;
+            } else {
+                write!(f, "\nruns of {} to {} bytes: {}", low, high, count)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An analyzer that takes its input a slice at a time, so that data too large
+/// for memory can be analyzed as it's read.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::{Analysis, Analyzer};
+///
+/// let mut analyzer = Analyzer::new();
+/// analyzer.push_slice(b"aa");
+/// analyzer.push_slice(b"ab");
+///
+/// assert_eq!(analyzer.finish(), Analysis::of(b"aaab"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Analyzer {
+    analysis: Analysis,
+    /// The byte of the current run and its length so far.
+    run: Option<(u8, u64)>,
+}
+
+impl Analyzer {
+    /// Create a new analyzer, with no input yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Analyze the next bytes of the input.
+    ///
+    /// * `bytes` - The next bytes of the input.
+    pub fn push_slice(&mut self, bytes: &[u8]) {
+        for (byte, len) in runs(bytes) {
+            let len = len as u64;
+
+            match self.run {
+                Some((b, ref mut so_far)) if b == byte => *so_far += len,
+                _ => {
+                    self.end_run();
+                    self.run = Some((byte, len));
+                }
+            }
+
+            self.analysis.input_size += len;
+            self.analysis.byte_counts[usize::from(byte)] += len;
+        }
+    }
+
+    /// Analyze the last run and return the report on the whole input.
+    pub fn finish(mut self) -> Analysis {
+        self.end_run();
+
+        self.analysis
+    }
+
+    /// Record the current run, if any, which has just ended.
+    fn end_run(&mut self) {
+        let (byte, len) = match self.run.take() {
+            Some(run) => run,
+            None => return,
+        };
+        let analysis = &mut self.analysis;
+
+        analysis.encoded_size += 2 * len.div_ceil(u64::from(u8::MAX));
+        analysis.run_count += 1;
+        *analysis.run_lengths.entry(len).or_insert(0) += 1;
+
+        if len >= MIN_LONG_RUN {
+            analysis.bytes_in_long_runs += len;
+        }
+
+        if analysis.longest_run.is_none_or(|longest| len > longest.len) {
+            analysis.longest_run = Some(Run { byte, len });
+            // The run ends where the input analyzed so far does.
+            analysis.longest_run_offset = analysis.input_size - len;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn stats_of_long_runs() {
+        let bytes = [vec![1; 600], vec![2; 4]].concat();
+        let (encoded, stats) = encode_with_stats(&bytes);
+
+        assert_eq!(encoded, crate::encode(&bytes));
+        assert_eq!(
+            stats,
+            Stats { input_size: 604, encoded_size: 8, run_count: 4, max_run: 255 }
+        );
+        assert_eq!(stats.average_run(), 151.0);
+    }
+
+    #[test]
+    fn stats_of_empty_input() {
+        let (encoded, stats) = encode_with_stats(&[]);
+
+        assert!(encoded.is_empty());
+        assert_eq!((stats.ratio(), stats.average_run()), (0.0, 0.0));
+    }
+
+    #[test]
+    fn analysis_of_long_runs() {
+        let bytes = [vec![2; 4], vec![1; 600], vec![2; 4], vec![3]].concat();
+        let analysis = Analysis::of(&bytes);
+
+        assert_eq!(
+            (analysis.input_size, analysis.encoded_size, analysis.run_count),
+            (609, crate::encoded_len(&bytes) as u64, 4)
+        );
+        assert_eq!(analysis.longest_run, Some(Run { byte: 1, len: 600 }));
+        assert_eq!(analysis.longest_run_offset, 4);
+        assert_eq!(analysis.bytes_in_long_runs, 608);
+        assert_eq!(analysis.median_run(), 4);
+        assert_eq!(analysis.mean_run(), 609.0 / 4.0);
+    }
+
+    #[test]
+    fn analysis_spans_pushed_slices() {
+        let bytes: Vec<u8> = (0..3000).map(|i| (i / 700) as u8).collect();
+        let mut analyzer = Analyzer::new();
+
+        for chunk in bytes.chunks(64) {
+            analyzer.push_slice(chunk);
+        }
+
+        assert_eq!(analyzer.finish(), Analysis::of(&bytes));
+    }
+
+    #[test]
+    fn analysis_of_empty_input() {
+        let analysis = Analysis::of(&[]);
+
+        assert_eq!(analysis.longest_run, None);
+        assert_eq!((analysis.ratio(), analysis.mean_run(), analysis.median_run()), (0.0, 0.0, 0));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn entropy_of_byte_distributions() {
+        assert_eq!(byte_entropy(&[]), 0.0);
+        assert_eq!(byte_entropy(&[7; 100]), 0.0);
+        assert_eq!(byte_entropy(b"ab"), 1.0);
+
+        let every_byte: Vec<u8> = (0..=255).collect();
+        assert_eq!(byte_entropy(&every_byte.repeat(4)), 8.0);
+
+        // 3/4 of one byte and 1/4 of another.
+        let bytes = b"aaab";
+        assert!((byte_entropy(bytes) - 0.811278).abs() < 1e-6);
+        assert_eq!(Analysis::of(bytes).entropy(), byte_entropy(bytes));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn likely_compressible_data() {
+        let every_byte: Vec<u8> = (0..=255).collect();
+        let runs_of_every_byte: Vec<u8> = every_byte.iter().flat_map(|&b| [b; 4]).collect();
+
+        assert!(is_likely_compressible(&[0; 100]));
+        assert!(!is_likely_compressible(&[]));
+        // Runs, but of too many values.
+        assert!(!is_likely_compressible(&runs_of_every_byte));
+        // Few values, but in no runs.
+        assert!(!is_likely_compressible(&b"ab".repeat(50)));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn analysis_report() {
+        let report = Analysis::of(b"abbbbc").to_string();
+
+        assert!(report.contains("longest run: 4 bytes of 0x62 at offset 1"));
+        assert!(report.contains("entropy: 1.25 bits per byte"));
+        assert!(report.ends_with("runs of 1 byte: 2\nruns of 4 to 7 bytes: 1"));
+    }
+}
@@ -0,0 +1,856 @@
+//! Streaming adapters around the codec.
+
+use std::io::{self, Read, Write};
+use std::iter::Fuse;
+
+use crate::{DecodeError, EncodeOptions, EncoderMode, RleError};
+
+/// The size of the chunks the streaming functions read their input in.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Run-length encode everything read from `r` and write the encoding to `w`.
+/// Return the number of bytes written.
+///
+/// * `r` - The reader the bytes to be encoded come from.
+/// * `w` - The writer that receives the encoded bytes.
+///
+/// # Examples
+///
+/// ```
+/// let mut encoded = Vec::new();
+///
+/// assert_eq!(rle_rs::encode_stream(&b"aaab"[..], &mut encoded).unwrap(), 4);
+/// assert_eq!(encoded, [b'a', 3, b'b', 1]);
+/// ```
+pub fn encode_stream<R: Read, W: Write>(r: R, w: W) -> io::Result<u64> {
+    encode_stream_with_opts(r, w, &EncodeOptions::default())
+}
+
+/// Run-length encode everything read from `r`, as tuned by `opts`, and write
+/// the encoding to `w`. Return the number of bytes written.
+///
+/// * `r` - The reader the bytes to be encoded come from.
+/// * `w` - The writer that receives the encoded bytes.
+/// * `opts` - The options to encode with. In [`EncoderMode::Adaptive`], the
+///   whole input is read into memory before it is encoded.
+pub fn encode_stream_with_opts<R: Read, W: Write>(
+    mut r: R, mut w: W, opts: &EncodeOptions
+) -> io::Result<u64> {
+    if opts.mode == EncoderMode::Adaptive {
+        let mut bytes = Vec::new();
+        r.read_to_end(&mut bytes)?;
+        let encoded = crate::encode_with_opts(&bytes, opts);
+        w.write_all(&encoded)?;
+        w.flush()?;
+
+        return Ok(encoded.len() as u64);
+    }
+
+    let mut encoder = RleEncoder::with_options(Counter { inner: w, count: 0 }, opts);
+    copy_chunks(r, &mut encoder)?;
+    let mut counter = encoder.finish()?;
+    counter.flush()?;
+
+    Ok(counter.count)
+}
+
+/// Decode the run-length encoded bytes read from `r` and write the result to
+/// `w`. Return the number of bytes written.
+///
+/// * `r` - The reader the encoded bytes come from.
+/// * `w` - The writer that receives the decoded bytes.
+///
+/// # Errors
+///
+/// Fails with [`io::ErrorKind::InvalidData`] if the encoded data is
+/// malformed. See [`RleDecoder`].
+///
+/// # Examples
+///
+/// ```
+/// let mut decoded = Vec::new();
+///
+/// assert_eq!(rle_rs::decode_stream(&[b'a', 3, b'b', 1][..], &mut decoded).unwrap(), 4);
+/// assert_eq!(decoded, b"aaab");
+/// ```
+pub fn decode_stream<R: Read, W: Write>(r: R, mut w: W) -> io::Result<u64> {
+    let written = copy_chunks(RleDecoder::new(r), &mut w)?;
+    w.flush()?;
+
+    Ok(written)
+}
+
+/// Run-length encode everything read from `r` and return the encoding.
+///
+/// The input is read a chunk at a time, so only the encoding is held in
+/// memory.
+///
+/// * `r` - The reader the bytes to be encoded come from.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(rle_rs::encode_from_reader(&b"aaab"[..]).unwrap(), [b'a', 3, b'b', 1]);
+/// ```
+pub fn encode_from_reader<R: Read>(r: R) -> io::Result<Vec<u8>> {
+    let mut encoded = Vec::new();
+    encode_stream(r, &mut encoded)?;
+
+    Ok(encoded)
+}
+
+/// Decode the run-length encoded bytes read from `r` and return the result.
+///
+/// The input is read a chunk at a time, so only the decoded data is held in
+/// memory.
+///
+/// * `r` - The reader the encoded bytes come from.
+///
+/// # Errors
+///
+/// Returns [`RleError::Decode`] if the encoded data is malformed, and
+/// [`RleError::Io`] if reading fails.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(rle_rs::decode_from_reader(&[b'a', 3, b'b', 1][..]).unwrap(), b"aaab");
+/// assert!(rle_rs::decode_from_reader(&[b'a'][..]).is_err());
+/// ```
+pub fn decode_from_reader<R: Read>(r: R) -> Result<Vec<u8>, RleError> {
+    let mut decoded = Vec::new();
+    decode_stream(r, &mut decoded)?;
+
+    Ok(decoded)
+}
+
+/// Run-length encode `bytes` and write the encoding to `w`, without building
+/// the whole encoding in memory first.
+///
+/// * `bytes` - The bytes to be encoded.
+/// * `w` - The writer that receives the encoded bytes.
+///
+/// # Examples
+///
+/// ```
+/// let mut encoded = Vec::new();
+/// rle_rs::encode_to_writer(b"aaab", &mut encoded).unwrap();
+///
+/// assert_eq!(encoded, [b'a', 3, b'b', 1]);
+/// ```
+pub fn encode_to_writer(bytes: &[u8], w: &mut impl Write) -> io::Result<()> {
+    let mut encoder = RleEncoder::new(w);
+
+    for chunk in bytes.chunks(CHUNK_SIZE) {
+        encoder.write_all(chunk)?;
+    }
+
+    encoder.finish()?;
+
+    Ok(())
+}
+
+/// Decode the run-length encoded `bytes` and write the result to `w`,
+/// without building the whole result in memory first.
+///
+/// * `bytes` - The bytes to be decoded.
+/// * `w` - The writer that receives the decoded bytes.
+///
+/// # Errors
+///
+/// Returns [`RleError::Decode`] if `bytes` is malformed, as [`decode`](crate::decode)
+/// reports it, in which case nothing is written, and [`RleError::Io`] if
+/// writing fails.
+///
+/// # Examples
+///
+/// ```
+/// let mut decoded = Vec::new();
+/// rle_rs::decode_to_writer(&[b'a', 3, b'b', 1], &mut decoded).unwrap();
+///
+/// assert_eq!(decoded, b"aaab");
+/// ```
+pub fn decode_to_writer(bytes: &[u8], w: &mut impl Write) -> Result<(), RleError> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err(DecodeError::TruncatedInput { at_byte: bytes.len() - 1 }.into());
+    }
+
+    if let Some(i) = bytes.chunks_exact(2).position(|pair| pair[1] == 0) {
+        return Err(DecodeError::ZeroCount { at_byte: 2 * i }.into());
+    }
+
+    for pair in bytes.chunks_exact(2) {
+        w.write_all(&[pair[0]; 255][..pair[1] as usize])?;
+    }
+
+    Ok(())
+}
+
+/// Copy everything from `r` to `w` in `CHUNK_SIZE`-byte chunks and return the
+/// number of bytes copied.
+fn copy_chunks<R: Read, W: Write>(mut r: R, w: &mut W) -> io::Result<u64> {
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut copied = 0;
+
+    loop {
+        match r.read(&mut buf) {
+            Ok(0) => return Ok(copied),
+            Ok(n) => {
+                w.write_all(&buf[..n])?;
+                copied += n as u64;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// A writer that counts the bytes written through it.
+struct Counter<W: Write> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: Write> Write for Counter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.count += n as u64;
+
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A writer that run-length encodes everything written to it and passes the
+/// encoding on to an inner writer.
+///
+/// Runs may span any number of `write` calls. The last run is only known to
+/// be complete once the input ends, so call [`RleEncoder::finish`] when done.
+/// Dropping the encoder also writes the pending run, but ignores any error
+/// doing so.
+///
+/// The encoder only writes [`EncoderMode::Basic`] pairs, since a literal run
+/// can't be written until it is known where it ends; the mode in the options
+/// it is given is ignored.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use rle_rs::RleEncoder;
+///
+/// let mut encoder = RleEncoder::new(Vec::new());
+/// encoder.write_all(b"aa").unwrap();
+/// encoder.write_all(b"ab").unwrap();
+///
+/// assert_eq!(encoder.finish().unwrap(), [b'a', 3, b'b', 1]);
+/// ```
+pub struct RleEncoder<W: Write> {
+    inner: Option<W>,
+    /// The longest run a single pair may hold.
+    max_run: u8,
+    /// The byte of the current run and how many times it has occurred.
+    run: Option<(u8, u8)>,
+}
+
+impl<W: Write> RleEncoder<W> {
+    /// Create a new encoder writing its output to `inner`.
+    ///
+    /// * `inner` - The writer that receives the encoded bytes.
+    pub fn new(inner: W) -> Self {
+        Self::with_options(inner, &EncodeOptions::default())
+    }
+
+    /// Create a new encoder writing its output to `inner`, as tuned by
+    /// `opts`.
+    ///
+    /// * `inner` - The writer that receives the encoded bytes.
+    /// * `opts` - The options to encode with.
+    pub fn with_options(inner: W, opts: &EncodeOptions) -> Self {
+        Self { inner: Some(inner), max_run: opts.max_run.max(1), run: None }
+    }
+
+    /// Write the pending run and return the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_pending()?;
+
+        Ok(self.inner.take().unwrap())
+    }
+
+    /// Write the pending run, if any, to the inner writer.
+    fn write_pending(&mut self) -> io::Result<()> {
+        if let Some((byte, occurrences)) = self.run.take() {
+            self.inner.as_mut().unwrap().write_all(&[byte, occurrences])?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for RleEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // The pairs of the runs closed by this buffer.
+        let mut encoding = Vec::new();
+
+        for &byte in buf {
+            match self.run {
+                Some((b, ref mut occurrences)) if b == byte && *occurrences < self.max_run => {
+                    *occurrences += 1;
+                }
+                Some((b, occurrences)) => {
+                    encoding.extend(&[b, occurrences]);
+                    self.run = Some((byte, 1));
+                }
+                None => self.run = Some((byte, 1)),
+            }
+        }
+
+        self.inner.as_mut().unwrap().write_all(&encoding)?;
+
+        Ok(buf.len())
+    }
+
+    /// Flush the inner writer. The pending run is kept open, since more input
+    /// may still extend it.
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.as_mut().unwrap().flush()
+    }
+}
+
+impl<W: Write> Drop for RleEncoder<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.write_pending();
+        }
+    }
+}
+
+/// An iterator that run-length encodes the bytes of another iterator.
+///
+/// Each run is read from the inner iterator only when the previous pair has
+/// been consumed, so memory use doesn't depend on the length of the input.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::RunEncoder;
+///
+/// let encoded: Vec<u8> = RunEncoder::new(b"aaab".iter().copied()).collect();
+///
+/// assert_eq!(encoded, [b'a', 3, b'b', 1]);
+/// ```
+pub struct RunEncoder<I: Iterator<Item = u8>> {
+    inner: Fuse<I>,
+    /// The first byte of the next run, read while looking for the end of the
+    /// current one.
+    lookahead: Option<u8>,
+    /// The count of the run whose byte was just yielded.
+    count: Option<u8>,
+}
+
+impl<I: Iterator<Item = u8>> RunEncoder<I> {
+    /// Create a new encoder over the bytes of `inner`.
+    ///
+    /// * `inner` - The iterator over the bytes to be encoded.
+    pub fn new(inner: I) -> Self {
+        Self { inner: inner.fuse(), lookahead: None, count: None }
+    }
+}
+
+impl<I: Iterator<Item = u8>> Iterator for RunEncoder<I> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if let Some(count) = self.count.take() {
+            return Some(count);
+        }
+
+        let byte = self.lookahead.take().or_else(|| self.inner.next())?;
+        let mut occurrences = 1;
+
+        loop {
+            match self.inner.next() {
+                Some(b) if b == byte && occurrences < 255 => occurrences += 1,
+                other => {
+                    self.lookahead = other;
+                    break;
+                }
+            }
+        }
+
+        self.count = Some(occurrences);
+
+        Some(byte)
+    }
+}
+
+/// A reader that decodes run-length encoded data read from an inner reader.
+///
+/// `[byte, count]` pairs are read lazily, so the decoded output never has to
+/// be held in memory as a whole. A pair may be split across any number of
+/// reads of the inner reader.
+///
+/// # Errors
+///
+/// Reading fails with [`io::ErrorKind::InvalidData`], wrapping a
+/// [`DecodeError`], when the encoded data ends in the middle of a pair or a
+/// pair has a count of zero.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Read;
+/// use rle_rs::RleDecoder;
+///
+/// let mut decoded = Vec::new();
+/// RleDecoder::new(&[b'a', 3, b'b', 1][..]).read_to_end(&mut decoded).unwrap();
+///
+/// assert_eq!(decoded, b"aaab");
+/// ```
+pub struct RleDecoder<R: Read> {
+    inner: R,
+    /// Encoded bytes read from `inner` but not decoded yet.
+    buf: Box<[u8]>,
+    start: usize,
+    end: usize,
+    /// How many encoded bytes have been decoded so far.
+    offset: usize,
+    /// The byte of a pair whose count hasn't been read yet.
+    half: Option<u8>,
+    /// The byte of the current run and how many times it is still to be
+    /// repeated.
+    run: (u8, usize),
+}
+
+impl<R: Read> RleDecoder<R> {
+    /// Create a new decoder reading encoded bytes from `inner`.
+    ///
+    /// * `inner` - The reader the encoded bytes come from.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buf: vec![0; CHUNK_SIZE].into_boxed_slice(),
+            start: 0,
+            end: 0,
+            offset: 0,
+            half: None,
+            run: (0, 0),
+        }
+    }
+
+    /// Return the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Read the next encoded byte, or `None` at the end of the input.
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        while self.start == self.end {
+            match self.inner.read(&mut self.buf) {
+                Ok(0) => return Ok(None),
+                Ok(n) => {
+                    self.start = 0;
+                    self.end = n;
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let byte = self.buf[self.start];
+        self.start += 1;
+        self.offset += 1;
+
+        Ok(Some(byte))
+    }
+
+    /// Read the next `[byte, count]` pair, or `None` if the input ends
+    /// cleanly between pairs.
+    fn read_pair(&mut self) -> io::Result<Option<(u8, u8)>> {
+        let byte = match self.half.take() {
+            Some(byte) => byte,
+            None => match self.read_byte()? {
+                Some(byte) => byte,
+                None => return Ok(None),
+            },
+        };
+
+        self.half = Some(byte);
+
+        match self.read_byte()? {
+            Some(0) => {
+                self.half = None;
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    DecodeError::ZeroCount { at_byte: self.offset - 2 },
+                ))
+            }
+            Some(count) => {
+                self.half = None;
+                Ok(Some((byte, count)))
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                DecodeError::TruncatedInput { at_byte: self.offset - 1 },
+            )),
+        }
+    }
+}
+
+impl<R: Read> Read for RleDecoder<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let mut written = 0;
+
+        while written < out.len() {
+            let (byte, remaining) = self.run;
+
+            if remaining > 0 {
+                let n = remaining.min(out.len() - written);
+                out[written..written + n].fill(byte);
+                written += n;
+                self.run.1 -= n;
+                continue;
+            }
+
+            // Don't block on the inner reader if there is something to
+            // return already.
+            if written > 0 && self.end - self.start < 2 {
+                break;
+            }
+
+            match self.read_pair()? {
+                Some((byte, count)) => self.run = (byte, count as usize),
+                None => break,
+            }
+        }
+
+        Ok(written)
+    }
+}
+
+/// A reader that decodes run-length encoded data read from any reader, for
+/// passing around where the type of the reader doesn't matter, such as to
+/// an image parser taking a `Read`: an [`RleDecoder`] over a boxed reader.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Read;
+/// use rle_rs::DecoderReader;
+///
+/// let mut decoded = Vec::new();
+/// DecoderReader::new(Box::new(&[b'a', 3, b'b', 1][..])).read_to_end(&mut decoded).unwrap();
+///
+/// assert_eq!(decoded, b"aaab");
+/// ```
+pub type DecoderReader<'a> = RleDecoder<Box<dyn Read + 'a>>;
+
+/// An iterator that decodes the run-length encoded bytes of another iterator.
+///
+/// Each `[byte, count]` pair is read from the inner iterator and its byte
+/// yielded `count` times before the next pair is read.
+///
+/// # Errors
+///
+/// If the inner iterator ends in the middle of a pair, a
+/// [`DecodeError::TruncatedInput`] is yielded and the iterator ends, as it
+/// does after a [`DecodeError::ZeroCount`] for a pair with a count of zero.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::RunDecoder;
+///
+/// let decoded: Result<Vec<u8>, _> = RunDecoder::new([b'a', 3, b'b', 1].iter().copied()).collect();
+///
+/// assert_eq!(decoded.unwrap(), b"aaab");
+/// ```
+pub struct RunDecoder<I: Iterator<Item = u8>> {
+    inner: Fuse<I>,
+    /// How many encoded bytes have been read so far.
+    offset: usize,
+    /// The byte of the current run and how many times it is still to be
+    /// repeated.
+    run: (u8, u8),
+    /// Whether an error has been yielded, ending the iteration.
+    failed: bool,
+}
+
+impl<I: Iterator<Item = u8>> RunDecoder<I> {
+    /// Create a new decoder over the encoded bytes of `inner`.
+    ///
+    /// * `inner` - The iterator over the bytes to be decoded.
+    pub fn new(inner: I) -> Self {
+        Self { inner: inner.fuse(), offset: 0, run: (0, 0), failed: false }
+    }
+}
+
+impl<I: Iterator<Item = u8>> Iterator for RunDecoder<I> {
+    type Item = Result<u8, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.run.1 == 0 {
+            if self.failed {
+                return None;
+            }
+
+            let byte = self.inner.next()?;
+
+            match self.inner.next() {
+                Some(0) => {
+                    self.failed = true;
+                    return Some(Err(DecodeError::ZeroCount { at_byte: self.offset }));
+                }
+                Some(count) => self.run = (byte, count),
+                None => {
+                    self.failed = true;
+                    return Some(Err(DecodeError::TruncatedInput { at_byte: self.offset }));
+                }
+            }
+
+            self.offset += 2;
+        }
+
+        self.run.1 -= 1;
+
+        Some(Ok(self.run.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode;
+
+    /// Feed `bytes` to an `RleEncoder` in `chunk_size`-byte writes.
+    fn encode_in_chunks(bytes: &[u8], chunk_size: usize) -> Vec<u8> {
+        let mut encoder = RleEncoder::new(Vec::new());
+
+        for chunk in bytes.chunks(chunk_size) {
+            encoder.write_all(chunk).unwrap();
+        }
+
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn empty_input() {
+        assert_eq!(encode_in_chunks(&[], 1), []);
+    }
+
+    #[test]
+    fn runs_spanning_writes() {
+        let bytes = b"aaaabbbcccccccd";
+
+        for chunk_size in 1..bytes.len() {
+            assert_eq!(encode_in_chunks(bytes, chunk_size), encode(bytes));
+        }
+    }
+
+    #[test]
+    fn runs_capped_at_255_across_writes() {
+        let bytes = [5; 700];
+
+        assert_eq!(encode_in_chunks(&bytes, 100), [5, 255, 5, 255, 5, 190]);
+    }
+
+    #[test]
+    fn encode_stream_with_opts_matches_encode_with_opts() {
+        let bytes: Vec<u8> = (0..100).flat_map(|n| vec![n as u8; n]).collect();
+        let opts = EncodeOptions { max_run: 7, ..EncodeOptions::default() };
+        let mut encoded = Vec::new();
+        encode_stream_with_opts(&bytes[..], &mut encoded, &opts).unwrap();
+
+        assert_eq!(encoded, crate::encode_with_opts(&bytes, &opts));
+    }
+
+    #[test]
+    fn encode_stream_with_opts_adaptive() {
+        let bytes = b"abcdddddef";
+        let opts = EncodeOptions { mode: EncoderMode::Adaptive, ..EncodeOptions::default() };
+        let mut encoded = Vec::new();
+
+        assert_eq!(encode_stream_with_opts(&bytes[..], &mut encoded, &opts).unwrap(), 9);
+        assert_eq!(encoded, crate::packbits::encode(bytes));
+    }
+
+    #[test]
+    fn drop_writes_pending_run() {
+        let mut out = Vec::new();
+
+        {
+            let mut encoder = RleEncoder::new(&mut out);
+            encoder.write_all(b"zz").unwrap();
+        }
+
+        assert_eq!(out, [b'z', 2]);
+    }
+
+    /// A reader that returns at most one byte per `read` call.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            Read::take(&mut self.0, 1).read(buf)
+        }
+    }
+
+    #[test]
+    fn rle_decoder_pairs_split_across_reads() {
+        let mut decoded = Vec::new();
+        RleDecoder::new(Trickle(&[b'a', 3, b'b', 200, b'c', 1]))
+            .read_to_end(&mut decoded)
+            .unwrap();
+
+        assert_eq!(decoded, crate::decode(&[b'a', 3, b'b', 200, b'c', 1]).unwrap());
+    }
+
+    #[test]
+    fn rle_decoder_truncated_input() {
+        let e = RleDecoder::new(&[b'a', 3, b'b'][..]).read_to_end(&mut Vec::new()).unwrap_err();
+
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(e.to_string(), DecodeError::TruncatedInput { at_byte: 2 }.to_string());
+    }
+
+    #[test]
+    fn rle_decoder_zero_count() {
+        let mut decoded = Vec::new();
+        let e = RleDecoder::new(Trickle(&[b'a', 3, b'b', 0, b'c', 1])).read_to_end(&mut decoded).unwrap_err();
+
+        assert_eq!(e.to_string(), DecodeError::ZeroCount { at_byte: 2 }.to_string());
+        assert_eq!(decoded, b"aaa");
+    }
+
+    #[test]
+    fn decoder_reader_over_a_boxed_reader() {
+        let encoded = crate::encode(&[7; 1000]);
+        let mut decoder = DecoderReader::new(Box::new(Trickle(&encoded)));
+        let mut decoded = Vec::new();
+        decoder.read_to_end(&mut decoded).unwrap();
+
+        assert_eq!(decoded, [7; 1000]);
+    }
+
+    #[test]
+    fn rle_decoder_eof() {
+        let mut decoder = RleDecoder::new(&[][..]);
+
+        assert_eq!(decoder.read(&mut [0; 4]).unwrap(), 0);
+    }
+
+    #[test]
+    fn run_decoder_matches_decode() {
+        let encoded = [b'a', 3, b'b', 0, b'c', 255];
+
+        assert_eq!(
+            RunDecoder::new(encoded.iter().copied()).collect::<Result<Vec<u8>, _>>(),
+            crate::decode(&encoded)
+        );
+    }
+
+    #[test]
+    fn run_decoder_truncated_input() {
+        let mut decoder = RunDecoder::new([b'a', 1, b'b'].iter().copied());
+
+        assert_eq!(decoder.next(), Some(Ok(b'a')));
+        assert_eq!(decoder.next(), Some(Err(DecodeError::TruncatedInput { at_byte: 2 })));
+        assert_eq!(decoder.next(), None);
+    }
+
+    #[test]
+    fn run_decoder_zero_count() {
+        let mut decoder = RunDecoder::new([b'a', 1, b'b', 0, b'c', 1].iter().copied());
+
+        assert_eq!(decoder.next(), Some(Ok(b'a')));
+        assert_eq!(decoder.next(), Some(Err(DecodeError::ZeroCount { at_byte: 2 })));
+        assert_eq!(decoder.next(), None);
+    }
+
+    #[test]
+    fn streams_larger_than_a_chunk() {
+        // Runs of every length up to 300, so that plenty of them cross the
+        // chunk boundaries.
+        let bytes: Vec<u8> = (0..300).flat_map(|n| vec![n as u8; n]).collect();
+        assert!(bytes.len() > 2 * CHUNK_SIZE);
+
+        let mut encoded = Vec::new();
+        let written = encode_stream(&bytes[..], &mut encoded).unwrap();
+
+        assert_eq!(encoded, encode(&bytes));
+        assert_eq!(written, encoded.len() as u64);
+
+        let mut decoded = Vec::new();
+        let written = decode_stream(Trickle(&encoded), &mut decoded).unwrap();
+
+        assert_eq!(decoded, bytes);
+        assert_eq!(written, bytes.len() as u64);
+    }
+
+    #[test]
+    fn encode_stream_run_crossing_a_chunk_boundary() {
+        let mut bytes = vec![1; CHUNK_SIZE - 10];
+        bytes.extend(&[2; 20]);
+        let mut encoded = Vec::new();
+        encode_stream(&bytes[..], &mut encoded).unwrap();
+
+        assert_eq!(encoded, encode(&bytes));
+        assert_eq!(&encoded[encoded.len() - 2..], [2, 20]);
+    }
+
+    #[test]
+    fn from_reader_pairs_split_across_reads() {
+        let bytes = [vec![4; 2000], vec![5; 3]].concat();
+        let encoded = encode_from_reader(Trickle(&bytes)).unwrap();
+
+        assert_eq!(encoded, crate::encode(&bytes));
+        assert_eq!(decode_from_reader(Trickle(&encoded)).unwrap(), bytes);
+        assert!(matches!(
+            decode_from_reader(Trickle(&encoded[..3])),
+            Err(RleError::Decode(DecodeError::TruncatedInput { at_byte: 2 }))
+        ));
+    }
+
+    #[test]
+    fn to_writer_round_trip() {
+        let bytes: Vec<u8> = (0..300).flat_map(|n| vec![n as u8; n]).collect();
+        let mut encoded = Vec::new();
+        encode_to_writer(&bytes, &mut encoded).unwrap();
+
+        assert_eq!(encoded, encode(&bytes));
+
+        let mut decoded = Vec::new();
+        decode_to_writer(&encoded, &mut decoded).unwrap();
+
+        assert_eq!(decoded, bytes);
+    }
+
+    #[test]
+    fn decode_to_writer_truncated_input_writes_nothing() {
+        let mut decoded = Vec::new();
+        let e = decode_to_writer(&[b'a', 3, b'b'], &mut decoded).unwrap_err();
+
+        assert!(matches!(e, RleError::Decode(DecodeError::TruncatedInput { at_byte: 2 })));
+        assert!(decoded.is_empty());
+
+        let e = decode_to_writer(&[b'a', 3, b'b', 0], &mut decoded).unwrap_err();
+
+        assert!(matches!(e, RleError::Decode(DecodeError::ZeroCount { at_byte: 2 })));
+        assert!(decoded.is_empty());
+    }
+
+    #[test]
+    fn run_encoder_matches_encode() {
+        let bytes: Vec<u8> = [&b"xyyzzz"[..], &[0; 300], b"x"].concat();
+
+        assert_eq!(RunEncoder::new(bytes.iter().copied()).collect::<Vec<u8>>(), encode(&bytes));
+    }
+
+    #[test]
+    fn run_encoder_empty_input() {
+        assert_eq!(RunEncoder::new(std::iter::empty()).next(), None);
+    }
+}
@@ -0,0 +1,374 @@
+//! A human-readable run-length scheme, for teaching and debugging.
+//!
+//! Each run is written as its byte followed by its length in decimal, e.g.
+//! `A11B1C4` for `AAAAAAAAAAABCCCC`. Bytes that aren't printable ASCII, and
+//! digits and backslashes, which would be ambiguous, are written as `\xNN`
+//! escapes. The encoding ends with a newline; newlines between runs are
+//! ignored when decoding, so long encodings can be wrapped.
+//!
+//! [`to_text`] and [`from_text`] instead spell out the pairs of an existing
+//! byte-pair encoding, e.g. `0x41×3 0x20×1`, to paste into issue reports and
+//! tests.
+
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt::Write;
+
+use crate::{DecodeError, DecodeOptions, ParseError};
+
+/// The sign between the byte and the count of a pair written by [`to_text`].
+const TIMES: char = '×';
+
+/// Read a byte slice and return its human-readable run-length encoding.
+///
+/// * `bytes` - The bytes to be encoded.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::text;
+///
+/// assert_eq!(text::encode(b"AAAAAAAAAAABCCCC"), "A11B1C4\n");
+/// assert_eq!(text::encode(b"\n\n7"), "\\x0a2\\x371\n");
+/// ```
+pub fn encode(bytes: &[u8]) -> String {
+    let mut encoding = String::new();
+    let mut iter = bytes.iter().peekable();
+
+    while let Some(&byte) = iter.next() {
+        let mut occurrences: u64 = 1;
+
+        while iter.next_if_eq(&&byte).is_some() {
+            occurrences += 1;
+        }
+
+        if (byte.is_ascii_graphic() || byte == b' ') && !byte.is_ascii_digit() && byte != b'\\' {
+            encoding.push(byte as char);
+        } else {
+            write!(encoding, "\\x{:02x}", byte).unwrap();
+        }
+
+        write!(encoding, "{}", occurrences).unwrap();
+    }
+
+    encoding.push('\n');
+
+    encoding
+}
+
+/// Read a human-readable run-length encoding and return its decoded
+/// contents.
+///
+/// * `bytes` - The text to be decoded.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::InvalidText`], with the line and column of the
+/// problem, if a run has a malformed escape, no count or a count of zero, or
+/// is too long to hold in memory.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::text;
+///
+/// assert_eq!(text::decode(b"A11B1C4\n").unwrap(), b"AAAAAAAAAAABCCCC");
+/// assert!(text::decode(b"A11B").is_err());
+/// ```
+pub fn decode(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    decode_with_opts(bytes, &DecodeOptions::default())
+}
+
+/// Read a human-readable run-length encoding and return its decoded
+/// contents, as limited by `opts`.
+///
+/// * `bytes` - The text to be decoded.
+/// * `opts` - The limits to decode within.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::InvalidText`], with the line and column of the
+/// count, if a run would make the decoded contents longer than `opts.limit`,
+/// and the same errors as [`decode`] otherwise.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::{text, DecodeOptions};
+///
+/// let opts = DecodeOptions { limit: 10 };
+///
+/// assert_eq!(text::decode_with_opts(b"A6B4\n", &opts).unwrap(), b"AAAAAABBBB");
+/// assert!(text::decode_with_opts(b"A6B5\n", &opts).is_err());
+/// ```
+pub fn decode_with_opts(bytes: &[u8], opts: &DecodeOptions) -> Result<Vec<u8>, DecodeError> {
+    let mut decoding = Vec::new();
+    let mut parser = Parser { bytes, i: 0, line: 1, line_start: 0 };
+
+    while let Some(next) = parser.peek() {
+        if next == b'\n' || next == b'\r' {
+            parser.advance();
+            continue;
+        }
+
+        let byte = parser.byte()?;
+        let start = parser.i;
+        let count = parser.count(opts.limit - decoding.len())?;
+
+        // Room for the run is asked for before it's written, so that a run
+        // too long for memory fails instead of aborting.
+        if decoding.try_reserve(count).is_err() {
+            return Err(parser.error(start, "run is too long to hold in memory"));
+        }
+
+        decoding.extend(core::iter::repeat_n(byte, count));
+    }
+
+    Ok(decoding)
+}
+
+/// Return the length of the run, or line break, that `bytes` starts with and
+/// the length of its decoding, without decoding it, or `None` if `bytes`
+/// ends before its count does or it's malformed.
+///
+/// * `bytes` - The text from the start of the run on.
+pub(crate) fn run_len(bytes: &[u8]) -> Option<(usize, u64)> {
+    let start = match *bytes.first()? {
+        b'\n' | b'\r' => return Some((1, 0)),
+        b'\\' => 4,
+        _ => 1,
+    };
+    let digits = bytes.get(start..)?.iter().take_while(|byte| byte.is_ascii_digit()).count();
+
+    if start + digits == bytes.len() {
+        return None;
+    }
+
+    // The digits are ASCII, so they're valid UTF-8.
+    match core::str::from_utf8(&bytes[start..start + digits]).unwrap().parse() {
+        Ok(0) | Err(_) => None,
+        Ok(count) => Some((start + digits, count)),
+    }
+}
+
+/// Write the `[byte, count]` pairs of a byte-pair encoding as text, each as
+/// its byte in hex and its count in decimal, separated by spaces.
+///
+/// A final byte without a count is written on its own, so that any slice
+/// can be written.
+///
+/// * `encoded` - The encoding to be written, as produced by
+///   [`encode`](crate::encode).
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::text;
+///
+/// assert_eq!(text::to_text(&rle_rs::encode(b"AAA BB")), "0x41×3 0x20×1 0x42×2");
+/// ```
+pub fn to_text(encoded: &[u8]) -> String {
+    let mut text = String::new();
+
+    for pair in encoded.chunks(2) {
+        if !text.is_empty() {
+            text.push(' ');
+        }
+
+        write!(text, "0x{:02x}", pair[0]).unwrap();
+
+        if let Some(count) = pair.get(1) {
+            write!(text, "{}{}", TIMES, count).unwrap();
+        }
+    }
+
+    text
+}
+
+/// Read pairs written by [`to_text`] and return the byte-pair encoding they
+/// spell out. Any whitespace may separate the pairs.
+///
+/// * `s` - The text to be read.
+///
+/// # Errors
+///
+/// Returns [`ParseError::InvalidFormat`] with a pair that isn't a byte in hex
+/// and a count, unless it's a byte on its own at the end, and
+/// [`ParseError::InvalidCount`] with a count that isn't a number from 0 to
+/// 255.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::{text, ParseError};
+///
+/// assert_eq!(text::from_text("0x41×3 0x20×1").unwrap(), [0x41, 3, 0x20, 1]);
+/// assert_eq!(text::from_text("0x41×300"), Err(ParseError::InvalidCount("300".into())));
+/// ```
+pub fn from_text(s: &str) -> Result<Vec<u8>, ParseError> {
+    let mut encoded = Vec::new();
+    let mut pairs = s.split_whitespace().peekable();
+
+    while let Some(pair) = pairs.next() {
+        let invalid = || ParseError::InvalidFormat(pair.to_owned());
+        let (byte, count) = match pair.split_once(TIMES) {
+            Some((byte, count)) => (byte, Some(count)),
+            None if pairs.peek().is_none() => (pair, None),
+            None => return Err(invalid()),
+        };
+
+        let digits = byte.strip_prefix("0x").filter(|d| d.len() == 2).ok_or_else(invalid)?;
+        encoded.push(u8::from_str_radix(digits, 16).map_err(|_| invalid())?);
+
+        if let Some(count) = count {
+            let parsed = count.parse::<u64>().ok().and_then(|c| u8::try_from(c).ok());
+            encoded.push(parsed.ok_or_else(|| ParseError::InvalidCount(count.to_owned()))?);
+        }
+    }
+
+    Ok(encoded)
+}
+
+/// The position reached while decoding, kept for error messages.
+struct Parser<'a> {
+    bytes: &'a [u8],
+    i: usize,
+    line: usize,
+    line_start: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.i).copied()
+    }
+
+    fn advance(&mut self) {
+        if self.bytes[self.i] == b'\n' {
+            self.line += 1;
+            self.line_start = self.i + 1;
+        }
+
+        self.i += 1;
+    }
+
+    /// Return an error about the byte at `at`, on the current line.
+    fn error(&self, at: usize, reason: &'static str) -> DecodeError {
+        DecodeError::InvalidText { line: self.line, column: at - self.line_start + 1, reason }
+    }
+
+    /// Read the byte of a run, escaped or not.
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let start = self.i;
+        let byte = self.bytes[start];
+        self.advance();
+
+        if byte != b'\\' {
+            return Ok(byte);
+        }
+
+        let escape = self.bytes.get(start + 1..start + 4).ok_or(self.error(start, "unfinished escape"))?;
+        let hex = core::str::from_utf8(&escape[1..]).ok().filter(|_| escape[0] == b'x');
+
+        match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            Some(byte) => {
+                self.i += 3;
+                Ok(byte)
+            }
+            None => Err(self.error(start, "invalid escape, expected \\xNN")),
+        }
+    }
+
+    /// Read the count of a run, which may be at most `room`.
+    fn count(&mut self, room: usize) -> Result<usize, DecodeError> {
+        let start = self.i;
+
+        while self.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+            self.advance();
+        }
+
+        if start == self.i {
+            return Err(self.error(start, "run has no count"));
+        }
+
+        // The digits are ASCII, so they're valid UTF-8.
+        let digits = core::str::from_utf8(&self.bytes[start..self.i]).unwrap();
+
+        match digits.parse() {
+            Ok(0) => Err(self.error(start, "run has a count of zero")),
+            Ok(count) if count > room => Err(self.error(start, "run makes the decoded data longer than the limit")),
+            Ok(count) => Ok(count),
+            Err(_) => Err(self.error(start, "run has a count too large to decode")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn round_trip() {
+        let bytes = [&b"plain text, with a looooong run"[..], &[0; 300], b"digits 1122 \\ \x1b\xff"].concat();
+
+        assert_eq!(decode(encode(&bytes).as_bytes()), Ok(bytes));
+        assert_eq!(encode(&[]), "\n");
+        assert_eq!(decode(b""), Ok(vec![]));
+    }
+
+    #[test]
+    fn decode_ignores_line_breaks_between_runs() {
+        assert_eq!(decode(b"a2\r\nb3\n\nc1"), Ok(b"aabbbc".to_vec()));
+    }
+
+    #[test]
+    fn decode_malformed_input() {
+        let invalid = |line, column, reason| Err(DecodeError::InvalidText { line, column, reason });
+
+        assert_eq!(decode(b"a2b"), invalid(1, 4, "run has no count"));
+        assert_eq!(decode(b"a2\nbb"), invalid(2, 2, "run has no count"));
+        assert_eq!(decode(b"a2\nb0"), invalid(2, 2, "run has a count of zero"));
+        assert_eq!(decode(b"a99999999999999999999999"), invalid(1, 2, "run has a count too large to decode"));
+        assert_eq!(decode(b"a2\nA999999999999999\n"), invalid(2, 2, "run is too long to hold in memory"));
+        assert_eq!(decode(b"a1\\x4"), invalid(1, 3, "unfinished escape"));
+        assert_eq!(decode(b"a1\\y411"), invalid(1, 3, "invalid escape, expected \\xNN"));
+        assert_eq!(decode(b"a1\\xzz1"), invalid(1, 3, "invalid escape, expected \\xNN"));
+    }
+
+    #[test]
+    fn decode_with_opts_enforces_the_limit() {
+        let opts = DecodeOptions { limit: 10 };
+
+        assert_eq!(decode_with_opts(b"a6\nb4", &opts), Ok(b"aaaaaabbbb".to_vec()));
+        assert_eq!(
+            decode_with_opts(b"a6\nb5", &opts),
+            Err(DecodeError::InvalidText { line: 2, column: 2, reason: "run makes the decoded data longer than the limit" })
+        );
+    }
+
+    #[test]
+    fn pairs_as_text_round_trip() {
+        let encoded = crate::encode(&[vec![0; 300], b"\n\xff".to_vec()].concat());
+        let text = to_text(&encoded);
+
+        assert_eq!(text, "0x00×255 0x00×45 0x0a×1 0xff×1");
+        assert_eq!(from_text(&text), Ok(encoded));
+        assert_eq!(from_text(" 0x41×3\n\t0x42×1 0x43"), Ok(vec![0x41, 3, 0x42, 1, 0x43]));
+        assert_eq!(to_text(&[0x41, 3, 0x43]), "0x41×3 0x43");
+        assert_eq!(from_text(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn from_text_malformed_pairs() {
+        let invalid_format = |s: &str| Err(ParseError::InvalidFormat(s.into()));
+
+        assert_eq!(from_text("0x41 0x42×1"), invalid_format("0x41"));
+        assert_eq!(from_text("41×1"), invalid_format("41×1"));
+        assert_eq!(from_text("0x4×1"), invalid_format("0x4×1"));
+        assert_eq!(from_text("0xzz×1"), invalid_format("0xzz×1"));
+        assert_eq!(from_text("0x41×-1"), Err(ParseError::InvalidCount("-1".into())));
+        assert_eq!(from_text("0x41×"), Err(ParseError::InvalidCount("".into())));
+    }
+}
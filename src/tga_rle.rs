@@ -0,0 +1,216 @@
+//! The run-length compression of Truevision TGA image files.
+//!
+//! The data is a sequence of packets of 1 to 128 pixels, each pixel being 1
+//! to 4 bytes long. A packet starts with a byte whose top bit is set for a
+//! run packet, followed by a pixel repeated as many times as the bottom 7
+//! bits plus 1, and clear for a raw packet, followed by as many pixels as
+//! the bottom 7 bits plus 1.
+//!
+//! The specification advises against packets crossing scan lines, so to
+//! produce the most compatible files, encode every line separately and join
+//! the results.
+
+use alloc::vec::Vec;
+
+use crate::DecodeError;
+
+/// The bit marking a run packet.
+const RUN_FLAG: u8 = 0x80;
+
+/// The most pixels a packet can hold.
+const MAX_PACKET: usize = 128;
+
+/// Read the pixels in a byte slice and return their TGA encoding.
+///
+/// * `bytes` - The pixels to be encoded.
+/// * `pixel_depth` - The size of a pixel, in bytes: 1 for grayscale or
+///   color-mapped images, 2 or 3 for true-color ones, and 4 for true-color
+///   ones with alpha.
+///
+/// # Panics
+///
+/// Panics if `pixel_depth` isn't from 1 to 4, or the length of `bytes` isn't
+/// a multiple of it.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::tga_rle;
+///
+/// let pixels = [1, 2, 1, 2, 1, 2, 3, 4, 5, 6];
+///
+/// assert_eq!(tga_rle::encode(&pixels, 2), [0x82, 1, 2, 0x01, 3, 4, 5, 6]);
+/// ```
+pub fn encode(bytes: &[u8], pixel_depth: usize) -> Vec<u8> {
+    check_depth(pixel_depth);
+    assert!(bytes.len().is_multiple_of(pixel_depth), "the data must be made of whole pixels");
+
+    let pixels: Vec<&[u8]> = bytes.chunks_exact(pixel_depth).collect();
+    let mut encoding = Vec::with_capacity(bytes.len() + bytes.len() / pixel_depth / MAX_PACKET + 1);
+    let mut i = 0;
+
+    while i < pixels.len() {
+        let run = run_len(&pixels[i..]);
+
+        if run > 1 {
+            encoding.push(RUN_FLAG | (run - 1) as u8);
+            encoding.extend(pixels[i]);
+            i += run;
+            continue;
+        }
+
+        // Gather pixels up to the next run.
+        let mut j = i + 1;
+
+        while j < pixels.len() && j - i < MAX_PACKET && run_len(&pixels[j..]) == 1 {
+            j += 1;
+        }
+
+        encoding.push((j - i - 1) as u8);
+        encoding.extend(pixels[i..j].concat());
+        i = j;
+    }
+
+    encoding
+}
+
+/// Read a TGA encoding and return the pixels it holds.
+///
+/// * `bytes` - The bytes to be decoded.
+/// * `pixel_depth` - The size of a pixel, in bytes, from 1 to 4.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::TruncatedInput`] if the input ends in the middle of
+/// a packet.
+///
+/// # Panics
+///
+/// Panics if `pixel_depth` isn't from 1 to 4.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::tga_rle;
+///
+/// let encoded = [0x82, 1, 2, 0x01, 3, 4, 5, 6];
+///
+/// assert_eq!(tga_rle::decode(&encoded, 2).unwrap(), [1, 2, 1, 2, 1, 2, 3, 4, 5, 6]);
+/// assert!(tga_rle::decode(&encoded[..7], 2).is_err());
+/// ```
+pub fn decode(bytes: &[u8], pixel_depth: usize) -> Result<Vec<u8>, DecodeError> {
+    check_depth(pixel_depth);
+
+    let mut decoding = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let header = bytes[i];
+        let count = (header & !RUN_FLAG) as usize + 1;
+        let truncated = DecodeError::TruncatedInput { at_byte: i };
+        i += 1;
+
+        if header & RUN_FLAG != 0 {
+            let pixel = bytes.get(i..i + pixel_depth).ok_or(truncated)?;
+
+            for _ in 0..count {
+                decoding.extend(pixel);
+            }
+
+            i += pixel_depth;
+        } else {
+            let len = count * pixel_depth;
+            decoding.extend(bytes.get(i..i + len).ok_or(truncated)?);
+            i += len;
+        }
+    }
+
+    Ok(decoding)
+}
+
+fn check_depth(pixel_depth: usize) {
+    assert!((1..=4).contains(&pixel_depth), "a pixel must be 1 to 4 bytes long");
+}
+
+/// Return the number of pixels the same as the first of `pixels`, up to the
+/// most a packet can hold.
+fn run_len(pixels: &[&[u8]]) -> usize {
+    pixels.iter().take(MAX_PACKET).take_while(|&&pixel| pixel == pixels[0]).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// Return a TGA file of a `width` by `height` image, with `pixel_depth`
+    /// bytes per pixel and the encoding `data`, stored top row first.
+    fn tga_file(data: &[u8], width: u16, height: u16, pixel_depth: usize) -> Vec<u8> {
+        // Run-length encoded grayscale or true-color.
+        let image_type = if pixel_depth == 1 { 11 } else { 10 };
+        let alpha_bits = if pixel_depth == 4 { 8 } else { 0 };
+        let mut file = vec![0, 0, image_type, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+        file.extend(&width.to_le_bytes());
+        file.extend(&height.to_le_bytes());
+        file.extend(&[pixel_depth as u8 * 8, 0x20 | alpha_bits]);
+        file.extend(data);
+
+        file
+    }
+
+    /// Decode a TGA file with an independent implementation and return its
+    /// pixels as they're stored, blue first.
+    fn decode_with_image(file: &[u8], pixel_depth: usize) -> Vec<u8> {
+        let image = image::load_from_memory_with_format(file, image::ImageFormat::Tga).unwrap();
+
+        match pixel_depth {
+            1 => image.to_luma8().into_raw(),
+            3 => image.to_rgb8().pixels().flat_map(|p| [p[2], p[1], p[0]]).collect(),
+            _ => image.to_rgba8().pixels().flat_map(|p| [p[2], p[1], p[0], p[3]]).collect(),
+        }
+    }
+
+    /// Return the pixels of a `width` by `height` test image, with runs and
+    /// noise in every row.
+    fn pixels(width: usize, height: usize, pixel_depth: usize) -> Vec<u8> {
+        (0..width * height)
+            .flat_map(|i| {
+                let x = i % width;
+                let value = if x < 150 { (i / width * 40) as u8 } else { (i * 37 % 253) as u8 };
+
+                (0..pixel_depth as u8).map(move |c| value.wrapping_add(c * 60))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn encoding_matches_image_decoder() {
+        for &pixel_depth in &[1, 3, 4] {
+            let (width, height) = (200, 5);
+            let pixels = pixels(width, height, pixel_depth);
+            let encoded = encode(&pixels, pixel_depth);
+            let file = tga_file(&encoded, width as u16, height as u16, pixel_depth);
+
+            assert_eq!(decode(&encoded, pixel_depth), Ok(pixels.clone()));
+            assert_eq!(decode_with_image(&file, pixel_depth), pixels, "{} bytes per pixel", pixel_depth);
+        }
+    }
+
+    #[test]
+    fn packets_hold_at_most_128_pixels() {
+        assert_eq!(encode(&[9; 300], 1), [0xff, 9, 0xff, 9, 0xab, 9]);
+
+        let noise: Vec<u8> = (0..130).collect();
+        let encoded = encode(&noise, 1);
+
+        assert_eq!((encoded[0], encoded[129]), (127, 1));
+        assert_eq!(decode(&encoded, 1), Ok(noise));
+    }
+
+    #[test]
+    fn decode_malformed_input() {
+        assert_eq!(decode(&[0x81, 1, 2], 3), Err(DecodeError::TruncatedInput { at_byte: 0 }));
+        assert_eq!(decode(&[0x00, 1, 0x01, 2], 1), Err(DecodeError::TruncatedInput { at_byte: 2 }));
+    }
+}
@@ -0,0 +1,134 @@
+//! A run-length scheme for data made of multi-byte units, such as 16-bit
+//! audio samples or 32-bit pixels, whose runs a byte at a time would miss.
+//!
+//! Each run is stored as its unit followed by a count, as in the byte-pair
+//! format. If the length of the data isn't a multiple of the unit size, the
+//! bytes left over at the end are stored as they are after the last run;
+//! they can be told apart because they're shorter than a run.
+
+use alloc::vec::Vec;
+
+use crate::DecodeError;
+
+/// Read a byte slice and return its run-length encoding in `unit`-byte
+/// units.
+///
+/// * `bytes` - The bytes to be encoded.
+/// * `unit` - The size of a unit, in bytes.
+///
+/// # Panics
+///
+/// Panics if `unit` is 0.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::units;
+///
+/// assert_eq!(units::encode(&[1, 2, 1, 2, 1, 2, 3], 2), [1, 2, 3, 3]);
+/// ```
+pub fn encode(bytes: &[u8], unit: usize) -> Vec<u8> {
+    assert!(unit > 0, "the unit size must be at least 1");
+
+    let mut encoding = Vec::new();
+    let units = bytes.chunks_exact(unit);
+    let tail = units.remainder();
+    let mut iter = units.peekable();
+
+    while let Some(current) = iter.next() {
+        let mut occurrences: u8 = 1;
+
+        while occurrences < u8::MAX && iter.next_if_eq(&current).is_some() {
+            occurrences += 1;
+        }
+
+        encoding.extend(current);
+        encoding.push(occurrences);
+    }
+
+    encoding.extend(tail);
+
+    encoding
+}
+
+/// Read a run-length encoding in `unit`-byte units and return its decoded
+/// contents.
+///
+/// * `bytes` - The bytes to be decoded.
+/// * `unit` - The size of a unit, in bytes.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::TruncatedInput`] if the input ends with a unit that
+/// has no count.
+///
+/// # Panics
+///
+/// Panics if `unit` is 0.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::units;
+///
+/// assert_eq!(units::decode(&[1, 2, 3, 3], 2).unwrap(), [1, 2, 1, 2, 1, 2, 3]);
+/// assert!(units::decode(&[1, 2], 2).is_err());
+/// ```
+pub fn decode(bytes: &[u8], unit: usize) -> Result<Vec<u8>, DecodeError> {
+    assert!(unit > 0, "the unit size must be at least 1");
+
+    let runs = bytes.chunks_exact(unit + 1);
+    let tail = runs.remainder();
+
+    if tail.len() == unit {
+        return Err(DecodeError::TruncatedInput { at_byte: bytes.len() - tail.len() });
+    }
+
+    let mut decoding = Vec::new();
+
+    for run in runs {
+        let (current, count) = run.split_at(unit);
+
+        for _ in 0..count[0] {
+            decoding.extend(current);
+        }
+    }
+
+    decoding.extend(tail);
+
+    Ok(decoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn runs_of_units() {
+        let pixels = [[9, 9, 9, 255]; 300].concat();
+
+        assert_eq!(encode(&pixels, 4), [9, 9, 9, 255, 255, 9, 9, 9, 255, 45]);
+        assert_eq!(decode(&encode(&pixels, 4), 4), Ok(pixels));
+    }
+
+    #[test]
+    fn byte_runs_across_units_are_not_runs() {
+        assert_eq!(encode(&[0, 0, 0, 1], 2), [0, 0, 1, 0, 1, 1]);
+    }
+
+    #[test]
+    fn tail_is_stored_as_it_is() {
+        let bytes = [1, 2, 3, 4, 1, 2, 3, 4, 5, 6, 7];
+
+        assert_eq!(encode(&bytes, 4), [1, 2, 3, 4, 2, 5, 6, 7]);
+        assert_eq!(decode(&encode(&bytes, 4), 4), Ok(bytes.to_vec()));
+        assert_eq!(encode(&[5], 2), [5]);
+        assert_eq!(decode(&[5], 2), Ok(vec![5]));
+    }
+
+    #[test]
+    fn decode_unit_without_count_is_an_error() {
+        assert_eq!(decode(&[1, 2, 1, 3, 4], 2), Err(DecodeError::TruncatedInput { at_byte: 3 }));
+    }
+}
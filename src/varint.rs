@@ -0,0 +1,310 @@
+//! A run-length scheme with variable-length counts, suited to data with very
+//! long runs, such as sparse disk images.
+//!
+//! Each run is stored as its byte followed by its length as an unsigned
+//! LEB128 varint: 7 bits per byte, least significant first, with the top bit
+//! set on every byte but the last. Runs are never split, so a run of any
+//! length takes a single pair, and runs shorter than 128 bytes take 2 bytes
+//! as in the byte-pair format.
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+#[cfg(feature = "std")]
+use std::io::Write;
+
+use crate::{DecodeError, DecodeOptions};
+#[cfg(feature = "std")]
+use crate::RleError;
+
+/// The most bytes a count can take, enough for any `u64`.
+const MAX_COUNT_LEN: usize = 10;
+
+/// The length of the buffer runs are written from by [`decode_to_writer`].
+#[cfg(feature = "std")]
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Read a byte slice and return its encoding with varint counts.
+///
+/// * `bytes` - The bytes to be encoded.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::varint;
+///
+/// assert_eq!(varint::encode(b"aaab"), [b'a', 3, b'b', 1]);
+/// assert_eq!(varint::encode(&[0; 300]), [0, 0xac, 0x02]);
+/// ```
+pub fn encode(bytes: &[u8]) -> Vec<u8> {
+    let mut encoding = Vec::new();
+    let mut iter = bytes.iter().peekable();
+
+    while let Some(&byte) = iter.next() {
+        let mut occurrences: u64 = 1;
+
+        while iter.next_if_eq(&&byte).is_some() {
+            occurrences += 1;
+        }
+
+        encoding.push(byte);
+        push_count(&mut encoding, occurrences);
+    }
+
+    encoding
+}
+
+/// Read an encoding with varint counts and return its decoded contents.
+///
+/// The decoded data is built in memory, so a small malicious input can ask
+/// for more than there is, which fails instead of being decoded; use
+/// [`decode_with_opts`] to set a lower limit, or [`decode_to_writer`] to
+/// decode data of any length.
+///
+/// * `bytes` - The bytes to be decoded, as produced by [`encode`].
+///
+/// # Errors
+///
+/// Returns [`DecodeError::TruncatedInput`] if the input ends in the middle of
+/// a run, [`DecodeError::ZeroCount`] if a run is empty, and
+/// [`DecodeError::CountOverflow`] if a count doesn't fit in a `u64`, or the
+/// decoded data in memory.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::varint;
+///
+/// assert_eq!(varint::decode(&[b'a', 3, b'b', 1]).unwrap(), b"aaab");
+/// assert!(varint::decode(&[b'a', 0x80]).is_err());
+/// ```
+pub fn decode(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    decode_with_opts(bytes, &DecodeOptions::default())
+}
+
+/// Read an encoding with varint counts and return its decoded contents, as
+/// limited by `opts`.
+///
+/// * `bytes` - The bytes to be decoded, as produced by [`encode`].
+/// * `opts` - The limits to decode within.
+///
+/// # Errors
+///
+/// Returns [`DecodeError::OutputTooLarge`] if the decoded contents would be
+/// longer than `opts.limit`, which is found before any of them is decoded,
+/// and the same errors as [`decode`] otherwise.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::{varint, DecodeError, DecodeOptions};
+///
+/// let opts = DecodeOptions { limit: 1000 };
+///
+/// assert_eq!(varint::decode_with_opts(&[0, 0xe8, 0x07], &opts).unwrap(), [0; 1000]);
+/// assert_eq!(
+///     varint::decode_with_opts(&[0, 0xe9, 0x07], &opts),
+///     Err(DecodeError::OutputTooLarge { limit: 1000 })
+/// );
+/// ```
+pub fn decode_with_opts(bytes: &[u8], opts: &DecodeOptions) -> Result<Vec<u8>, DecodeError> {
+    let runs = parse(bytes)?;
+    let len = runs.iter().try_fold(0u64, |len, &(_, _, count)| len.checked_add(count));
+
+    if len.is_none_or(|len| len > opts.limit as u64) {
+        return Err(DecodeError::OutputTooLarge { limit: opts.limit });
+    }
+
+    let mut decoding = Vec::new();
+
+    for (at_byte, byte, count) in runs {
+        // Room for each run is asked for before it's written, so that data
+        // too large for memory fails instead of aborting.
+        let count = usize::try_from(count)
+            .ok()
+            .filter(|&count| decoding.try_reserve(count).is_ok())
+            .ok_or(DecodeError::CountOverflow { at_byte })?;
+        decoding.extend(core::iter::repeat_n(byte, count));
+    }
+
+    Ok(decoding)
+}
+
+/// Decode an encoding with varint counts and write the result to `w`, a
+/// chunk at a time, so that huge runs never have to fit in memory.
+///
+/// * `bytes` - The bytes to be decoded, as produced by [`encode`].
+/// * `w` - The writer that receives the decoded bytes.
+///
+/// # Errors
+///
+/// Returns [`RleError::Decode`] for the same reasons as [`decode`], in which
+/// case nothing is written, and [`RleError::Io`] if writing fails.
+///
+/// # Examples
+///
+/// ```
+/// use rle_rs::varint;
+///
+/// let mut decoded = Vec::new();
+/// varint::decode_to_writer(&[0, 0xac, 0x02], &mut decoded).unwrap();
+///
+/// assert_eq!(decoded, [0; 300]);
+/// ```
+#[cfg(feature = "std")]
+pub fn decode_to_writer(bytes: &[u8], w: &mut impl Write) -> Result<(), RleError> {
+    for (_, byte, mut count) in parse(bytes)? {
+        let chunk = [byte; CHUNK_SIZE];
+
+        while count > 0 {
+            let len = count.min(CHUNK_SIZE as u64);
+            w.write_all(&chunk[..len as usize])?;
+            count -= len;
+        }
+    }
+
+    Ok(())
+}
+
+/// Append `count` to `encoding` as a varint.
+pub(crate) fn push_count(encoding: &mut Vec<u8>, mut count: u64) {
+    while count >= 0x80 {
+        encoding.push(count as u8 | 0x80);
+        count >>= 7;
+    }
+
+    encoding.push(count as u8);
+}
+
+/// Read the varint starting at `bytes[i]` and return it along with the offset
+/// of the byte after it.
+///
+/// * `bytes` - The encoded data.
+/// * `i` - The offset of the varint.
+/// * `at_byte` - The offset of the run the varint is the count of, for errors.
+pub(crate) fn read_count(
+    bytes: &[u8], mut i: usize, at_byte: usize
+) -> Result<(u64, usize), DecodeError> {
+    let mut count: u64 = 0;
+    let mut shift = 0;
+
+    loop {
+        let next = *bytes.get(i).ok_or(DecodeError::TruncatedInput { at_byte })?;
+        i += 1;
+
+        if shift / 7 == MAX_COUNT_LEN - 1 && next > 1 {
+            return Err(DecodeError::CountOverflow { at_byte });
+        }
+
+        count |= u64::from(next & 0x7f) << shift;
+        shift += 7;
+
+        if next & 0x80 == 0 {
+            return Ok((count, i));
+        }
+    }
+}
+
+/// Return the offset, byte and count of every run in `bytes`, checking them
+/// all before anything is decoded.
+fn parse(bytes: &[u8]) -> Result<Vec<(usize, u8, u64)>, DecodeError> {
+    let mut runs = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let (count, next) = read_count(bytes, i + 1, i)?;
+
+        if count == 0 {
+            return Err(DecodeError::ZeroCount { at_byte: i });
+        }
+
+        runs.push((i, bytes[i], count));
+        i = next;
+    }
+
+    Ok(runs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn long_runs_take_one_pair() {
+        assert_eq!(encode(&[7; 127]), [7, 127]);
+        assert_eq!(encode(&[7; 128]), [7, 0x80, 0x01]);
+        assert_eq!(encode(&vec![0; 10_000_000]), [0, 0x80, 0xad, 0xe2, 0x04]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = [vec![1; 5], vec![2; 70_000], vec![3], vec![1; 300]].concat();
+
+        assert_eq!(decode(&encode(&bytes)), Ok(bytes));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn decode_to_writer_round_trip() {
+        let bytes = [vec![1; 5], vec![2; 70_000], vec![3], vec![1; 300]].concat();
+        let mut decoded = Vec::new();
+
+        decode_to_writer(&encode(&bytes), &mut decoded).unwrap();
+        assert_eq!(decoded, bytes);
+    }
+
+    #[test]
+    fn largest_count() {
+        let mut encoded = vec![0];
+        push_count(&mut encoded, u64::MAX);
+
+        assert_eq!(encoded.len(), 1 + MAX_COUNT_LEN);
+        assert_eq!(parse(&encoded), Ok(vec![(0, 0, u64::MAX)]));
+
+        // No memory holds the run, which fails instead of aborting.
+        assert_eq!(decode(&encoded), Err(DecodeError::CountOverflow { at_byte: 0 }));
+        assert_eq!(
+            decode_with_opts(&encoded, &DecodeOptions { limit: 1 << 20 }),
+            Err(DecodeError::OutputTooLarge { limit: 1 << 20 })
+        );
+    }
+
+    #[test]
+    fn decode_with_opts_enforces_the_limit() {
+        let encoded = encode(&[&[1; 600][..], &[2; 400]].concat());
+        let opts = |limit| DecodeOptions { limit };
+
+        assert_eq!(decode_with_opts(&encoded, &opts(1000)), decode(&encoded));
+        assert_eq!(decode_with_opts(&encoded, &opts(999)), Err(DecodeError::OutputTooLarge { limit: 999 }));
+
+        // The runs add up to more than a u64 holds.
+        let mut encoded = vec![0];
+        push_count(&mut encoded, u64::MAX);
+        encoded.push(1);
+        push_count(&mut encoded, 1);
+
+        assert_eq!(decode(&encoded), Err(DecodeError::OutputTooLarge { limit: usize::MAX }));
+    }
+
+    #[test]
+    fn decode_malformed_input() {
+        assert_eq!(decode(b"a"), Err(DecodeError::TruncatedInput { at_byte: 0 }));
+        assert_eq!(decode(&[b'a', 1, b'b', 0x80]), Err(DecodeError::TruncatedInput { at_byte: 2 }));
+        assert_eq!(decode(&[b'a', 0]), Err(DecodeError::ZeroCount { at_byte: 0 }));
+
+        let too_large = [&[b'a'][..], &[0xff; MAX_COUNT_LEN - 1], &[2]].concat();
+        assert_eq!(decode(&too_large), Err(DecodeError::CountOverflow { at_byte: 0 }));
+
+        let too_long = [&[b'a'][..], &[0x80; MAX_COUNT_LEN], &[1]].concat();
+        assert_eq!(decode(&too_long), Err(DecodeError::CountOverflow { at_byte: 0 }));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn decode_to_writer_malformed_input_writes_nothing() {
+        let mut decoded = Vec::new();
+
+        assert!(decode_to_writer(&[b'a', 3, b'b'], &mut decoded).is_err());
+        assert!(decoded.is_empty());
+    }
+}
@@ -0,0 +1,25 @@
+//! JavaScript bindings to the codec, built with the `wasm` feature, for use
+//! with `wasm-bindgen`, as a `cdylib`.
+
+use js_sys::Uint8Array;
+use wasm_bindgen::prelude::*;
+
+/// Run-length encode `input`, as [`encode`](crate::encode) does.
+///
+/// * `input` - The bytes to be encoded.
+#[wasm_bindgen(js_name = encode)]
+pub fn encode(input: &Uint8Array) -> Uint8Array {
+    Uint8Array::from(&crate::encode(&input.to_vec())[..])
+}
+
+/// Decode `input`, as [`decode`](crate::decode) does.
+///
+/// * `input` - The bytes to be decoded.
+///
+/// # Errors
+///
+/// Throws an `Error` describing the problem if `input` is malformed.
+#[wasm_bindgen(js_name = decode)]
+pub fn decode(input: &Uint8Array) -> Result<Uint8Array, JsError> {
+    Ok(Uint8Array::from(&crate::decode(&input.to_vec())?[..]))
+}
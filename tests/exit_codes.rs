@@ -0,0 +1,106 @@
+//! The exit status of the program for each kind of failure.
+
+use std::env;
+use std::fs;
+use std::process::{self, Command};
+
+use rle_rs::Format;
+
+/// Write `bytes` to a fresh file in the temporary directory and return its
+/// path.
+///
+/// * `name` - A name unique to the calling test.
+/// * `bytes` - The contents of the file.
+fn temp_file(name: &str, bytes: &[u8]) -> String {
+    let path = env::temp_dir().join(format!("rle-rs-exit-{}-{}", process::id(), name));
+    fs::write(&path, bytes).unwrap();
+
+    path.to_str().unwrap().to_owned()
+}
+
+/// Run the program with `args` and return its exit status.
+///
+/// * `args` - The command-line arguments, without the program's name.
+fn status(args: &[&str]) -> i32 {
+    Command::new(env!("CARGO_BIN_EXE_rle-rs"))
+        .args(args)
+        .output()
+        .unwrap()
+        .status
+        .code()
+        .unwrap()
+}
+
+#[test]
+fn success_is_0() {
+    let path = temp_file("ok", b"xxxy");
+
+    assert_eq!(status(&["-f", &path]), 0);
+    assert_eq!(status(&["--help"]), 0);
+}
+
+#[test]
+fn malformed_data_is_1() {
+    let path = temp_file("malformed.rle", b"not encoded");
+
+    assert_eq!(status(&["-t", &path]), 1);
+}
+
+#[test]
+fn invalid_arguments_are_2() {
+    assert_eq!(status(&[]), 2);
+    assert_eq!(status(&["--no-such-option", "file"]), 2);
+    assert_eq!(status(&["-c", "-o", "out", "file"]), 2);
+}
+
+#[test]
+fn unreadable_input_is_3() {
+    let path = env::temp_dir().join(format!("rle-rs-exit-{}-missing", process::id()));
+
+    assert_eq!(status(&[path.to_str().unwrap()]), 3);
+}
+
+#[test]
+fn checksum_mismatch_is_4() {
+    let mut encoded = rle_rs::encode_with_header(b"xxxy", Format::Byte);
+    // The first byte of the checksum, at the start of the trailer.
+    let crc = encoded.len() - rle_rs::Trailer::LEN;
+    encoded[crc] ^= 0xff;
+    let path = temp_file("corrupt.rle", &encoded);
+
+    assert_eq!(status(&["-t", &path]), 4);
+}
+
+#[test]
+fn trailing_data_is_1() {
+    let encoded = [&rle_rs::encode_with_header(b"xxxy", Format::Byte)[..], b"\0\0\0\0garbage"].concat();
+    let path = temp_file("trailing.rle", &encoded);
+    let output = Command::new(env!("CARGO_BIN_EXE_rle-rs")).args(["-t", &path]).output().unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("trailing data at offset 23 is not an encoded file"));
+    assert_eq!(status(&["-t", "--lenient", &path]), 0);
+}
+
+#[test]
+fn length_mismatch_of_a_long_run_is_4() {
+    // A run of 2^53 bytes, followed by a trailer recording 1, which must be
+    // found not to match without decoding the run.
+    let run = [b'a', 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x0f];
+    let trailer = rle_rs::Trailer { crc: 0, len: 1 }.to_bytes();
+    let encoded = [&rle_rs::Header::new(Format::Varint).to_bytes()[..], &run, &trailer].concat();
+    let path = temp_file("long-run.rle", &encoded);
+
+    assert_eq!(status(&["-t", &path]), 4);
+    assert_eq!(status(&["-d", "-c", &path]), 4);
+}
+
+#[test]
+fn partial_success_is_5() {
+    let path = temp_file("partial", b"xxxy");
+    let missing = env::temp_dir().join(format!("rle-rs-exit-{}-partial-missing", process::id()));
+
+    assert_eq!(status(&["-f", &path, missing.to_str().unwrap()]), 5);
+    // Only files that failed, whatever their order.
+    assert_eq!(status(&["-f", missing.to_str().unwrap(), missing.to_str().unwrap()]), 3);
+}